@@ -0,0 +1,98 @@
+//! Shrinking arena: in survival mode the outermost ring of open floor turns
+//! into wall every so often.
+
+use bevy::prelude::*;
+
+use crate::food::Food;
+use crate::powerups::PowerUp;
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::walls::{spawn_wall, Wall};
+use crate::{snake_movement, Grid, Position};
+
+/// Seconds of play between the arena closing in by one ring.
+pub const SHRINK_INTERVAL: f32 = 30.0;
+/// Rings that can close before the arena stops shrinking, leaving a 6x6
+/// space in the middle of the board.
+const MAX_RINGS: i32 = 7;
+
+pub struct Arena {
+    pub timer: Timer,
+    /// Rings closed so far this run.
+    rings: i32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(SHRINK_INTERVAL, true),
+            rings: 0,
+        }
+    }
+}
+
+impl Arena {
+    /// Whether the arena will close in again.
+    pub fn is_shrinking(&self) -> bool {
+        self.rings < MAX_RINGS
+    }
+}
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Arena>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_arena))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(shrink_arena.after(snake_movement)),
+            );
+    }
+}
+
+fn reset_arena(mut arena: ResMut<Arena>) {
+    *arena = Arena::default();
+}
+
+/// How many cells `pos` is in from the nearest edge of the board.
+fn ring(grid: Grid, pos: Position) -> i32 {
+    pos.x
+        .min(pos.y)
+        .min(grid.width as i32 - 1 - pos.x)
+        .min(grid.height as i32 - 1 - pos.y)
+}
+
+fn shrink_arena(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    rules: Res<GameRules>,
+    mut arena: ResMut<Arena>,
+    grid: Res<Grid>,
+    walls: Query<&Position, With<Wall>>,
+    pickups: Query<(Entity, &Position), Or<(With<Food>, With<PowerUp>)>>,
+) {
+    if rules.mode != GameMode::Survival || !arena.is_shrinking() {
+        return;
+    }
+    if !arena.timer.tick(move_timer.step()).just_finished() {
+        return;
+    }
+    let closing = arena.rings;
+    arena.rings += 1;
+    let walled: Vec<Position> = walls.iter().copied().collect();
+    for pos in grid.cells() {
+        if ring(*grid, pos) == closing && !walled.contains(&pos) {
+            spawn_wall(&mut commands, pos);
+        }
+    }
+    // Anything left in the closed ring would be out of reach. Replacement
+    // food spawns on the free cells that remain.
+    for (entt, pos) in pickups.iter() {
+        if ring(*grid, *pos) <= closing {
+            commands.entity(entt).despawn();
+        }
+    }
+}
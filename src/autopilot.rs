@@ -0,0 +1,450 @@
+//! Computer control for a player's snake, toggled with F1 and used by the
+//! attract-mode demo. It replans every tick and draws the path it's on.
+//!
+//! F2 switches between strategies: a search from the head to the closest
+//! reachable food, with walls as obstacles and snakes in the way until their
+//! tails have passed, using any of the algorithms in `pathfinding`, or a
+//! Hamiltonian cycle through every cell that can fill the whole board. How
+//! many cells each algorithm would explore from the current position is
+//! shown on the HUD for comparison.
+//!
+//! F3 switches the heuristic A* and greedy best-first use, and F4/F5 lower
+//! and raise its weight. The cells the current search explored are shaded so
+//! the effect shows on the board.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::demo::Demo;
+use crate::food::{Food, FoodKind};
+use crate::pathfinding::{Algorithm, Board, SearchOptions};
+use crate::portals::Portal;
+use crate::replay::Playback;
+use crate::rules::GameRules;
+use crate::sequence::{FoodNumber, Sequence};
+use crate::state::{take_just_pressed, GameState};
+use crate::terrain::Terrain;
+use crate::tick::MovementTick;
+use crate::walls::Wall;
+use crate::{
+    cell_center, snake_movement, Direction, Grid, Player, Position, Size, SnakeHead, SnakeSegments,
+};
+
+const PATH_COLOR: Color = Color::WHITE;
+const EXPLORED_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.08);
+/// Path markers sit on the floor under the snake, above portals.
+const PATH_Z: f32 = -0.02;
+/// Explored cells are shaded under the path.
+const EXPLORED_Z: f32 = -0.03;
+/// Cells kept free between the head and the tail when taking a shortcut
+/// off the Hamiltonian cycle, enough for the biggest growth from one food.
+const SHORTCUT_BUFFER: usize = 4;
+
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Right,
+    Direction::Down,
+];
+
+/// Steers the snake it's on instead of the keyboard.
+#[derive(Component)]
+pub struct Autopilot;
+
+/// How the autopilot picks its moves.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    Search(Algorithm),
+    /// Follows the Hamiltonian cycle, cutting across it towards food while
+    /// the snake is short. Falls back to A* on boards with walls or portals,
+    /// which the cycle would run into.
+    Hamiltonian,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::Search(Algorithm::AStar)
+    }
+}
+
+impl Strategy {
+    pub fn label(self) -> &'static str {
+        match self {
+            Strategy::Search(algorithm) => algorithm.label(),
+            Strategy::Hamiltonian => "Hamiltonian cycle",
+        }
+    }
+
+    /// The strategy after this one, going through every search algorithm.
+    fn next(self) -> Self {
+        match self {
+            Strategy::Search(algorithm) => {
+                let i = Algorithm::ALL.iter().position(|a| *a == algorithm).unwrap();
+                Algorithm::ALL
+                    .get(i + 1)
+                    .map_or(Strategy::Hamiltonian, |next| Strategy::Search(*next))
+            }
+            Strategy::Hamiltonian => Strategy::Search(Algorithm::ALL[0]),
+        }
+    }
+}
+
+/// Cells each search algorithm explored from the player's head on the last
+/// tick, in `Algorithm::ALL` order. Empty while the autopilot is off.
+#[derive(Default)]
+pub struct SearchStats(pub Vec<(Algorithm, usize)>);
+
+/// A closed path visiting every cell of the board once: a serpentine over
+/// all columns but the first, which leads back down to the start.
+struct HamiltonianCycle {
+    /// The board it was laid out for.
+    grid: Grid,
+    order: Vec<Position>,
+    index: HashMap<Position, usize>,
+}
+
+impl FromWorld for HamiltonianCycle {
+    fn from_world(world: &mut World) -> Self {
+        Self::new(*world.resource::<Grid>())
+    }
+}
+
+impl HamiltonianCycle {
+    /// Only closes up with an even number of rows, where the last one ends
+    /// next to the return column.
+    fn new(grid: Grid) -> Self {
+        let (width, height) = (grid.width as i32, grid.height as i32);
+        let mut order = Vec::with_capacity((grid.width * grid.height) as usize);
+        for y in 0..height {
+            let row = (1..width).map(|x| Position { x, y });
+            if y % 2 == 0 {
+                order.extend(row);
+            } else {
+                order.extend(row.rev());
+            }
+        }
+        order.extend((0..height).rev().map(|y| Position { x: 0, y }));
+        let index = order.iter().enumerate().map(|(i, pos)| (*pos, i)).collect();
+        Self { grid, order, index }
+    }
+
+    fn closes(&self) -> bool {
+        self.grid.height.is_multiple_of(2)
+    }
+
+    /// How many steps along the cycle it takes to get from `from` to `to`.
+    fn distance(&self, from: Position, to: Position) -> usize {
+        let len = self.order.len();
+        (self.index[&to] + len - self.index[&from]) % len
+    }
+
+    /// The next move for a snake with `body` (head first), plus the rest of
+    /// the way round the cycle to the closest of `targets`.
+    ///
+    /// The body always trails the head along the cycle, so every cell ahead
+    /// of the head up to the tail is free. While the snake is short it can
+    /// skip ahead to any of those cells, as long as it doesn't pass the food
+    /// or get too close to its tail.
+    fn plan(
+        &self,
+        board: &Board,
+        body: &[Position],
+        targets: &[Position],
+    ) -> Option<Vec<(Direction, Position)>> {
+        let head = body[0];
+        let tail = *body.last()?;
+        let to_tail = self.distance(head, tail);
+        let to_food = targets
+            .iter()
+            .map(|target| self.distance(head, *target))
+            .min();
+        let shortcuts = body.len() < self.order.len() / 2;
+        let (direction, next, ahead) = DIRECTIONS
+            .into_iter()
+            .map(|direction| (direction, head.step(direction)))
+            .filter(|(_, next)| board.is_open_at(*next, 1))
+            .map(|(direction, next)| (direction, next, self.distance(head, next)))
+            .filter(|(_, _, ahead)| {
+                *ahead == 1
+                    || (shortcuts
+                        && *ahead + SHORTCUT_BUFFER < to_tail
+                        && to_food.is_some_and(|to_food| *ahead <= to_food))
+            })
+            .max_by_key(|(_, _, ahead)| *ahead)?;
+
+        let mut path = vec![(direction, next)];
+        if let Some(to_food) = to_food {
+            let start = self.index[&next];
+            // Nothing is left to add when a target is on the head's cell.
+            for step in 1..=to_food.saturating_sub(ahead) {
+                let from = path.last().unwrap().1;
+                let cell = self.order[(start + step) % self.order.len()];
+                let direction = DIRECTIONS
+                    .into_iter()
+                    .find(|direction| from.step(*direction) == cell)?;
+                path.push((direction, cell));
+            }
+        }
+        Some(path)
+    }
+}
+
+/// One cell of a path the autopilot is following, or one its search
+/// explored. Only drawn there, food can still turn up under it.
+#[derive(Component)]
+struct PathMarker(Position);
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Strategy>()
+            .init_resource::<SearchOptions>()
+            .init_resource::<SearchStats>()
+            .init_resource::<HamiltonianCycle>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(toggle_input)
+                    .with_system(strategy_input)
+                    .with_system(heuristic_input),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Debugger).with_system(clear_markers))
+            .add_system(place_markers)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(autopilot_steering.before(snake_movement)),
+            );
+    }
+}
+
+fn toggle_input(
+    mut commands: Commands,
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+    players: Query<(Entity, &Player, Option<&Autopilot>)>,
+) {
+    // Any key ends the demo instead, and a replay steers itself.
+    if demo.is_active() || playback.is_active() || !take_just_pressed(&mut kbd_input, [KeyCode::F1])
+    {
+        return;
+    }
+    for (snake, player, autopilot) in players.iter() {
+        if player.0 != 1 {
+            continue;
+        }
+        if autopilot.is_some() {
+            commands.entity(snake).remove::<Autopilot>();
+        } else {
+            commands.entity(snake).insert(Autopilot);
+        }
+    }
+}
+
+fn strategy_input(mut kbd_input: ResMut<Input<KeyCode>>, mut strategy: ResMut<Strategy>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::F2]) {
+        *strategy = strategy.next();
+    }
+}
+
+pub fn heuristic_input(mut kbd_input: ResMut<Input<KeyCode>>, mut options: ResMut<SearchOptions>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::F3]) {
+        options.heuristic = options.heuristic.next();
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::F4]) {
+        options.weight =
+            (options.weight - SearchOptions::WEIGHT_STEP).max(SearchOptions::MIN_WEIGHT);
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::F5]) {
+        options.weight =
+            (options.weight + SearchOptions::WEIGHT_STEP).min(SearchOptions::MAX_WEIGHT);
+    }
+}
+
+/// Directions a snake at `pos` heading `heading` can move in next without
+/// turning back on itself or running into anything in `blocked`.
+pub fn safe_directions(
+    grid: Grid,
+    pos: Position,
+    heading: Direction,
+    blocked: &[Position],
+    wrap_edges: bool,
+) -> Vec<Direction> {
+    DIRECTIONS
+        .into_iter()
+        .filter(|direction| *direction != heading.opposite())
+        .filter(|direction| {
+            let mut next = pos.step(*direction);
+            if wrap_edges {
+                next = grid.wrap(next);
+            }
+            grid.contains(next) && !blocked.contains(&next)
+        })
+        .collect()
+}
+
+/// Follows the path the strategy picks. With no path it takes any safe
+/// direction, preferring the one that ends up closest to food.
+fn autopilot_steering(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    strategy: Res<Strategy>,
+    options: Res<SearchOptions>,
+    mut cycle: ResMut<HamiltonianCycle>,
+    sequence: Res<Sequence>,
+    mut stats: ResMut<SearchStats>,
+    mut heads: Query<(&mut SnakeHead, &Position, &SnakeSegments, Option<&Player>), With<Autopilot>>,
+    positions: Query<&Position>,
+    snakes: Query<&SnakeSegments>,
+    walls: Query<&Position, With<Wall>>,
+    food: Query<(&Position, &Food, Option<&FoodNumber>)>,
+    portals: Query<(&Position, &Portal)>,
+    terrain: Query<(&Position, &Terrain)>,
+    markers: Query<Entity, With<PathMarker>>,
+) {
+    for entt in markers.iter() {
+        commands.entity(entt).despawn();
+    }
+    // Numbered food that isn't next is as good as a wall.
+    let out_of_turn = food
+        .iter()
+        .filter(|(.., number)| number.is_some_and(|number| number.0 != sequence.next))
+        .map(|(pos, ..)| pos);
+    let walls: Vec<Position> = walls.iter().chain(out_of_turn).copied().collect();
+    // Body cell `i` of a snake `len` long, counting from the head, is left
+    // behind after `len - i` steps. Segments just grown stack on the tail,
+    // and the one closest to the head counts.
+    let mut vacates = HashMap::new();
+    for segments in snakes.iter() {
+        let len = segments.len() as i32;
+        for (i, segment) in segments.iter().enumerate() {
+            if let Ok(pos) = positions.get(*segment) {
+                let free = vacates.entry(*pos).or_insert(0);
+                *free = (len - i as i32).max(*free);
+            }
+        }
+    }
+    // Everything in the way right now, for when there's no path to follow.
+    let occupied: Vec<Position> = walls.iter().chain(vacates.keys()).copied().collect();
+    let targets: Vec<Position> = food
+        .iter()
+        .filter(|(_, food, number)| {
+            food.kind != FoodKind::Poison && number.is_none_or(|number| number.0 == sequence.next)
+        })
+        .map(|(pos, ..)| *pos)
+        .collect();
+    let board = Board {
+        grid: *grid,
+        blocked: &walls,
+        portals: portals
+            .iter()
+            .map(|(pos, portal)| (*pos, portal.exit))
+            .collect(),
+        wrap_edges: rules.wrap_edges,
+        costs: terrain
+            .iter()
+            .map(|(pos, terrain)| (*pos, terrain.cost()))
+            .collect(),
+        vacates,
+    };
+    if cycle.grid != *grid {
+        *cycle = HamiltonianCycle::new(*grid);
+    }
+    // The cycle runs through every cell, so any wall or portal breaks it.
+    let cycle_clear = walls.is_empty() && board.portals.is_empty() && cycle.closes();
+    stats.0.clear();
+    for (mut head, pos, segments, player) in heads.iter_mut() {
+        if player.is_some_and(|player| player.0 == 1) {
+            stats.0 = Algorithm::ALL
+                .into_iter()
+                .map(|algorithm| {
+                    let explored = board.search(algorithm, &options, *pos, &targets).explored;
+                    (algorithm, explored.len())
+                })
+                .collect();
+        }
+        let body: Vec<Position> = segments
+            .iter()
+            .filter_map(|e| positions.get(*e).ok())
+            .copied()
+            .collect();
+        let mut explored = Vec::new();
+        let plan = match *strategy {
+            Strategy::Hamiltonian if cycle_clear => cycle.plan(&board, &body, &targets),
+            _ => None,
+        }
+        .or_else(|| {
+            let algorithm = match *strategy {
+                Strategy::Search(algorithm) => algorithm,
+                Strategy::Hamiltonian => Algorithm::AStar,
+            };
+            let result = board.search(algorithm, &options, *pos, &targets);
+            explored = result.explored;
+            result.path
+        })
+        .filter(|path| !path.is_empty());
+        for cell in explored {
+            spawn_marker(&mut commands, cell, EXPLORED_COLOR, EXPLORED_Z, 0.9);
+        }
+        if let Some(path) = plan {
+            head.direction = path[0].0;
+            // The last cell is the food itself, or just the next step when
+            // there's no food to head for.
+            for (_, cell) in &path[..path.len() - 1] {
+                spawn_marker(&mut commands, *cell, PATH_COLOR, PATH_Z, 0.2);
+            }
+            continue;
+        }
+        let distance_to_food = |direction: &Direction| {
+            let next = board.step(*pos, *direction);
+            board.distance_to(next, &targets)
+        };
+        if let Some(direction) =
+            safe_directions(*grid, *pos, head.direction, &occupied, rules.wrap_edges)
+                .into_iter()
+                .min_by_key(distance_to_food)
+        {
+            head.direction = direction;
+        }
+    }
+}
+
+/// Leaves the board to the search debugger, which draws its own.
+fn clear_markers(mut commands: Commands, markers: Query<Entity, With<PathMarker>>) {
+    for entt in markers.iter() {
+        commands.entity(entt).despawn();
+    }
+}
+
+fn spawn_marker(commands: &mut Commands, position: Position, color: Color, z: f32, size: f32) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform::from_xyz(0.0, 0.0, z),
+            // Shown once `place_markers` has put it in its cell.
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(PathMarker(position))
+        .insert(Size::square(size));
+}
+
+/// Keeps the markers on their cells as the window is resized.
+fn place_markers(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut markers: Query<(&PathMarker, &mut Transform, &mut Visibility)>,
+) {
+    let window = windows.get_primary().unwrap();
+    for (marker, mut transform, mut visibility) in markers.iter_mut() {
+        let center = cell_center(window, *grid, marker.0);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        if !visibility.is_visible {
+            visibility.is_visible = true;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! The endless mode. Every `BIOME_POINTS` points the board moves on to the
+//! next biome: the background, the player's snake and the walls all change
+//! color, and the walls change shape. Biomes are read from
+//! `assets/biomes.ron`, or from a `biomes.ron` in the config directory if
+//! there is one.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::color::rgb;
+use crate::persistence;
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::state::GameState;
+use crate::walls::Wall;
+use crate::{Player, Size, SnakeHead, SnakeSegments, CLEAR_COLOR};
+
+const BIOMES_FILE: &str = "biomes.ron";
+const BUNDLED_BIOMES: &str = include_str!("../assets/biomes.ron");
+/// Points scored in each biome before the next one.
+const BIOME_POINTS: u32 = 25;
+
+#[derive(Clone, Copy, Deserialize)]
+enum ObstacleStyle {
+    /// Fills the whole cell, like walls everywhere else.
+    Block,
+    Boulder,
+    Pillar,
+}
+
+impl ObstacleStyle {
+    fn size(self) -> Size {
+        match self {
+            ObstacleStyle::Block => Size::square(1.0),
+            ObstacleStyle::Boulder => Size::square(0.8),
+            ObstacleStyle::Pillar => Size {
+                width: 0.5,
+                height: 1.0,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Biome {
+    pub name: String,
+    background: (f32, f32, f32),
+    snake: (f32, f32, f32),
+    walls: (f32, f32, f32),
+    obstacles: ObstacleStyle,
+}
+
+/// Every biome, in the order they come up.
+pub struct Biomes(Vec<Biome>);
+
+impl Biomes {
+    fn load() -> Self {
+        Self(persistence::load_list(
+            BIOMES_FILE,
+            BUNDLED_BIOMES,
+            "biomes",
+        ))
+    }
+
+    /// The biome for a run that has scored `score` points.
+    fn at(&self, score: u32) -> usize {
+        (score / BIOME_POINTS) as usize % self.0.len()
+    }
+}
+
+/// Index of the biome the run is in, `None` outside the endless mode.
+#[derive(Default)]
+pub struct CurrentBiome(Option<usize>);
+
+impl CurrentBiome {
+    pub fn get<'a>(&self, biomes: &'a Biomes) -> Option<&'a Biome> {
+        self.0.map(|index| &biomes.0[index])
+    }
+}
+
+/// The color behind the board. Looks that change colors, like the handheld
+/// one, start from this rather than from `ClearColor`.
+pub struct Background(pub Color);
+
+impl Default for Background {
+    fn default() -> Self {
+        Self(CLEAR_COLOR)
+    }
+}
+
+pub struct BiomesPlugin;
+
+impl Plugin for BiomesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Biomes::load())
+            .init_resource::<CurrentBiome>()
+            .init_resource::<Background>()
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(leave_biomes))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(leave_biomes))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(change_biome)
+                    .with_system(paint_biome.after(change_biome)),
+            );
+    }
+}
+
+fn leave_biomes(mut current: ResMut<CurrentBiome>, mut background: ResMut<Background>) {
+    *current = CurrentBiome::default();
+    *background = Background::default();
+}
+
+fn change_biome(
+    rules: Res<GameRules>,
+    score: Res<Score>,
+    biomes: Res<Biomes>,
+    mut current: ResMut<CurrentBiome>,
+    mut background: ResMut<Background>,
+) {
+    if rules.mode != GameMode::Endless {
+        return;
+    }
+    let index = biomes.at(score.0);
+    if current.0 != Some(index) {
+        current.0 = Some(index);
+        background.0 = rgb(biomes.0[index].background);
+        info!("Biome: {}", biomes.0[index].name);
+    }
+}
+
+/// Colors the player's snake and the walls for the current biome. Only
+/// what's new or out of date is touched, so other looks can recolor on top.
+fn paint_biome(
+    biomes: Res<Biomes>,
+    current: Res<CurrentBiome>,
+    mut heads: Query<(&mut SnakeHead, &SnakeSegments), With<Player>>,
+    mut segments: Query<&mut Sprite, Without<Wall>>,
+    mut walls: Query<(ChangeTrackers<Wall>, &mut Sprite, &mut Size), With<Wall>>,
+) {
+    let biome = match current.get(&biomes) {
+        Some(biome) => biome,
+        None => return,
+    };
+    for (mut head, snake_segments) in heads.iter_mut() {
+        head.paint(snake_segments, &mut segments, rgb(biome.snake));
+    }
+    for (tracker, mut sprite, mut size) in walls.iter_mut() {
+        if current.is_changed() || tracker.is_added() {
+            sprite.color = rgb(biome.walls);
+            *size = biome.obstacles.size();
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! The size of the board, picked on the menu or with `--board`, and saved
+//! in the settings when picked on the menu. The current level is always kept
+//! the same size: picking a size no level is made for leaves the board
+//! empty, and picking a level sizes the board for it. Each run's `Grid`
+//! starts out as the level's size.
+//!
+//! The board is drawn as a checkerboard, with every other cell a touch
+//! lighter than the background, and redrawn whenever the grid changes size.
+
+use bevy::prelude::*;
+
+use crate::cli::CliArgs;
+use crate::level::{CurrentLevel, Level};
+use crate::settings::Settings;
+use crate::state::GameState;
+use crate::{cell_center, Grid, Position, Size};
+
+/// Smallest and largest board, in cells each way.
+pub const MIN_SIZE: u32 = 10;
+pub const MAX_SIZE: u32 = 60;
+/// The sizes the menu cycles through.
+const SIZES: [(u32, u32); 4] = [(20, 20), (30, 30), (40, 20), (40, 40)];
+/// Laid over the background, so it stays a touch lighter whatever the
+/// background is.
+const LIGHT_TILE_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.04);
+/// Behind everything else on the board.
+const TILE_Z: f32 = -0.1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct BoardConfig {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoardConfig {
+    pub fn label(self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+
+    /// Switches to the next size on the menu.
+    pub fn cycle(&mut self) {
+        let next = SIZES
+            .iter()
+            .position(|size| *size == (self.width, self.height))
+            .map_or(0, |i| (i + 1) % SIZES.len());
+        (self.width, self.height) = SIZES[next];
+    }
+
+    pub fn grid(self) -> Grid {
+        Grid {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Whether `level` is made for this board.
+    pub fn fits(self, level: &Level) -> bool {
+        Grid::of(level) == self.grid()
+    }
+
+    /// An empty level this size.
+    pub fn open_level(self) -> Level {
+        Level::open(self.width, self.height)
+    }
+}
+
+impl FromWorld for BoardConfig {
+    fn from_world(world: &mut World) -> Self {
+        let cli = world.resource::<CliArgs>();
+        let (requested, level_requested) = (cli.board, cli.level.is_some());
+        // A level asked for keeps its own size unless `--board` says too.
+        let saved = world
+            .resource::<Settings>()
+            .board
+            .filter(|_| !level_requested);
+        let requested = requested.or(saved);
+        let mut current = world.resource_mut::<CurrentLevel>();
+        let level_size = Self {
+            width: current.level.width,
+            height: current.level.height,
+        };
+        let board = match requested {
+            Some((width, height))
+                if (MIN_SIZE..=MAX_SIZE).contains(&width)
+                    && (MIN_SIZE..=MAX_SIZE).contains(&height) =>
+            {
+                Self { width, height }
+            }
+            Some((width, height)) => {
+                error!(
+                    "Board {}x{} is too big or small, it has to be {} to {} cells each way",
+                    width, height, MIN_SIZE, MAX_SIZE
+                );
+                level_size
+            }
+            None => level_size,
+        };
+        // The size asked for wins over the level's.
+        if !board.fits(&current.level) {
+            if level_requested {
+                warn!(
+                    "Playing an open {} board instead of {}, which is {}",
+                    board.label(),
+                    current.level.name,
+                    level_size.label()
+                );
+            }
+            *current = CurrentLevel::custom(board.open_level());
+        }
+        board
+    }
+}
+
+/// One of the checkerboard's lighter cells. It has no `Position`, which
+/// would make it count as something lying on the board.
+#[derive(Component)]
+struct LightTile(Position);
+
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BoardConfig>()
+            .init_resource::<Grid>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu)
+                    .with_system(follow_level)
+                    .with_system(reset_grid),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_grid))
+            .add_system(spawn_checkerboard)
+            .add_system(place_tiles.after(spawn_checkerboard));
+    }
+}
+
+fn follow_level(current: Res<CurrentLevel>, mut board: ResMut<BoardConfig>) {
+    if !board.fits(&current.level) {
+        board.width = current.level.width;
+        board.height = current.level.height;
+    }
+}
+
+/// Sizes the board for the current level. The grid is only written when it
+/// changed, so nothing watching it sees a change otherwise.
+pub fn reset_grid(current: Res<CurrentLevel>, mut grid: ResMut<Grid>) {
+    let size = Grid::of(&current.level);
+    if *grid != size {
+        *grid = size;
+    }
+}
+
+fn spawn_checkerboard(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    tiles: Query<Entity, With<LightTile>>,
+) {
+    if !grid.is_changed() {
+        return;
+    }
+    for entt in tiles.iter() {
+        commands.entity(entt).despawn();
+    }
+    for pos in grid.cells().filter(|pos| (pos.x + pos.y) % 2 == 0) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: LIGHT_TILE_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, TILE_Z),
+                // Shown once `place_tiles` has put it in its cell.
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(LightTile(pos))
+            .insert(Size::square(1.0));
+    }
+}
+
+/// Keeps the tiles on their cells as the window is resized.
+fn place_tiles(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut tiles: Query<(&LightTile, &mut Transform, &mut Visibility)>,
+) {
+    let window = windows.get_primary().unwrap();
+    for (tile, mut transform, mut visibility) in tiles.iter_mut() {
+        let center = cell_center(window, *grid, tile.0);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        if !visibility.is_visible {
+            visibility.is_visible = true;
+        }
+    }
+}
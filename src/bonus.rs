@@ -0,0 +1,146 @@
+//! The bonus round played between campaign levels. For `BONUS_DURATION`
+//! seconds the cleared board is covered in food and nothing can kill the
+//! snake: it wraps around the edges and waits at walls and at its own body
+//! until it's turned. What it scores is carried into the next level.
+
+use bevy::prelude::*;
+
+use crate::campaign::CampaignRun;
+use crate::food::{free_cells, spawn_food, Food, FoodKind, Occupying};
+use crate::score::Score;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::tick::BASE_TICK_INTERVAL;
+use crate::walls::Wall;
+use crate::{snake_movement_input, Grid, Player, Position, SnakeHead, SnakeSegments};
+
+const BONUS_DURATION: f32 = 20.0;
+const BONUS_TICK_INTERVAL: f32 = BASE_TICK_INTERVAL * 0.75;
+const BONUS_FOOD_POINTS: u32 = 5;
+
+/// Time left in the bonus round, `None` outside of one.
+#[derive(Default)]
+pub struct BonusClock(Option<Timer>);
+
+impl BonusClock {
+    pub fn seconds_left(&self) -> Option<f32> {
+        self.0
+            .as_ref()
+            .map(|timer| (timer.duration() - timer.elapsed()).as_secs_f32())
+    }
+}
+
+/// Steps the snake on its own, as the movement tick only runs in `Playing`.
+struct BonusTick(Timer);
+
+impl Default for BonusTick {
+    fn default() -> Self {
+        Self(Timer::from_seconds(BONUS_TICK_INTERVAL, true))
+    }
+}
+
+pub struct BonusRoundPlugin;
+
+impl Plugin for BonusRoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BonusClock>()
+            .init_resource::<BonusTick>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::BonusRound).with_system(start_bonus_round),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::BonusRound)
+                    .with_system(snake_movement_input.before(bonus_movement))
+                    .with_system(bonus_movement)
+                    .with_system(run_bonus_clock.after(bonus_movement))
+                    .with_system(leave_input),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::BonusRound).with_system(end_bonus_round));
+    }
+}
+
+/// Covers every free cell in food and starts the clock. The snake carries on
+/// from where it cleared the level.
+fn start_bonus_round(
+    mut commands: Commands,
+    grid: Res<Grid>,
+    mut clock: ResMut<BonusClock>,
+    mut tick: ResMut<BonusTick>,
+    mut score: ResMut<Score>,
+    food: Query<Entity, With<Food>>,
+    occupied: Query<&Position, (Occupying, Without<Food>)>,
+) {
+    for entt in food.iter() {
+        commands.entity(entt).despawn();
+    }
+    for position in free_cells(*grid, occupied.iter()) {
+        spawn_food(&mut commands, position, FoodKind::Normal);
+    }
+    clock.0 = Some(Timer::from_seconds(BONUS_DURATION, false));
+    *tick = BonusTick::default();
+    *score = Score::default();
+}
+
+fn bonus_movement(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<Grid>,
+    mut tick: ResMut<BonusTick>,
+    mut score: ResMut<Score>,
+    mut heads: Query<(&mut SnakeHead, &SnakeSegments), With<Player>>,
+    mut positions: Query<&mut Position, (Without<Wall>, Without<Food>)>,
+    walls: Query<&Position, With<Wall>>,
+    food: Query<(Entity, &Position), With<Food>>,
+) {
+    if !tick.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (mut head, segments) in heads.iter_mut() {
+        let body: Vec<Position> = segments
+            .iter()
+            .map(|entt| *positions.get(*entt).unwrap())
+            .collect();
+        head.apply_next_turn();
+        let next = grid.wrap(body[0].step(head.direction));
+        // The tail moves out of the way as the head moves in.
+        let blocked =
+            walls.iter().any(|pos| *pos == next) || body[..body.len() - 1].contains(&next);
+        if blocked {
+            continue;
+        }
+        for (entt, pos) in segments.iter().zip(std::iter::once(next).chain(body)) {
+            *positions.get_mut(*entt).unwrap() = pos;
+        }
+        if let Some((entt, _)) = food.iter().find(|(_, pos)| **pos == next) {
+            commands.entity(entt).despawn();
+            score.0 += BONUS_FOOD_POINTS;
+        }
+    }
+}
+
+/// Moves on to the next level once time is up, taking the bonus along.
+fn run_bonus_clock(
+    time: Res<Time>,
+    score: Res<Score>,
+    mut clock: ResMut<BonusClock>,
+    mut campaign: ResMut<CampaignRun>,
+    mut state_writer: EventWriter<GameStateEvent>,
+) {
+    let timer = match clock.0.as_mut() {
+        Some(timer) => timer,
+        None => return,
+    };
+    if timer.tick(time.delta()).just_finished() {
+        campaign.bonus = score.0;
+        state_writer.send(GameStateEvent::StartRun);
+    }
+}
+
+fn leave_input(mut kbd_input: ResMut<Input<KeyCode>>, mut writer: EventWriter<GameStateEvent>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn end_bonus_round(mut clock: ResMut<BonusClock>) {
+    clock.0 = None;
+}
@@ -0,0 +1,232 @@
+//! Boss food: a rare 2x2 piece that takes `BOSS_HITS` hits to eat. Each hit
+//! knocks it to another spot on the board, and the last one pays out a big
+//! growth and score. Pips over it show how many hits it has left.
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::food::{free_cells, Occupying};
+use crate::rng::GameRng;
+use crate::state::GameState;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::{cell_center, snake_growth, snake_movement, Grid, GrowthEvent, Player, Position, Size};
+
+const SPAWN_INTERVAL: f32 = 20.0;
+const SPAWN_CHANCE: f32 = 0.3;
+const BOSS_HITS: u32 = 3;
+/// Segments the snake grows by when the boss is finished off.
+const BOSS_GROWTH: u32 = 5;
+/// Points for each of those segments, before multipliers.
+const BOSS_POINTS_PER_SEGMENT: u32 = 10;
+const BOSS_COLOR: Color = Color::rgb(0.85, 0.15, 0.2);
+const PIP_FULL_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+const PIP_EMPTY_COLOR: Color = Color::rgba(0.3, 0.3, 0.3, 0.8);
+/// Side of a pip as a share of a cell.
+const PIP_SIZE: f32 = 0.3;
+
+/// The boss on the board, if any.
+#[derive(Default)]
+struct Boss(Option<BossFight>);
+
+struct BossFight {
+    /// Bottom left of the four cells it covers.
+    anchor: Position,
+    hits_left: u32,
+}
+
+struct BossSpawnTimer(Timer);
+
+impl Default for BossSpawnTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(SPAWN_INTERVAL, true))
+    }
+}
+
+/// One of the boss's four cells. They have a `Position` like any food, so
+/// nothing else spawns on them.
+#[derive(Component)]
+struct BossCell;
+
+/// A health pip drawn over the boss, the `n`th from the left.
+#[derive(Component)]
+struct BossPip(u32);
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Boss>()
+            .init_resource::<BossSpawnTimer>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(clear_boss))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(spawn_boss.after(snake_movement))
+                    .with_system(hit_boss.after(snake_movement).before(snake_growth)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(place_pips));
+    }
+}
+
+/// The four cells covered by a boss anchored at `anchor`.
+fn boss_cells(anchor: Position) -> [Position; 4] {
+    [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| Position {
+        x: anchor.x + dx,
+        y: anchor.y + dy,
+    })
+}
+
+/// A random spot where the whole boss fits on free cells.
+fn random_anchor(rng: &mut impl Rng, grid: Grid, free: &[Position]) -> Option<Position> {
+    let anchors: Vec<Position> = free
+        .iter()
+        .filter(|pos| pos.x < grid.width as i32 - 1 && pos.y < grid.height as i32 - 1)
+        .filter(|pos| boss_cells(**pos).iter().all(|cell| free.contains(cell)))
+        .copied()
+        .collect();
+    anchors.choose(rng).copied()
+}
+
+fn clear_boss(
+    mut commands: Commands,
+    mut boss: ResMut<Boss>,
+    mut timer: ResMut<BossSpawnTimer>,
+    entities: Query<Entity, Or<(With<BossCell>, With<BossPip>)>>,
+) {
+    boss.0 = None;
+    timer.0.reset();
+    for entt in entities.iter() {
+        commands.entity(entt).despawn();
+    }
+}
+
+fn spawn_boss(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    mut timer: ResMut<BossSpawnTimer>,
+    mut boss: ResMut<Boss>,
+    mut rng: ResMut<GameRng>,
+    grid: Res<Grid>,
+    occupied: Query<&Position, Occupying>,
+) {
+    if !timer.0.tick(move_timer.step()).just_finished()
+        || boss.0.is_some()
+        || rng.boss().gen::<f32>() >= SPAWN_CHANCE
+    {
+        return;
+    }
+    let anchor = match random_anchor(rng.boss(), *grid, &free_cells(*grid, occupied.iter())) {
+        Some(anchor) => anchor,
+        None => return,
+    };
+    for cell in boss_cells(anchor) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: BOSS_COLOR,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(BossCell)
+            .insert(cell)
+            .insert(Size::square(1.0));
+    }
+    for n in 0..BOSS_HITS {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: PIP_FULL_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, 0.5),
+                ..default()
+            })
+            .insert(BossPip(n));
+    }
+    boss.0 = Some(BossFight {
+        anchor,
+        hits_left: BOSS_HITS,
+    });
+}
+
+/// A player's head running into any of the boss's cells hits it, moving it
+/// elsewhere until the last hit finishes it off.
+fn hit_boss(
+    mut commands: Commands,
+    mut boss: ResMut<Boss>,
+    mut rng: ResMut<GameRng>,
+    grid: Res<Grid>,
+    mut growth_writer: EventWriter<GrowthEvent>,
+    heads: Query<(Entity, &Position), With<Player>>,
+    mut cells: Query<(Entity, &mut Position), (With<BossCell>, Without<Player>)>,
+    mut pips: Query<(Entity, &BossPip, &mut Sprite)>,
+    occupied: Query<&Position, (Occupying, Without<BossCell>)>,
+) {
+    let fight = match boss.0.as_mut() {
+        Some(fight) => fight,
+        None => return,
+    };
+    let covered = boss_cells(fight.anchor);
+    let hitter = match heads.iter().find(|(_, pos)| covered.contains(pos)) {
+        Some((snake, _)) => snake,
+        None => return,
+    };
+    fight.hits_left -= 1;
+    if fight.hits_left == 0 {
+        for _ in 0..BOSS_GROWTH {
+            growth_writer.send(GrowthEvent {
+                snake: hitter,
+                value: BOSS_POINTS_PER_SEGMENT,
+            });
+        }
+        for (entt, ..) in cells.iter() {
+            commands.entity(entt).despawn();
+        }
+        for (entt, ..) in pips.iter() {
+            commands.entity(entt).despawn();
+        }
+        boss.0 = None;
+        return;
+    }
+    for (_, pip, mut sprite) in pips.iter_mut() {
+        if pip.0 >= fight.hits_left {
+            sprite.color = PIP_EMPTY_COLOR;
+        }
+    }
+    // The boss's own cells count as free here, but the head that just hit
+    // it doesn't.
+    let free = free_cells(*grid, occupied.iter());
+    if let Some(anchor) = random_anchor(rng.boss(), *grid, &free) {
+        fight.anchor = anchor;
+        for ((_, mut pos), cell) in cells.iter_mut().zip(boss_cells(anchor)) {
+            *pos = cell;
+        }
+    }
+}
+
+/// Lines the pips up just above the boss.
+fn place_pips(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    boss: Res<Boss>,
+    mut pips: Query<(&BossPip, &mut Transform)>,
+) {
+    let fight = match &boss.0 {
+        Some(fight) => fight,
+        None => return,
+    };
+    let window = windows.get_primary().unwrap();
+    let cell_size = grid.cell_size(window);
+    // Halfway between the two top cells, a little above them.
+    let center = cell_center(window, *grid, fight.anchor)
+        + Vec2::new(cell_size / 2.0, cell_size * 1.5 + cell_size * PIP_SIZE);
+    let spacing = cell_size * PIP_SIZE * 1.5;
+    for (pip, mut transform) in pips.iter_mut() {
+        let offset = (pip.0 as f32 - (BOSS_HITS - 1) as f32 / 2.0) * spacing;
+        transform.translation.x = center.x + offset;
+        transform.translation.y = center.y;
+        transform.scale = Vec3::new(cell_size * PIP_SIZE, cell_size * PIP_SIZE, 1.0);
+    }
+}
@@ -0,0 +1,303 @@
+//! The campaign: the bundled levels played in order, each cleared by growing
+//! the snake to a goal length. Clearing a level unlocks the next one, and how
+//! far the player has got is kept between sessions. With the campaign picked
+//! as the mode, Enter on the menu opens a level select screen instead of
+//! starting a run.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::gameover::restart_input;
+use crate::input_map::InputMap;
+use crate::level::CurrentLevel;
+use crate::menu::{menu_input, MenuCursor};
+use crate::persistence;
+use crate::replay::Playback;
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::tick::MovementTick;
+use crate::ui;
+use crate::{player_length, reset_game, snake_growth, Player, SnakeSegments};
+
+const PROGRESS_FILE: &str = "campaign.ron";
+
+/// Bundled level and the length that clears it, in campaign order.
+pub const STAGES: [(&str, usize); 8] = [
+    ("open", 10),
+    ("bars", 12),
+    ("box", 14),
+    ("cross", 16),
+    ("warp", 18),
+    ("marsh", 20),
+    ("vault", 20),
+    ("gauntlet", 24),
+];
+
+/// How many stages have been cleared. Every stage up to and including the
+/// first one not cleared yet can be played.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CampaignProgress {
+    cleared: usize,
+}
+
+impl CampaignProgress {
+    pub fn load() -> Self {
+        persistence::load(PROGRESS_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = persistence::save(PROGRESS_FILE, self) {
+            warn!("Failed to save campaign progress: {}", err);
+        }
+    }
+
+    fn unlocked(&self, stage: usize) -> bool {
+        stage <= self.cleared
+    }
+}
+
+/// The stage being played and whether this run has cleared it, plus the level
+/// picked on the menu so it can be put back afterwards.
+#[derive(Default)]
+pub struct CampaignRun {
+    pub stage: usize,
+    pub cleared: bool,
+    /// Points from the bonus round, which the next stage starts out with.
+    pub bonus: u32,
+    picked: Option<CurrentLevel>,
+}
+
+impl CampaignRun {
+    pub fn goal(&self) -> usize {
+        STAGES[self.stage].1
+    }
+
+    /// Whether there's another stage after this one.
+    pub fn has_next(&self) -> bool {
+        self.stage + 1 < STAGES.len()
+    }
+}
+
+/// The stage highlighted on the level select screen.
+#[derive(Default)]
+struct Selected(usize);
+
+#[derive(Component)]
+struct LevelSelectScreen;
+
+#[derive(Component)]
+struct StageList;
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CampaignProgress::load())
+            .init_resource::<CampaignRun>()
+            .init_resource::<Selected>()
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_campaign))
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(open_input.before(menu_input)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::LevelSelect).with_system(spawn_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::LevelSelect)
+                    .with_system(select_input)
+                    .with_system(stage_list_update.after(select_input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::LevelSelect).with_system(despawn_screen))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(start_stage.after(reset_game)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(reach_goal.after(snake_growth)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .with_system(next_stage.before(restart_input)),
+            );
+    }
+}
+
+fn stage_level(stage: usize) -> CurrentLevel {
+    CurrentLevel::bundled_named(STAGES[stage].0).expect("campaign stages are bundled levels")
+}
+
+fn open_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    rules: Res<GameRules>,
+    cursor: Res<MenuCursor>,
+    progress: Res<CampaignProgress>,
+    mut selected: ResMut<Selected>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if rules.mode == GameMode::Campaign
+        && cursor.on_play()
+        && take_just_pressed(&mut kbd_input, [KeyCode::Return])
+    {
+        selected.0 = progress.cleared.min(STAGES.len() - 1);
+        writer.send(GameStateEvent::OpenLevelSelect);
+    }
+}
+
+fn select_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    progress: Res<CampaignProgress>,
+    mut selected: ResMut<Selected>,
+    mut campaign: ResMut<CampaignRun>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::Up, KeyCode::W]) {
+        selected.0 = selected.0.saturating_sub(1);
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Down, KeyCode::S]) {
+        let next = selected.0 + 1;
+        if next < STAGES.len() && progress.unlocked(next) {
+            selected.0 = next;
+        }
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+        let picked = std::mem::replace(&mut *current_level, stage_level(selected.0));
+        *campaign = CampaignRun {
+            stage: selected.0,
+            cleared: false,
+            bonus: 0,
+            picked: Some(picked),
+        };
+        writer.send(GameStateEvent::StartRun);
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn spawn_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    progress: Res<CampaignProgress>,
+    selected: Res<Selected>,
+) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(LevelSelectScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line(
+                "Campaign",
+                ui::text_style(&asset_server, 64.0),
+            ));
+            parent
+                .spawn_bundle(ui::text_line(
+                    stage_list(&progress, selected.0),
+                    ui::text_style(&asset_server, 24.0),
+                ))
+                .insert(StageList);
+            parent.spawn_bundle(ui::text_line(
+                "Up and Down to pick a level, Enter to play, Esc to go back",
+                ui::text_style(&asset_server, 24.0),
+            ));
+        });
+}
+
+/// One line per stage, with the selected one marked.
+fn stage_list(progress: &CampaignProgress, selected: usize) -> String {
+    STAGES
+        .iter()
+        .enumerate()
+        .map(|(stage, (_, goal))| {
+            let marker = if stage == selected { ">" } else { " " };
+            let status = if stage < progress.cleared {
+                "cleared".to_string()
+            } else if progress.unlocked(stage) {
+                format!("reach length {}", goal)
+            } else {
+                "locked".to_string()
+            };
+            format!(
+                "{} {}. {}: {}",
+                marker,
+                stage + 1,
+                stage_level(stage).level.name,
+                status
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn stage_list_update(
+    progress: Res<CampaignProgress>,
+    selected: Res<Selected>,
+    mut texts: Query<&mut Text, With<StageList>>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = stage_list(&progress, selected.0);
+    }
+}
+
+fn despawn_screen(mut commands: Commands, screens: Query<Entity, With<LevelSelectScreen>>) {
+    for entt in screens.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
+
+fn start_stage(mut campaign: ResMut<CampaignRun>, mut score: ResMut<Score>) {
+    campaign.cleared = false;
+    score.0 += std::mem::take(&mut campaign.bonus);
+}
+
+/// Ends the run as cleared once the player's snake is long enough, unlocking
+/// the next stage.
+fn reach_goal(
+    rules: Res<GameRules>,
+    mut campaign: ResMut<CampaignRun>,
+    mut progress: ResMut<CampaignProgress>,
+    players: Query<(&Player, &SnakeSegments)>,
+    playback: Res<Playback>,
+    mut state_writer: EventWriter<GameStateEvent>,
+) {
+    if rules.mode != GameMode::Campaign
+        || campaign.cleared
+        || player_length(&players) < campaign.goal()
+    {
+        return;
+    }
+    campaign.cleared = true;
+    if !playback.is_active() && progress.cleared <= campaign.stage {
+        progress.cleared = campaign.stage + 1;
+        progress.save();
+    }
+    state_writer.send(GameStateEvent::EndRun);
+}
+
+/// Restart after clearing a stage goes on to the bonus round, which then starts
+/// the next stage, see `bonus`.
+fn next_stage(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    rules: Res<GameRules>,
+    mut campaign: ResMut<CampaignRun>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if rules.mode != GameMode::Campaign
+        || !campaign.cleared
+        || !campaign.has_next()
+        || !take_just_pressed(&mut kbd_input, input_map.restart.iter().copied())
+    {
+        return;
+    }
+    campaign.stage += 1;
+    *current_level = stage_level(campaign.stage);
+    writer.send(GameStateEvent::StartBonusRound);
+}
+
+fn end_campaign(mut campaign: ResMut<CampaignRun>, mut current_level: ResMut<CurrentLevel>) {
+    if let Some(picked) = campaign.picked.take() {
+        *current_level = picked;
+    }
+}
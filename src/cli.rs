@@ -0,0 +1,49 @@
+//! Command line options.
+
+#[derive(Default)]
+pub struct CliArgs {
+    /// Bundled level name or path to a level file, see `--level`.
+    pub level: Option<String>,
+    /// Port to host an online versus game on, see `--host`.
+    pub host: Option<u16>,
+    /// Address of an online versus game to join, see `--join`.
+    pub join: Option<String>,
+    /// Seed for every run instead of a random one, see `--seed`.
+    pub seed: Option<u64>,
+    /// Board width and height in cells, see `--board`.
+    pub board: Option<(u32, u32)>,
+}
+
+impl CliArgs {
+    pub fn parse() -> Self {
+        let mut cli = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--level" => cli.level = args.next(),
+                "--host" => match args.next().map(|port| port.parse()) {
+                    Some(Ok(port)) => cli.host = Some(port),
+                    _ => eprintln!("--host needs a port number"),
+                },
+                "--join" => cli.join = args.next(),
+                "--seed" => match args.next().map(|seed| seed.parse()) {
+                    Some(Ok(seed)) => cli.seed = Some(seed),
+                    _ => eprintln!("--seed needs a number"),
+                },
+                "--board" => match args.next().as_deref().and_then(parse_size) {
+                    Some(size) => cli.board = Some(size),
+                    _ => eprintln!("--board needs a size like 40x20"),
+                },
+                // Logging isn't set up yet while the app is being built.
+                _ => eprintln!("Ignoring unknown argument {}", arg),
+            }
+        }
+        cli
+    }
+}
+
+/// Reads a board size written as `WIDTHxHEIGHT`.
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
@@ -0,0 +1,42 @@
+//! Helpers for recoloring in hue, saturation and lightness.
+
+use bevy::prelude::*;
+
+/// Saturation below which a color counts as gray.
+pub const GRAY_SATURATION: f32 = 0.1;
+
+/// Recolors every section of `text` that `recolor` gives a new color for.
+/// Takes the `Mut` so text that needs none isn't marked changed and laid out
+/// again.
+pub fn recolor_text(mut text: Mut<Text>, recolor: impl Fn(Color) -> Option<Color>) {
+    if !text
+        .sections
+        .iter()
+        .any(|section| recolor(section.style.color).is_some())
+    {
+        return;
+    }
+    for section in text.sections.iter_mut() {
+        if let Some(color) = recolor(section.style.color) {
+            section.style.color = color;
+        }
+    }
+}
+
+/// A color given as red, green and blue, like the RON files have them.
+pub fn rgb((r, g, b): (f32, f32, f32)) -> Color {
+    Color::rgb(r, g, b)
+}
+
+/// `color` as hue, saturation, lightness and alpha.
+pub fn hsla(color: Color) -> (f32, f32, f32, f32) {
+    match color.as_hsla() {
+        Color::Hsla {
+            hue,
+            saturation,
+            lightness,
+            alpha,
+        } => (hue, saturation, lightness, alpha),
+        _ => unreachable!("as_hsla gives hsla"),
+    }
+}
@@ -0,0 +1,99 @@
+//! Palettes for color blindness, picked in the settings like the handheld's
+//! greens. Every sprite and text keeps the lightness and saturation of its
+//! color, but the hue moves to one of a few that can be told apart: with
+//! deuteranopia and protanopia greens turn blue and reds purple, and with
+//! tritanopia blues and greens turn cyan and violets magenta. Grays are left
+//! as they are.
+//!
+//! A few colors end up sharing a hue, so food kinds also differ in shape, see
+//! `food_sprites`.
+
+use bevy::prelude::*;
+
+use crate::color::{hsla, recolor_text, GRAY_SATURATION};
+use crate::handheld::{green, Palette};
+use crate::settings::Settings;
+
+/// Where each band of hues starts, in degrees, and the hue it moves to. Hues
+/// below the first band are in the last one. Each band moves to a hue that
+/// stays put, so recoloring a color again leaves it the same.
+const RED_GREEN_HUES: [(f32, f32); 7] = [
+    (15.0, 30.0),
+    (45.0, 58.0),
+    // Greens join the blues.
+    (75.0, 235.0),
+    (170.0, 195.0),
+    (215.0, 235.0),
+    (250.0, 290.0),
+    (315.0, 330.0),
+];
+const BLUE_YELLOW_HUES: [(f32, f32); 5] = [
+    (20.0, 30.0),
+    (45.0, 55.0),
+    (75.0, 185.0),
+    (250.0, 300.0),
+    (315.0, 355.0),
+];
+/// Reds and oranges look dark with protanopia, so they're drawn at least this
+/// light.
+const PROTANOPIA_MIN_LIGHTNESS: f32 = 0.55;
+
+fn hues(palette: Palette) -> Option<&'static [(f32, f32)]> {
+    match palette {
+        Palette::Deuteranopia | Palette::Protanopia => Some(&RED_GREEN_HUES),
+        Palette::Tritanopia => Some(&BLUE_YELLOW_HUES),
+        Palette::FullColor | Palette::Handheld => None,
+    }
+}
+
+/// `color` as the palette draws it, `None` if that's how it's drawn already
+/// or the palette isn't one for color blindness.
+pub fn in_palette(palette: Palette, color: Color) -> Option<Color> {
+    let hues = hues(palette)?;
+    let (hue, saturation, lightness, alpha) = hsla(color);
+    if saturation < GRAY_SATURATION {
+        return None;
+    }
+    let target = hues
+        .iter()
+        .rev()
+        .find(|(start, _)| hue >= *start)
+        .unwrap_or(&hues[hues.len() - 1])
+        .1;
+    let warm = !(45.0..315.0).contains(&target);
+    let light = if palette == Palette::Protanopia && warm {
+        lightness.max(PROTANOPIA_MIN_LIGHTNESS)
+    } else {
+        lightness
+    };
+    // Converting back and forth isn't exact, so a color that's close enough
+    // counts as recolored.
+    if (hue - target).abs() < 0.5 && light - lightness < 0.005 {
+        return None;
+    }
+    Some(Color::hsla(target, saturation, light, alpha).as_rgba())
+}
+
+pub struct ColorblindPlugin;
+
+impl Plugin for ColorblindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, recolor);
+    }
+}
+
+/// The background is recolored along with the rest of the handheld look, see
+/// `handheld`.
+fn recolor(settings: Res<Settings>, mut sprites: Query<&mut Sprite>, mut texts: Query<&mut Text>) {
+    if hues(settings.palette).is_none() || green(&settings) {
+        return;
+    }
+    for mut sprite in sprites.iter_mut() {
+        if let Some(color) = in_palette(settings.palette, sprite.color) {
+            sprite.color = color;
+        }
+    }
+    for text in texts.iter_mut() {
+        recolor_text(text, |color| in_palette(settings.palette, color));
+    }
+}
@@ -0,0 +1,85 @@
+//! Streak bonus for eating food in quick succession.
+
+use bevy::prelude::*;
+
+use crate::food::FoodKind;
+use crate::powerups::ActiveEffects;
+use crate::rules::GameRules;
+use crate::score::Score;
+use crate::sprint::Sprint;
+use crate::state::GameState;
+use crate::tick::MovementTick;
+use crate::{snake_eating, FoodEatenEvent};
+
+/// Ticks the player has after eating to keep the streak going.
+pub const COMBO_WINDOW: u32 = 15;
+/// Extra points for each food in the streak after the first.
+const COMBO_BONUS_STEP: u32 = 2;
+const MAX_COMBO_BONUS: u32 = 10;
+
+#[derive(Default)]
+pub struct Combo {
+    /// Food eaten in a row, each within `COMBO_WINDOW` ticks of the last.
+    pub streak: u32,
+    /// Ticks left to extend the streak.
+    pub ticks_left: u32,
+}
+
+impl Combo {
+    /// Bonus points for the current streak, before score multipliers.
+    pub fn bonus(&self) -> u32 {
+        (self.streak.saturating_sub(1) * COMBO_BONUS_STEP).min(MAX_COMBO_BONUS)
+    }
+}
+
+pub struct ComboPlugin;
+
+impl Plugin for ComboPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Combo>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_combo))
+            .add_system_set(SystemSet::on_enter(GameState::Respawning).with_system(reset_combo))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(combo_update.after(snake_eating)),
+            );
+    }
+}
+
+fn reset_combo(mut combo: ResMut<Combo>) {
+    *combo = Combo::default();
+}
+
+fn combo_update(
+    mut combo: ResMut<Combo>,
+    mut score: ResMut<Score>,
+    rules: Res<GameRules>,
+    effects: Res<ActiveEffects>,
+    mut sprint: ResMut<Sprint>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+) {
+    let mut ate = false;
+    for eaten in eaten_reader.iter() {
+        ate = true;
+        // Poison breaks the streak instead of extending it.
+        if eaten.kind == FoodKind::Poison {
+            *combo = Combo::default();
+            continue;
+        }
+        combo.streak = if combo.ticks_left > 0 {
+            combo.streak + 1
+        } else {
+            1
+        };
+        combo.ticks_left = COMBO_WINDOW;
+        score.0 += sprint
+            .halve(combo.bonus() * effects.score_multiplier() * rules.mirror.score_multiplier());
+    }
+    if !ate {
+        combo.ticks_left = combo.ticks_left.saturating_sub(1);
+        if combo.ticks_left == 0 {
+            combo.streak = 0;
+        }
+    }
+}
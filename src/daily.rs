@@ -0,0 +1,66 @@
+//! The daily challenge: a board everyone shares for the day. The level and
+//! rules are fixed, the obstacles and food follow from the date, and the
+//! day's best scores are kept apart from the other high scores.
+
+use bevy::prelude::*;
+use chrono::Datelike;
+
+use crate::level::CurrentLevel;
+use crate::menu::{menu_input, MenuCursor};
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+
+/// Set while a daily challenge is being played, holding the rules and level
+/// picked on the menu so they can be put back afterwards.
+#[derive(Default)]
+struct Daily(Option<(GameRules, CurrentLevel)>);
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Daily>()
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_daily))
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(start_daily.before(menu_input)),
+            );
+    }
+}
+
+/// The seed for today's board. The date is taken in UTC, so everyone plays
+/// the same board at the same moment wherever they are.
+pub fn todays_seed() -> u64 {
+    let today = chrono::Utc::now().date_naive();
+    today.year() as u64 * 10_000 + today.month() as u64 * 100 + today.day() as u64
+}
+
+/// Swaps in the challenge's rules and level just before the menu starts the
+/// run.
+fn start_daily(
+    kbd_input: Res<Input<KeyCode>>,
+    mut daily: ResMut<Daily>,
+    mut rules: ResMut<GameRules>,
+    mut current_level: ResMut<CurrentLevel>,
+    cursor: Res<MenuCursor>,
+) {
+    if rules.mode != GameMode::Daily
+        || !cursor.on_play()
+        || !kbd_input.just_pressed(KeyCode::Return)
+    {
+        return;
+    }
+    let picked = std::mem::replace(&mut *rules, GameRules::daily());
+    let level = std::mem::replace(&mut *current_level, CurrentLevel::default_level());
+    daily.0 = Some((picked, level));
+}
+
+fn end_daily(
+    mut daily: ResMut<Daily>,
+    mut rules: ResMut<GameRules>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    if let Some((picked, level)) = daily.0.take() {
+        *rules = picked;
+        *current_level = level;
+    }
+}
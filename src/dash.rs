@@ -0,0 +1,99 @@
+//! Dashing. Holding the dash key moves a player's snake two cells a tick
+//! instead of one, as long as it has the energy: each dash uses up
+//! `DASH_COST` and each piece of food eaten gives back `FOOD_ENERGY`. Both
+//! cells are checked for collisions, see `snake_movement`.
+
+use bevy::prelude::*;
+
+use crate::input_map::InputMap;
+use crate::replay::Playback;
+use crate::rules::GameRules;
+use crate::state::GameState;
+use crate::{snake_movement, GrowthEvent, Player};
+
+pub const MAX_ENERGY: u32 = 100;
+const DASH_COST: u32 = 20;
+const FOOD_ENERGY: u32 = 20;
+
+#[derive(Component)]
+pub struct Dash {
+    pub energy: u32,
+    requested: bool,
+    /// Whether the snake dashed on the last tick.
+    pub dashed: bool,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        Self {
+            energy: MAX_ENERGY,
+            requested: false,
+            dashed: false,
+        }
+    }
+}
+
+impl Dash {
+    /// Dashes on the next tick if there's energy for it then.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Whether to dash this tick, using up the energy if so.
+    pub fn take(&mut self) -> bool {
+        self.dashed = self.requested && self.energy >= DASH_COST;
+        self.requested = false;
+        if self.dashed {
+            self.energy -= DASH_COST;
+        }
+        self.dashed
+    }
+}
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(equip_players)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(dash_input.before(snake_movement)),
+            )
+            .add_system(refill_energy);
+    }
+}
+
+/// Every player's snake can dash, starting out with full energy.
+fn equip_players(mut commands: Commands, players: Query<Entity, (With<Player>, Without<Dash>)>) {
+    for entt in players.iter() {
+        commands.entity(entt).insert(Dash::default());
+    }
+}
+
+fn dash_input(
+    kbd_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    rules: Res<GameRules>,
+    playback: Res<Playback>,
+    mut players: Query<(&Player, &mut Dash)>,
+) {
+    for (player, mut dash) in players.iter_mut() {
+        // A replay dashes where the recording says, see `replay`.
+        if player.0 == 1 && playback.is_active() {
+            continue;
+        }
+        let controls = input_map.player(player.0, rules.mode.two_player());
+        if kbd_input.any_pressed(controls.dash.iter().copied()) {
+            dash.request();
+        }
+    }
+}
+
+fn refill_energy(mut growth_reader: EventReader<GrowthEvent>, mut dashes: Query<&mut Dash>) {
+    // Extra length from the same food comes without points.
+    for growth in growth_reader.iter().filter(|growth| growth.value > 0) {
+        if let Ok(mut dash) = dashes.get_mut(growth.snake) {
+            dash.energy = (dash.energy + FOOD_ENERGY).min(MAX_ENERGY);
+        }
+    }
+}
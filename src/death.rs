@@ -0,0 +1,104 @@
+//! The dying snake's last moments: it flashes red, then its segments pop one
+//! by one from the tail to the head, each in a burst of particles. Only then
+//! does the run go on, to respawning or the results screen.
+//!
+//! The segments are hidden rather than despawned, so the results screen can
+//! still tell how long the snake was.
+
+use bevy::prelude::*;
+
+use crate::particles::spawn_burst;
+use crate::state::{GameState, GameStateEvent};
+use crate::{Grid, SnakeHead, SnakeSegments};
+
+const DEATH_COLOR: Color = Color::rgb(0.9, 0.1, 0.1);
+/// Seconds of flashing before the segments start popping.
+const FLASH_TIME: f32 = 0.6;
+const FLASH_INTERVAL: f32 = 0.1;
+/// Seconds between pops, shortened for long snakes so popping never takes
+/// longer than `MAX_POP_TIME`.
+const POP_INTERVAL: f32 = 0.05;
+const MAX_POP_TIME: f32 = 1.0;
+
+/// The snakes dying and where the run goes once they're gone.
+#[derive(Default)]
+pub struct Dying {
+    snakes: Vec<Entity>,
+    then: Option<GameStateEvent>,
+    elapsed: f32,
+}
+
+impl Dying {
+    pub fn start(&mut self, snakes: Vec<Entity>, then: GameStateEvent) {
+        *self = Self {
+            snakes,
+            then: Some(then),
+            elapsed: 0.0,
+        };
+    }
+}
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Dying>()
+            .add_system_set(SystemSet::on_update(GameState::Dying).with_system(animate_death));
+    }
+}
+
+fn animate_death(
+    mut commands: Commands,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut dying: ResMut<Dying>,
+    snakes: Query<(&SnakeHead, &SnakeSegments)>,
+    mut sprites: Query<(&mut Sprite, &mut Visibility, &Transform)>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    dying.elapsed += time.delta_seconds();
+    let window = windows.get_primary().unwrap();
+    let mut done = true;
+    for (head, segments) in dying
+        .snakes
+        .iter()
+        .filter_map(|snake| snakes.get(*snake).ok())
+    {
+        if dying.elapsed < FLASH_TIME {
+            let color = if ((dying.elapsed / FLASH_INTERVAL) as u32).is_multiple_of(2) {
+                DEATH_COLOR
+            } else {
+                head.color
+            };
+            for entt in segments.iter() {
+                if let Ok((mut sprite, ..)) = sprites.get_mut(*entt) {
+                    sprite.color = color;
+                }
+            }
+            done = false;
+            continue;
+        }
+        let interval = POP_INTERVAL.min(MAX_POP_TIME / segments.len() as f32);
+        let popped = ((dying.elapsed - FLASH_TIME) / interval) as usize + 1;
+        done &= popped >= segments.len();
+        for entt in segments.iter().rev().take(popped) {
+            if let Ok((_, mut visibility, transform)) = sprites.get_mut(*entt) {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                    spawn_burst(
+                        &mut commands,
+                        transform.translation.truncate(),
+                        grid.cell_size(window),
+                        DEATH_COLOR,
+                    );
+                }
+            }
+        }
+    }
+    if done {
+        if let Some(then) = dying.then.take() {
+            writer.send(then);
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! Step-through view of the autopilot's search. F6 during a run freezes the
+//! board and each press of Space explores one more cell, showing the open
+//! and closed cells with the cost of reaching each one (g) and the estimate
+//! of the cost left (h).
+//!
+//! The board is a sandbox: walls, mud and water are painted with the left
+//! mouse button and erased with the right, and the start and food can be
+//! dragged around. V on the menu opens it on an empty board. B, P and K fill
+//! it with a new maze, which also becomes the level for the next run.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::autopilot::{heuristic_input, Strategy};
+use crate::demo::Demo;
+use crate::food::{Food, FoodKind};
+use crate::level::CurrentLevel;
+use crate::maze::MazeAlgorithm;
+use crate::pathfinding::{Algorithm, Board, Search, SearchOptions};
+use crate::portals::Portal;
+use crate::rng::GameRng;
+use crate::rules::GameRules;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::terrain::Terrain;
+use crate::ui;
+use crate::walls::Wall;
+use crate::{
+    cursor_to_grid, Grid, Player, Position, Size, SnakeHead, SnakeSegment, CLEAR_COLOR, SNAKE_COLOR,
+};
+
+const WALL_COLOR: Color = Color::BLACK;
+const PORTAL_COLOR: Color = Color::rgb(0.3, 0.3, 0.9);
+const GOAL_COLOR: Color = Color::rgb(1.0, 0.65, 0.0);
+const OPEN_COLOR: Color = Color::rgba(0.2, 0.8, 0.3, 0.45);
+const CLOSED_COLOR: Color = Color::rgba(0.8, 0.3, 0.2, 0.45);
+const PATH_COLOR: Color = Color::WHITE;
+/// The sandbox board covers the frozen game, with the search drawn on top.
+const BACKDROP_Z: f32 = 0.4;
+const TILE_Z: f32 = 0.45;
+const CELL_Z: f32 = 0.5;
+const PATH_Z: f32 = 0.6;
+const LABEL_Z: f32 = 0.7;
+const LABEL_FONT_SIZE: f32 = 11.0;
+const PANEL_FONT_SIZE: f32 = 18.0;
+
+/// The board as it was when the debugger opened, and how far to run the
+/// search over it.
+///
+/// The search borrows its board, so rather than keeping one around it's run
+/// again up to the current step whenever something changes. The board is
+/// small enough for that to be instant.
+struct DebugSearch {
+    grid: Grid,
+    blocked: Vec<Position>,
+    portals: Vec<(Position, Position)>,
+    wrap_edges: bool,
+    terrain: HashMap<Position, Terrain>,
+    start: Position,
+    targets: Vec<Position>,
+    algorithm: Algorithm,
+    expansions: usize,
+    /// What the left mouse button paints, walls when `None`.
+    brush: Option<Terrain>,
+}
+
+impl DebugSearch {
+    /// Whether a wall or terrain could be painted on `pos`.
+    fn is_paintable(&self, pos: Position) -> bool {
+        pos != self.start
+            && !self.targets.contains(&pos)
+            && !self.portals.iter().any(|(portal, _)| *portal == pos)
+    }
+
+    /// Whether the start or food could move onto `pos`.
+    fn is_free(&self, pos: Position) -> bool {
+        self.is_paintable(pos) && !self.blocked.contains(&pos)
+    }
+
+    fn board(&self) -> Board<'_> {
+        Board {
+            grid: self.grid,
+            blocked: &self.blocked,
+            portals: self.portals.clone(),
+            wrap_edges: self.wrap_edges,
+            costs: self
+                .terrain
+                .iter()
+                .map(|(pos, terrain)| (*pos, terrain.cost()))
+                .collect(),
+            // Snakes are frozen in place here, walls like any other.
+            vacates: HashMap::new(),
+        }
+    }
+}
+
+/// What the left mouse button is moving while held.
+enum Drag {
+    Start,
+    Goal(usize),
+}
+
+#[derive(Component)]
+struct DebugCell;
+
+#[derive(Component)]
+struct DebugPanel;
+
+#[derive(Component)]
+struct DebugBackdrop;
+
+pub struct DebuggerPlugin;
+
+impl Plugin for DebuggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Playing).with_system(open_input))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Debugger)
+                    .with_system(snapshot_board)
+                    .with_system(spawn_backdrop)
+                    .with_system(spawn_panel),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Debugger)
+                    .with_system(step_input)
+                    .with_system(brush_input)
+                    .with_system(maze_input)
+                    .with_system(edit_board)
+                    .with_system(heuristic_input)
+                    .with_system(close_input)
+                    .with_system(
+                        draw_search
+                            .after(step_input)
+                            .after(brush_input)
+                            .after(maze_input)
+                            .after(edit_board)
+                            .after(heuristic_input),
+                    ),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Debugger).with_system(despawn_overlay));
+    }
+}
+
+fn open_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    demo: Res<Demo>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if !demo.is_active() && take_just_pressed(&mut kbd_input, [KeyCode::F6]) {
+        writer.send(GameStateEvent::OpenDebugger);
+    }
+}
+
+fn close_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::F6, KeyCode::Escape]) {
+        // Opened from the menu there's no run to go back to.
+        if state.inactives().is_empty() {
+            writer.send(GameStateEvent::ReturnToMenu);
+        } else {
+            writer.send(GameStateEvent::Resume);
+        }
+    }
+}
+
+/// Searches from the first player's head, the same way the autopilot would.
+/// From the menu the board starts out empty.
+fn snapshot_board(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    strategy: Res<Strategy>,
+    heads: Query<(&Position, &Player), With<SnakeHead>>,
+    blocked: Query<&Position, Or<(With<Wall>, With<SnakeSegment>, With<SnakeHead>)>>,
+    food: Query<(&Position, &Food)>,
+    portals: Query<(&Position, &Portal)>,
+    terrain: Query<(&Position, &Terrain)>,
+) {
+    let algorithm = match *strategy {
+        Strategy::Search(algorithm) => algorithm,
+        Strategy::Hamiltonian => Algorithm::AStar,
+    };
+    let head = heads.iter().find(|(_, player)| player.0 == 1);
+    let start = match head {
+        Some((pos, _)) if !state.inactives().is_empty() => *pos,
+        _ => {
+            let middle = grid.height as i32 / 2;
+            commands.insert_resource(DebugSearch {
+                grid: *grid,
+                blocked: Vec::new(),
+                portals: Vec::new(),
+                wrap_edges: false,
+                terrain: HashMap::new(),
+                start: Position {
+                    x: grid.width as i32 / 4,
+                    y: middle,
+                },
+                targets: vec![Position {
+                    x: grid.width as i32 * 3 / 4,
+                    y: middle,
+                }],
+                algorithm,
+                expansions: 0,
+                brush: None,
+            });
+            return;
+        }
+    };
+    commands.insert_resource(DebugSearch {
+        grid: *grid,
+        // The start can be dragged away, so the head isn't left behind as a
+        // wall.
+        blocked: blocked
+            .iter()
+            .copied()
+            .filter(|pos| *pos != start)
+            .collect(),
+        portals: portals
+            .iter()
+            .map(|(pos, portal)| (*pos, portal.exit))
+            .collect(),
+        wrap_edges: rules.wrap_edges,
+        terrain: terrain
+            .iter()
+            .map(|(pos, terrain)| (*pos, *terrain))
+            .collect(),
+        start,
+        targets: food
+            .iter()
+            .filter(|(_, food)| food.kind != FoodKind::Poison)
+            .map(|(pos, _)| *pos)
+            .collect(),
+        algorithm,
+        expansions: 0,
+        brush: None,
+    });
+}
+
+fn step_input(mut kbd_input: ResMut<Input<KeyCode>>, search: Option<ResMut<DebugSearch>>) {
+    let mut search = match search {
+        Some(search) => search,
+        None => return,
+    };
+    if take_just_pressed(&mut kbd_input, [KeyCode::Space, KeyCode::Right]) {
+        search.expansions = search.expansions.saturating_add(1);
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+        search.expansions = usize::MAX;
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::R]) {
+        search.expansions = 0;
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::F2]) {
+        search.algorithm = search.algorithm.next();
+        search.expansions = 0;
+    }
+}
+
+fn brush_input(kbd_input: Res<Input<KeyCode>>, search: Option<ResMut<DebugSearch>>) {
+    let mut search = match search {
+        Some(search) => search,
+        None => return,
+    };
+    if kbd_input.just_pressed(KeyCode::W) {
+        search.brush = None;
+    } else if kbd_input.just_pressed(KeyCode::M) {
+        search.brush = Some(Terrain::Mud);
+    } else if kbd_input.just_pressed(KeyCode::N) {
+        search.brush = Some(Terrain::Water);
+    }
+}
+
+fn maze_input(
+    kbd_input: Res<Input<KeyCode>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut rng: ResMut<GameRng>,
+    search: Option<ResMut<DebugSearch>>,
+) {
+    let mut search = match search {
+        Some(search) => search,
+        None => return,
+    };
+    let algorithm = match MazeAlgorithm::ALL
+        .into_iter()
+        .find(|algorithm| kbd_input.just_pressed(algorithm.key()))
+    {
+        Some(algorithm) => algorithm,
+        None => return,
+    };
+    let level = algorithm.generate(search.grid, rng.board());
+    let start = level.start;
+    // The food goes as far from the start as the maze allows.
+    let goal = search
+        .grid
+        .cells()
+        .filter(|pos| level.is_floor(*pos) && *pos != start)
+        .max_by_key(|pos| (pos.x - start.x).abs() + (pos.y - start.y).abs());
+    search.blocked = level.walls().collect();
+    search.portals.clear();
+    search.terrain.clear();
+    search.wrap_edges = false;
+    search.start = start;
+    search.targets = goal.into_iter().collect();
+    search.expansions = 0;
+    *current_level = CurrentLevel::custom(level);
+}
+
+fn edit_board(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mouse_input: Res<Input<MouseButton>>,
+    search: Option<ResMut<DebugSearch>>,
+    mut drag: Local<Option<Drag>>,
+) {
+    let mut search = match search {
+        Some(search) => search,
+        None => return,
+    };
+    let cell = match windows
+        .get_primary()
+        .and_then(|window| cursor_to_grid(window, *grid))
+    {
+        Some(cell) => cell,
+        None => return,
+    };
+    if mouse_input.just_pressed(MouseButton::Left) {
+        *drag = if cell == search.start {
+            Some(Drag::Start)
+        } else {
+            search
+                .targets
+                .iter()
+                .position(|target| *target == cell)
+                .map(Drag::Goal)
+        };
+    }
+    // Only touch the search when something moves, a change redraws it.
+    if mouse_input.pressed(MouseButton::Left) {
+        match (&*drag, search.brush) {
+            (Some(Drag::Start), _) if search.is_free(cell) => search.start = cell,
+            (Some(Drag::Goal(i)), _) if search.is_free(cell) => search.targets[*i] = cell,
+            (None, None) if search.is_paintable(cell) && !search.blocked.contains(&cell) => {
+                search.terrain.remove(&cell);
+                search.blocked.push(cell);
+            }
+            (None, Some(terrain))
+                if search.is_paintable(cell) && search.terrain.get(&cell) != Some(&terrain) =>
+            {
+                search.blocked.retain(|pos| *pos != cell);
+                search.terrain.insert(cell, terrain);
+            }
+            _ => {}
+        }
+    } else if mouse_input.pressed(MouseButton::Right)
+        && (search.blocked.contains(&cell) || search.terrain.contains_key(&cell))
+    {
+        search.blocked.retain(|pos| *pos != cell);
+        search.terrain.remove(&cell);
+    }
+}
+
+fn draw_search(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    search: Option<Res<DebugSearch>>,
+    options: Res<SearchOptions>,
+    cells: Query<Entity, With<DebugCell>>,
+    mut panels: Query<&mut Text, With<DebugPanel>>,
+) {
+    let search = match search {
+        Some(search) => search,
+        None => return,
+    };
+    if !search.is_changed() && !options.is_changed() {
+        return;
+    }
+    for entt in cells.iter() {
+        commands.entity(entt).despawn();
+    }
+
+    let board = search.board();
+    let mut run = Search::new(
+        &board,
+        search.algorithm,
+        &options,
+        search.start,
+        &search.targets,
+    );
+    let mut expansions = 0;
+    let mut running = true;
+    while expansions < search.expansions {
+        running = run.expand();
+        if !running {
+            break;
+        }
+        expansions += 1;
+    }
+
+    for pos in &search.blocked {
+        spawn_cell(&mut commands, *pos, WALL_COLOR, TILE_Z, 1.0);
+    }
+    for (pos, terrain) in &search.terrain {
+        spawn_cell(&mut commands, *pos, terrain.color(), TILE_Z, 1.0);
+    }
+    for (pos, _) in &search.portals {
+        spawn_cell(&mut commands, *pos, PORTAL_COLOR, TILE_Z, 1.0);
+    }
+    for pos in &search.targets {
+        spawn_cell(&mut commands, *pos, GOAL_COLOR, TILE_Z, 0.8);
+    }
+    spawn_cell(&mut commands, search.start, SNAKE_COLOR, TILE_Z, 0.8);
+
+    let open = run.open();
+    let label_style = ui::text_style(&asset_server, LABEL_FONT_SIZE);
+    let cells = run
+        .closed()
+        .iter()
+        .map(|pos| (*pos, CLOSED_COLOR))
+        .chain(open.iter().map(|pos| (*pos, OPEN_COLOR)));
+    for (pos, color) in cells {
+        spawn_cell(&mut commands, pos, color, CELL_Z, 1.0);
+        let g = run.cost(pos).unwrap_or_default();
+        let label = if search.algorithm.uses_heuristic() {
+            format!("g {}\nh {:.1}", g, run.remaining(pos))
+        } else {
+            format!("g {}", g)
+        };
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    label,
+                    label_style.clone(),
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, LABEL_Z),
+                ..default()
+            })
+            .insert(DebugCell)
+            .insert(pos);
+    }
+    let path = run.path();
+    if let Some(path) = &path {
+        for (_, cell) in path {
+            spawn_cell(&mut commands, *cell, PATH_COLOR, PATH_Z, 0.2);
+        }
+    }
+
+    let outcome = match (&path, running) {
+        (Some(path), _) => format!(
+            "found a path of {} steps costing {}",
+            path.len(),
+            path.last()
+                .and_then(|(_, end)| run.cost(*end))
+                .unwrap_or_default()
+        ),
+        (None, false) => "no path to food".to_string(),
+        (None, true) => format!("{} open", open.len()),
+    };
+    let heuristic = if search.algorithm.uses_heuristic() {
+        format!(" ({} x{})", options.heuristic.label(), options.weight)
+    } else {
+        String::new()
+    };
+    let brush = search.brush.map_or("walls", Terrain::label);
+    for mut text in panels.iter_mut() {
+        text.sections[0].value = format!(
+            "{}{}: explored {}, {}\n\
+             Space: step   Enter: finish   R: restart   F2: algorithm   F3-F5: heuristic\n\
+             LMB: paint {} or drag start and food   RMB: erase   W/M/N: wall, mud, water\n\
+             B/P/K: backtracker, Prim's or Kruskal's maze   F6: back",
+            search.algorithm.label(),
+            heuristic,
+            run.closed().len(),
+            outcome,
+            brush
+        );
+    }
+}
+
+fn spawn_cell(commands: &mut Commands, position: Position, color: Color, z: f32, size: f32) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform::from_xyz(0.0, 0.0, z),
+            ..default()
+        })
+        .insert(DebugCell)
+        .insert(position)
+        .insert(Size::square(size));
+}
+
+/// Hides the game underneath so only the sandbox board shows.
+fn spawn_backdrop(mut commands: Commands) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: CLEAR_COLOR,
+                custom_size: Some(Vec2::splat(10_000.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, BACKDROP_Z),
+            ..default()
+        })
+        .insert(DebugBackdrop);
+}
+
+fn spawn_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(32.0),
+                    left: Val::Px(12.0),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::with_section(
+                "",
+                ui::text_style(&asset_server, PANEL_FONT_SIZE),
+                TextAlignment::default(),
+            ),
+            ..default()
+        })
+        .insert(DebugPanel);
+}
+
+fn despawn_overlay(
+    mut commands: Commands,
+    overlays: Query<Entity, Or<(With<DebugCell>, With<DebugPanel>, With<DebugBackdrop>)>>,
+) {
+    for entt in overlays.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    commands.remove_resource::<DebugSearch>();
+}
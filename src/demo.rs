@@ -0,0 +1,139 @@
+//! Attract mode: after a while idle on the menu the autopilot plays a classic
+//! run until any key is pressed.
+
+use bevy::prelude::*;
+
+use crate::autopilot::Autopilot;
+use crate::net::NetSession;
+use crate::pause::pause_input;
+use crate::rules::{GameMode, GameRules};
+use crate::state::{GameState, GameStateEvent};
+use crate::ui;
+use crate::Player;
+
+/// Seconds without input on the menu before the demo starts.
+const IDLE_TIME: f32 = 15.0;
+
+/// Set while the demo is running, holding the mode picked on the menu so it
+/// can be put back afterwards.
+#[derive(Default)]
+pub struct Demo(Option<GameMode>);
+
+impl Demo {
+    pub fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+struct IdleTimer(Timer);
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(IDLE_TIME, false))
+    }
+}
+
+#[derive(Component)]
+struct DemoBanner;
+
+pub struct DemoPlugin;
+
+impl Plugin for DemoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Demo>()
+            .init_resource::<IdleTimer>()
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_demo))
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(idle_check))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(spawn_banner))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(engage_autopilot)
+                    .with_system(exit_input.before(pause_input)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Respawning).with_system(exit_input));
+    }
+}
+
+fn idle_check(
+    time: Res<Time>,
+    kbd_input: Res<Input<KeyCode>>,
+    net: Option<Res<NetSession>>,
+    mut timer: ResMut<IdleTimer>,
+    mut demo: ResMut<Demo>,
+    mut rules: ResMut<GameRules>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    // An online host waits on the menu for the other player.
+    if net.is_some() {
+        return;
+    }
+    if kbd_input.get_just_pressed().next().is_some() {
+        timer.0.reset();
+    }
+    if timer.0.tick(time.delta()).just_finished() {
+        demo.0 = Some(rules.mode);
+        rules.mode = GameMode::Classic;
+        writer.send(GameStateEvent::StartRun);
+    }
+}
+
+fn end_demo(
+    mut commands: Commands,
+    mut timer: ResMut<IdleTimer>,
+    mut demo: ResMut<Demo>,
+    mut rules: ResMut<GameRules>,
+    banners: Query<Entity, With<DemoBanner>>,
+) {
+    timer.0.reset();
+    if let Some(mode) = demo.0.take() {
+        rules.mode = mode;
+    }
+    for entt in banners.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
+
+fn spawn_banner(mut commands: Commands, asset_server: Res<AssetServer>, demo: Res<Demo>) {
+    if !demo.is_active() {
+        return;
+    }
+    commands
+        .spawn_bundle(ui::text_line(
+            "Demo - press any key",
+            ui::text_style(&asset_server, 28.0),
+        ))
+        .insert(DemoBanner);
+}
+
+/// Hands the players' snakes to the autopilot, including ones respawned
+/// after losing a life.
+fn engage_autopilot(
+    mut commands: Commands,
+    demo: Res<Demo>,
+    players: Query<Entity, (With<Player>, Without<Autopilot>)>,
+) {
+    if !demo.is_active() {
+        return;
+    }
+    for snake in players.iter() {
+        commands.entity(snake).insert(Autopilot);
+    }
+}
+
+fn exit_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    demo: Res<Demo>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if !demo.is_active() {
+        return;
+    }
+    let pressed: Vec<KeyCode> = kbd_input.get_just_pressed().copied().collect();
+    if !pressed.is_empty() {
+        // The key only ends the demo, nothing else should react to it.
+        for key in pressed {
+            kbd_input.reset(key);
+        }
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
@@ -0,0 +1,95 @@
+//! Keys and doors. A door is a wall until a player's head picks up a key
+//! with the same letter, which opens every door of that letter at once.
+
+use bevy::prelude::*;
+
+use crate::level::Level;
+use crate::tick::MovementTick;
+use crate::walls::Wall;
+use crate::{snake_movement, Player, Position, Size};
+
+/// One color per letter, cycling for letters past the end.
+const KEY_COLORS: [Color; 6] = [
+    Color::rgb(1.0, 0.85, 0.2),
+    Color::rgb(0.3, 0.9, 1.0),
+    Color::rgb(1.0, 0.4, 0.8),
+    Color::rgb(0.5, 1.0, 0.4),
+    Color::rgb(1.0, 0.55, 0.2),
+    Color::rgb(0.7, 0.6, 1.0),
+];
+
+/// A closed door. Doors are also walls, so everything that avoids walls
+/// avoids them until they open.
+#[derive(Component)]
+pub struct Door {
+    key: char,
+}
+
+#[derive(Component)]
+pub struct DoorKey {
+    key: char,
+}
+
+fn key_color(key: char) -> Color {
+    KEY_COLORS[(key as usize - 'a' as usize) % KEY_COLORS.len()]
+}
+
+/// Spawns every door and key in `level`.
+pub fn spawn_doors(commands: &mut Commands, level: &Level) {
+    for (position, key) in level.doors() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: key_color(key) * 0.6,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Door { key })
+            .insert(Wall)
+            .insert(position)
+            .insert(Size::square(1.0));
+    }
+    for (position, key) in level.keys() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: key_color(key),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(DoorKey { key })
+            .insert(position)
+            .insert(Size::square(0.5));
+    }
+}
+
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(MovementTick)
+                .with_system(pick_up_keys.after(snake_movement)),
+        );
+    }
+}
+
+fn pick_up_keys(
+    mut commands: Commands,
+    heads: Query<&Position, With<Player>>,
+    keys: Query<(Entity, &Position, &DoorKey)>,
+    doors: Query<(Entity, &Door)>,
+) {
+    for (entt, position, key) in keys.iter() {
+        if !heads.iter().any(|head| head == position) {
+            continue;
+        }
+        commands.entity(entt).despawn();
+        for (door, _) in doors.iter().filter(|(_, door)| door.key == key.key) {
+            commands.entity(door).despawn();
+        }
+    }
+}
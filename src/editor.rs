@@ -0,0 +1,336 @@
+//! Level editor: paint walls, portals, mud, water, ice, spikes and speed pads
+//! with the mouse, place the snake's spawn point and save the result as a
+//! level file.
+
+use bevy::prelude::*;
+
+use crate::doors::{spawn_doors, DoorKey};
+use crate::food::Food;
+use crate::level::{CurrentLevel, Level};
+use crate::pads::{spawn_pads, SpeedPad};
+use crate::persistence;
+use crate::portals::{spawn_portals, Portal};
+use crate::spikes::{spawn_spike, Spike};
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::terrain::{spawn_terrain, Terrain};
+use crate::ui;
+use crate::walls::{spawn_wall, Wall};
+use crate::{
+    cursor_to_grid, Direction, Grid, Position, Size, SnakeHead, SnakeSegment, SNAKE_COLOR,
+};
+
+const CUSTOM_LEVEL_FILE: &str = "levels/custom.ron";
+
+/// The level being edited. Only written back to `CurrentLevel` on save.
+struct EditorLevel(Level);
+
+/// What the left mouse button places.
+#[derive(Clone, Copy)]
+enum Brush {
+    Wall,
+    /// An end of the portal pair with this digit.
+    Portal(u32),
+    Terrain(Terrain),
+    Spike,
+    /// A speed pad pushing this way.
+    Pad(Direction),
+}
+
+const PORTAL_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+#[derive(Component)]
+struct EditorUi;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+struct SpawnMarker;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Editor).with_system(enter_editor))
+            .add_system_set(
+                SystemSet::on_update(GameState::Editor)
+                    .with_system(paint_tiles)
+                    .with_system(editor_commands)
+                    .with_system(sync_board.after(paint_tiles).after(editor_commands)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Editor).with_system(exit_editor));
+    }
+}
+
+fn enter_editor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current: Res<CurrentLevel>,
+    board: Query<Entity, Or<(With<Food>, With<SnakeSegment>, With<SnakeHead>)>>,
+) {
+    for entt in board.iter() {
+        commands.entity(entt).despawn();
+    }
+    commands.insert_resource(EditorLevel(current.level.clone()));
+    commands.insert_resource(Brush::Wall);
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: SNAKE_COLOR,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(SpawnMarker)
+        .insert(current.level.start)
+        .insert(Size::square(0.8));
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(8.0),
+                    left: Val::Px(12.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(EditorUi)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line(
+                "LMB: paint   RMB: erase   Shift+LMB: spawn   D: turn spawn",
+                ui::text_style(&asset_server, 18.0),
+            ));
+            parent.spawn_bundle(ui::text_line(
+                "W: wall brush   1-9: portal pair brush   M: mud brush   N: water brush",
+                ui::text_style(&asset_server, 18.0),
+            ));
+            parent.spawn_bundle(ui::text_line(
+                "I: ice brush   X: spike brush   P: speed pad brush (again to turn it)",
+                ui::text_style(&asset_server, 18.0),
+            ));
+            parent.spawn_bundle(ui::text_line(
+                "Ctrl+S: save   Ctrl+O: load   Esc: menu",
+                ui::text_style(&asset_server, 18.0),
+            ));
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 18.0)))
+                .insert(StatusText);
+        });
+}
+
+fn paint_tiles(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mouse_input: Res<Input<MouseButton>>,
+    kbd_input: Res<Input<KeyCode>>,
+    brush: Res<Brush>,
+    mut editor_level: ResMut<EditorLevel>,
+) {
+    let cell = match windows
+        .get_primary()
+        .and_then(|window| cursor_to_grid(window, *grid))
+    {
+        Some(cell) => cell,
+        None => return,
+    };
+    let level = &editor_level.0;
+    let shift = kbd_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    if mouse_input.pressed(MouseButton::Left) {
+        if !level.is_floor(cell) || level.start == cell {
+            return;
+        }
+        if shift {
+            editor_level.0.start = cell;
+        } else {
+            match *brush {
+                Brush::Wall => editor_level.0.set_wall(cell, true),
+                // A pair only has two ends, further clicks are ignored until
+                // one is erased.
+                Brush::Portal(pair) if level.portal_count(pair) < 2 => {
+                    editor_level.0.set_portal(cell, pair)
+                }
+                Brush::Portal(_) => {}
+                Brush::Terrain(terrain) => editor_level.0.set_terrain(cell, terrain),
+                Brush::Spike => editor_level.0.set_spike(cell),
+                Brush::Pad(direction) => editor_level.0.set_pad(cell, direction),
+            }
+        }
+    } else if mouse_input.pressed(MouseButton::Right) && !level.is_floor(cell) {
+        editor_level.0.set_wall(cell, false);
+    }
+}
+
+fn editor_commands(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut editor_level: ResMut<EditorLevel>,
+    mut brush: ResMut<Brush>,
+    mut current: ResMut<CurrentLevel>,
+    mut state_writer: EventWriter<GameStateEvent>,
+    mut status: Query<&mut Text, With<StatusText>>,
+) {
+    let ctrl = kbd_input.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let mut message = None;
+    if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        state_writer.send(GameStateEvent::ReturnToMenu);
+    } else if kbd_input.just_pressed(KeyCode::D) {
+        let start_direction = &mut editor_level.0.start_direction;
+        *start_direction = clockwise(*start_direction);
+        message = Some(format!("Spawn facing {:?}", start_direction));
+    } else if kbd_input.just_pressed(KeyCode::P) {
+        let direction = match *brush {
+            Brush::Pad(direction) => clockwise(direction),
+            _ => Direction::Up,
+        };
+        *brush = Brush::Pad(direction);
+        message = Some(format!("Painting speed pads pushing {:?}", direction));
+    } else if kbd_input.just_pressed(KeyCode::W) {
+        *brush = Brush::Wall;
+        message = Some("Painting walls".to_string());
+    } else if kbd_input.just_pressed(KeyCode::M) {
+        *brush = Brush::Terrain(Terrain::Mud);
+        message = Some("Painting mud".to_string());
+    } else if kbd_input.just_pressed(KeyCode::N) {
+        *brush = Brush::Terrain(Terrain::Water);
+        message = Some("Painting water".to_string());
+    } else if kbd_input.just_pressed(KeyCode::I) {
+        *brush = Brush::Terrain(Terrain::Ice);
+        message = Some("Painting ice".to_string());
+    } else if kbd_input.just_pressed(KeyCode::X) {
+        *brush = Brush::Spike;
+        message = Some("Painting spikes".to_string());
+    } else if let Some(index) = PORTAL_KEYS
+        .iter()
+        .position(|key| kbd_input.just_pressed(*key))
+    {
+        let pair = index as u32 + 1;
+        *brush = Brush::Portal(pair);
+        message = Some(format!("Painting portal pair {}", pair));
+    } else if ctrl && kbd_input.just_pressed(KeyCode::S) {
+        message = Some(save_level(&mut editor_level.0, &mut current));
+    } else if ctrl && kbd_input.just_pressed(KeyCode::O) {
+        message = Some(load_level(&mut editor_level.0));
+    }
+    if let Some(message) = message {
+        for mut text in status.iter_mut() {
+            text.sections[0].value = message.clone();
+        }
+    }
+}
+
+fn clockwise(direction: Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Right,
+        Direction::Right => Direction::Down,
+        Direction::Down => Direction::Left,
+        Direction::Left => Direction::Up,
+    }
+}
+
+fn save_level(level: &mut Level, current: &mut CurrentLevel) -> String {
+    let path = match persistence::data_path(CUSTOM_LEVEL_FILE) {
+        Some(path) => path,
+        None => return "No config directory to save to".to_string(),
+    };
+    if let Err(err) = level.validate() {
+        return format!("Can't save: {}", err);
+    }
+    level.name = "Custom".to_string();
+    match level.save(&path) {
+        Ok(()) => {
+            *current = CurrentLevel::custom(level.clone());
+            format!("Saved to {}", path.display())
+        }
+        Err(err) => format!("Failed to save: {}", err),
+    }
+}
+
+fn load_level(level: &mut Level) -> String {
+    let path = match persistence::data_path(CUSTOM_LEVEL_FILE) {
+        Some(path) => path,
+        None => return "No config directory to load from".to_string(),
+    };
+    match Level::load(&path) {
+        Ok(loaded) => {
+            *level = loaded;
+            format!("Loaded {}", path.display())
+        }
+        Err(err) => format!("Failed to load: {}", err),
+    }
+}
+
+/// Rebuilds the wall, door, portal, terrain, spike and pad sprites and moves the spawn
+/// marker whenever the edited level changes. A loaded level can be a different
+/// size, which resizes the board.
+fn sync_board(
+    mut commands: Commands,
+    editor_level: Res<EditorLevel>,
+    mut grid: ResMut<Grid>,
+    tiles: Query<
+        Entity,
+        Or<(
+            With<Wall>,
+            With<Portal>,
+            With<Terrain>,
+            With<DoorKey>,
+            With<Spike>,
+            With<SpeedPad>,
+        )>,
+    >,
+    mut markers: Query<&mut Position, With<SpawnMarker>>,
+) {
+    if !editor_level.is_changed() {
+        return;
+    }
+    if *grid != Grid::of(&editor_level.0) {
+        *grid = Grid::of(&editor_level.0);
+    }
+    for entt in tiles.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    for position in editor_level.0.walls() {
+        spawn_wall(&mut commands, position);
+    }
+    spawn_doors(&mut commands, &editor_level.0);
+    for (position, terrain) in editor_level.0.terrain() {
+        spawn_terrain(&mut commands, position, terrain);
+    }
+    for position in editor_level.0.spikes() {
+        spawn_spike(&mut commands, position);
+    }
+    spawn_pads(&mut commands, &editor_level.0);
+    spawn_portals(&mut commands, &editor_level.0);
+    for mut marker in markers.iter_mut() {
+        *marker = editor_level.0.start;
+    }
+}
+
+fn exit_editor(
+    mut commands: Commands,
+    ui: Query<Entity, With<EditorUi>>,
+    markers: Query<Entity, With<SpawnMarker>>,
+) {
+    for entt in ui.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    for entt in markers.iter() {
+        commands.entity(entt).despawn();
+    }
+    commands.remove_resource::<EditorLevel>();
+    commands.remove_resource::<Brush>();
+}
@@ -0,0 +1,142 @@
+//! Computer-controlled snakes that roam the board and compete for food. The
+//! player dies on running into one, and they die on running into anything.
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::autopilot::{safe_directions, DIRECTIONS};
+use crate::food::{free_cells, Food, FoodKind, Occupying};
+use crate::rng::GameRng;
+use crate::rules::GameRules;
+use crate::state::GameState;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::walls::Wall;
+use crate::{
+    snake_movement, spawn_snake, Direction, Grid, Player, Position, SnakeHead, SnakeSegment,
+};
+
+const ENEMY_COLOR: Color = Color::rgb(0.95, 0.35, 0.3);
+/// Seconds between checks for missing enemies, which also gives the player
+/// a head start at the beginning of a run.
+const SPAWN_INTERVAL: f32 = 3.0;
+/// Enemies never appear closer than this to the player's head.
+const MIN_SPAWN_DISTANCE: i32 = 6;
+/// Chance each tick that an enemy takes a random safe turn instead of
+/// heading for food, so they roam rather than beeline.
+const WANDER_CHANCE: f32 = 0.15;
+
+/// Marks the head of a computer-controlled snake.
+#[derive(Component)]
+pub struct Enemy;
+
+struct EnemySpawner(Timer);
+
+impl Default for EnemySpawner {
+    fn default() -> Self {
+        Self(Timer::from_seconds(SPAWN_INTERVAL, true))
+    }
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnemySpawner>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_spawner))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(enemy_steering.before(snake_movement))
+                    .with_system(spawn_enemies.after(snake_movement)),
+            );
+    }
+}
+
+fn reset_spawner(mut spawner: ResMut<EnemySpawner>) {
+    *spawner = EnemySpawner::default();
+}
+
+/// Tops the board up to the configured number of enemies, one at a time.
+fn spawn_enemies(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    rules: Res<GameRules>,
+    mut spawner: ResMut<EnemySpawner>,
+    mut rng: ResMut<GameRng>,
+    grid: Res<Grid>,
+    enemies: Query<(), With<Enemy>>,
+    players: Query<&Position, With<Player>>,
+    occupied: Query<&Position, Occupying>,
+) {
+    if !spawner.0.tick(move_timer.step()).just_finished()
+        || enemies.iter().count() >= rules.enemies as usize
+    {
+        return;
+    }
+    let free = free_cells(*grid, occupied.iter());
+    let far_from_player = |pos: &Position| {
+        players
+            .iter()
+            .all(|head| (head.x - pos.x).abs() + (head.y - pos.y).abs() >= MIN_SPAWN_DISTANCE)
+    };
+    // Room for the tail behind and a couple of cells ahead.
+    let mut spots: Vec<(Position, Direction)> = free
+        .iter()
+        .filter(|pos| far_from_player(pos))
+        .flat_map(|pos| DIRECTIONS.map(|direction| (*pos, direction)))
+        .filter(|(pos, direction)| {
+            let ahead = pos.step(*direction);
+            [
+                pos.step(direction.opposite()),
+                ahead,
+                ahead.step(*direction),
+            ]
+            .iter()
+            .all(|cell| free.contains(cell))
+        })
+        .collect();
+    spots.shuffle(rng.enemies());
+    if let Some((pos, direction)) = spots.first() {
+        let enemy = spawn_snake(&mut commands, *pos, *direction, ENEMY_COLOR);
+        commands.entity(enemy).insert(Enemy);
+    }
+}
+
+/// Picks each enemy's next direction: towards the closest edible food,
+/// avoiding anything that would kill it.
+fn enemy_steering(
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    mut rng: ResMut<GameRng>,
+    mut enemies: Query<(&mut SnakeHead, &Position), With<Enemy>>,
+    blocked: Query<&Position, Or<(With<Wall>, With<SnakeSegment>, With<SnakeHead>)>>,
+    food: Query<(&Position, &Food)>,
+) {
+    let blocked: Vec<Position> = blocked.iter().copied().collect();
+    let targets: Vec<Position> = food
+        .iter()
+        .filter(|(_, food)| food.kind != FoodKind::Poison)
+        .map(|(pos, _)| *pos)
+        .collect();
+    for (mut head, pos) in enemies.iter_mut() {
+        let safe = safe_directions(*grid, *pos, head.direction, &blocked, rules.wrap_edges);
+        let distance_to_food = |direction: &Direction| {
+            let next = pos.step(*direction);
+            targets
+                .iter()
+                .map(|target| (target.x - next.x).abs() + (target.y - next.y).abs())
+                .min()
+                .unwrap_or(0)
+        };
+        let choice = if rng.enemies().gen::<f32>() < WANDER_CHANCE || targets.is_empty() {
+            safe.choose(rng.enemies()).copied()
+        } else {
+            safe.iter().copied().min_by_key(distance_to_food)
+        };
+        // With nowhere safe to go it keeps going and hits whatever is ahead.
+        if let Some(direction) = choice {
+            head.direction = direction;
+        }
+    }
+}
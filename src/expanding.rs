@@ -0,0 +1,47 @@
+//! The expanding board mode. Every time the player's snake reaches another
+//! `MILESTONE_LENGTH` segments the board grows by two cells in each
+//! direction, up to `MAX_GROWTHS` times. The new rows and columns are added
+//! along the top and right so nothing already on the board has to move.
+
+use bevy::prelude::*;
+
+use crate::level::CurrentLevel;
+use crate::rules::{GameMode, GameRules};
+use crate::tick::MovementTick;
+use crate::{player_length, snake_growth, Grid, Player, SnakeSegments};
+
+const MILESTONE_LENGTH: usize = 5;
+/// Cells added to the width and the height each time.
+const GROWTH: u32 = 2;
+/// Twenty cells more each way, 40x40 from the usual 20x20.
+const MAX_GROWTHS: u32 = 10;
+
+pub struct ExpandingPlugin;
+
+impl Plugin for ExpandingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(MovementTick)
+                .with_system(grow_board.after(snake_growth)),
+        );
+    }
+}
+
+fn grow_board(
+    rules: Res<GameRules>,
+    current: Res<CurrentLevel>,
+    mut grid: ResMut<Grid>,
+    players: Query<(&Player, &SnakeSegments)>,
+) {
+    if rules.mode != GameMode::Expanding {
+        return;
+    }
+    // Every run starts out on the level's own size, see `board`.
+    let growths = (grid.width - current.level.width) / GROWTH;
+    let next_milestone = MILESTONE_LENGTH * (growths as usize + 1);
+    if growths < MAX_GROWTHS && player_length(&players) >= next_milestone {
+        grid.width += GROWTH;
+        grid.height += GROWTH;
+    }
+}
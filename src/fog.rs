@@ -0,0 +1,138 @@
+//! Fog of war. With `GameRules::fog` on, the board is darkened everywhere
+//! except close to the players' heads, and every few seconds a ping briefly
+//! shows where the food is.
+
+use bevy::prelude::*;
+
+use crate::board::reset_grid;
+use crate::food::Food;
+use crate::rules::GameRules;
+use crate::state::GameState;
+use crate::{cell_center, Grid, Player, Position, Size};
+
+/// How far from a head cells stay visible, in cells.
+const VISIBLE_RADIUS: f32 = 4.0;
+const FOG_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.9);
+/// Above everything else on the board.
+const FOG_Z: f32 = 0.9;
+const PING_INTERVAL: f32 = 5.0;
+/// Seconds a ping takes to fade back into the fog.
+const PING_FADE: f32 = 1.0;
+
+/// The fog over one cell. It doesn't get a `Position` of its own, so it
+/// isn't mistaken for something standing on the cell.
+#[derive(Component)]
+struct FogCell(Position);
+
+struct PingTimer(Timer);
+
+impl Default for PingTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(PING_INTERVAL, true))
+    }
+}
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PingTimer>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(spawn_fog.after(reset_grid)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(resize_fog)
+                    .with_system(update_fog.after(resize_fog)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(despawn_fog))
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(despawn_fog));
+    }
+}
+
+fn spawn_cells(commands: &mut Commands, grid: Grid) {
+    for pos in grid.cells() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: FOG_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, FOG_Z),
+                ..default()
+            })
+            .insert(FogCell(pos))
+            .insert(Size::square(1.0));
+    }
+}
+
+fn spawn_fog(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    mut ping: ResMut<PingTimer>,
+    cells: Query<Entity, With<FogCell>>,
+) {
+    for entt in cells.iter() {
+        commands.entity(entt).despawn();
+    }
+    if !rules.fog {
+        return;
+    }
+    ping.0.reset();
+    spawn_cells(&mut commands, *grid);
+}
+
+/// Covers the board again when it grows.
+fn resize_fog(mut commands: Commands, grid: Res<Grid>, cells: Query<Entity, With<FogCell>>) {
+    if !grid.is_changed() || cells.is_empty() {
+        return;
+    }
+    for entt in cells.iter() {
+        commands.entity(entt).despawn();
+    }
+    spawn_cells(&mut commands, *grid);
+}
+
+fn update_fog(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut ping: ResMut<PingTimer>,
+    heads: Query<&Position, With<Player>>,
+    food: Query<&Position, With<Food>>,
+    mut cells: Query<(&FogCell, &mut Sprite, &mut Transform)>,
+) {
+    if cells.is_empty() {
+        return;
+    }
+    ping.0.tick(time.delta());
+    // Food shows through the fog right after a ping, fading back out over
+    // `PING_FADE` seconds.
+    let since_ping = ping.0.elapsed_secs();
+    let food_alpha = FOG_COLOR.a() * (since_ping / PING_FADE).min(1.0);
+    let window = windows.get_primary().unwrap();
+    for (FogCell(pos), mut sprite, mut transform) in cells.iter_mut() {
+        let center = cell_center(window, *grid, *pos);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        let visible = heads.iter().any(|head| {
+            let (dx, dy) = ((head.x - pos.x) as f32, (head.y - pos.y) as f32);
+            dx * dx + dy * dy <= VISIBLE_RADIUS * VISIBLE_RADIUS
+        });
+        let alpha = if visible {
+            0.0
+        } else if food.iter().any(|food_pos| food_pos == pos) {
+            food_alpha
+        } else {
+            FOG_COLOR.a()
+        };
+        sprite.color.set_a(alpha);
+    }
+}
+
+fn despawn_fog(mut commands: Commands, cells: Query<Entity, With<FogCell>>) {
+    for entt in cells.iter() {
+        commands.entity(entt).despawn();
+    }
+}
@@ -0,0 +1,296 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::level::CurrentLevel;
+use crate::pads::SpeedPad;
+use crate::rng::GameRng;
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+use crate::terrain::Terrain;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::{snake_eating, snake_movement, Grid, Position, Size, SnakeHead};
+
+/// How long golden food stays on the board, and for how much of any expiring
+/// item's lifetime it blinks as a warning.
+const GOLDEN_LIFETIME: f32 = 6.0;
+const EXPIRY_BLINK_TIME: f32 = 2.0;
+const BLINK_RATE: f32 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum FoodKind {
+    Normal,
+    Bonus,
+    Mega,
+    /// Shrinks the snake instead of growing it.
+    Poison,
+    /// Rare and short-lived, worth a lot of points and growth.
+    Golden,
+}
+
+impl FoodKind {
+    /// Kinds that count towards the level's food cap. The others spawn on
+    /// their own schedule, see `SpecialFoodSpawns`.
+    const REGULAR: [FoodKind; 3] = [FoodKind::Normal, FoodKind::Bonus, FoodKind::Mega];
+
+    fn color(self) -> Color {
+        match self {
+            FoodKind::Normal => Color::rgb(1.0, 0.65, 0.0),
+            FoodKind::Bonus => Color::rgb(0.3, 0.8, 1.0),
+            FoodKind::Mega => Color::rgb(1.0, 0.2, 0.6),
+            FoodKind::Poison => Color::rgb(0.55, 0.1, 0.75),
+            FoodKind::Golden => Color::GOLD,
+        }
+    }
+
+    fn size(self) -> f32 {
+        match self {
+            FoodKind::Normal => 0.8,
+            FoodKind::Bonus => 0.7,
+            FoodKind::Mega => 0.95,
+            FoodKind::Poison => 0.6,
+            FoodKind::Golden => 0.9,
+        }
+    }
+
+    /// Relative chance of this kind being picked when regular food spawns.
+    fn spawn_weight(self) -> u32 {
+        match self {
+            FoodKind::Normal => 80,
+            FoodKind::Bonus => 15,
+            FoodKind::Mega => 5,
+            FoodKind::Poison | FoodKind::Golden => 0,
+        }
+    }
+
+    /// Points awarded for eating this kind.
+    pub fn value(self) -> u32 {
+        match self {
+            FoodKind::Normal => 1,
+            FoodKind::Bonus => 3,
+            FoodKind::Mega => 5,
+            FoodKind::Poison => 0,
+            FoodKind::Golden => 15,
+        }
+    }
+
+    /// Segments the snake grows by when eating this kind.
+    pub fn growth(self) -> u32 {
+        match self {
+            FoodKind::Golden => 3,
+            FoodKind::Poison => 0,
+            _ => 1,
+        }
+    }
+
+    fn is_regular(self) -> bool {
+        Self::REGULAR.contains(&self)
+    }
+
+    fn random(rng: &mut impl Rng) -> Self {
+        *Self::REGULAR
+            .choose_weighted(rng, |kind| kind.spawn_weight())
+            .unwrap()
+    }
+}
+
+#[derive(Component)]
+pub struct Food {
+    pub kind: FoodKind,
+}
+
+/// Despawns the entity once the timer runs out, blinking for the last
+/// `EXPIRY_BLINK_TIME` seconds. The timer counts game time, see `tick`.
+#[derive(Component)]
+pub struct Expiring(pub Timer);
+
+/// A food kind that rolls a chance to appear every `interval` seconds, with at
+/// most one on the board at a time.
+struct SpecialSpawn {
+    kind: FoodKind,
+    timer: Timer,
+    chance: f32,
+}
+
+struct SpecialFoodSpawns(Vec<SpecialSpawn>);
+
+/// Seconds of play the board has been short of regular food.
+#[derive(Default)]
+struct MissingFor(f32);
+
+impl Default for SpecialFoodSpawns {
+    fn default() -> Self {
+        Self(vec![
+            SpecialSpawn {
+                kind: FoodKind::Poison,
+                timer: Timer::from_seconds(10.0, true),
+                chance: 0.5,
+            },
+            SpecialSpawn {
+                kind: FoodKind::Golden,
+                timer: Timer::from_seconds(15.0, true),
+                chance: 0.3,
+            },
+        ])
+    }
+}
+
+pub struct FoodPlugin;
+
+impl Plugin for FoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpecialFoodSpawns>()
+            .init_resource::<MissingFor>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_food_timers))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(blink_expiring))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(replenish_food.after(snake_movement))
+                    .with_system(spawn_special_food.after(snake_movement))
+                    .with_system(expire_food.after(snake_eating)),
+            );
+    }
+}
+
+/// Narrows a query of positions down to what takes up its cell. Terrain and
+/// speed pads are floor, so anything can still be put on them.
+pub type Occupying = (Without<Terrain>, Without<SpeedPad>);
+
+/// Every cell on the board that no entity currently occupies.
+pub fn free_cells<'a>(
+    grid: Grid,
+    occupied: impl IntoIterator<Item = &'a Position>,
+) -> Vec<Position> {
+    let occupied: Vec<Position> = occupied.into_iter().copied().collect();
+    grid.cells().filter(|pos| !occupied.contains(pos)).collect()
+}
+
+/// Tops the board back up to the level's food cap once eaten food has been
+/// gone for the difficulty's respawn delay.
+fn replenish_food(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    rules: Res<GameRules>,
+    mut missing_for: ResMut<MissingFor>,
+    mut rng: ResMut<GameRng>,
+    current: Res<CurrentLevel>,
+    grid: Res<Grid>,
+    food: Query<&Food>,
+    occupied: Query<&Position, Occupying>,
+) {
+    // The sequence mode deals out its own food.
+    if rules.mode == GameMode::Sequence {
+        return;
+    }
+    let regular = food.iter().filter(|f| f.kind.is_regular()).count();
+    let missing = (current.level.food.max_food as usize).saturating_sub(regular);
+    if missing == 0 {
+        missing_for.0 = 0.0;
+        return;
+    }
+    missing_for.0 += move_timer.step().as_secs_f32();
+    if missing_for.0 < rules.food_respawn_delay {
+        return;
+    }
+    missing_for.0 = 0.0;
+    // Snake segments, walls and other food all have a position, so anything
+    // left over is free floor.
+    let mut free = free_cells(*grid, occupied.iter());
+    for _ in 0..missing {
+        let mut pick = rng.next_food();
+        let kind = FoodKind::random(&mut pick);
+        if let Some(position) = random_free_cell(&mut pick, *grid, &free) {
+            free.retain(|pos| *pos != position);
+            spawn_food(&mut commands, position, kind);
+        }
+    }
+}
+
+/// Tries cells anywhere on the board until one is free, so a seeded piece
+/// lands on the same cell in every run whenever that cell is free there.
+pub fn random_free_cell(rng: &mut impl Rng, grid: Grid, free: &[Position]) -> Option<Position> {
+    if free.is_empty() {
+        return None;
+    }
+    loop {
+        let pos = Position {
+            x: rng.gen_range(0..grid.width as i32),
+            y: rng.gen_range(0..grid.height as i32),
+        };
+        if free.contains(&pos) {
+            return Some(pos);
+        }
+    }
+}
+
+fn reset_food_timers(mut spawns: ResMut<SpecialFoodSpawns>, mut missing_for: ResMut<MissingFor>) {
+    for spawn in spawns.0.iter_mut() {
+        spawn.timer.reset();
+    }
+    *missing_for = MissingFor::default();
+}
+
+fn spawn_special_food(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    mut spawns: ResMut<SpecialFoodSpawns>,
+    mut rng: ResMut<GameRng>,
+    grid: Res<Grid>,
+    food: Query<&Food>,
+    occupied: Query<&Position, Occupying>,
+) {
+    for spawn in spawns.0.iter_mut() {
+        if !spawn.timer.tick(move_timer.step()).just_finished()
+            || food.iter().any(|f| f.kind == spawn.kind)
+            || rng.special_food().gen::<f32>() >= spawn.chance
+        {
+            continue;
+        }
+        if let Some(position) = free_cells(*grid, occupied.iter()).choose(rng.special_food()) {
+            spawn_food(&mut commands, *position, spawn.kind);
+        }
+    }
+}
+
+fn expire_food(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    mut food: Query<(Entity, &Position, &mut Expiring)>,
+    heads: Query<&Position, With<SnakeHead>>,
+) {
+    for (entt, pos, mut expiring) in food.iter_mut() {
+        // Food a snake is on has just been eaten, and goes that way.
+        let eaten = heads.iter().any(|head| head == pos);
+        if expiring.0.tick(move_timer.step()).finished() && !eaten {
+            commands.entity(entt).despawn();
+        }
+    }
+}
+
+/// The timer only moves on ticks, so the blinking follows the clock.
+fn blink_expiring(time: Res<Time>, mut food: Query<(&Expiring, &mut Visibility)>) {
+    let blink_on = (time.seconds_since_startup() as f32 * BLINK_RATE).fract() < 0.5;
+    for (expiring, mut visibility) in food.iter_mut() {
+        let remaining = expiring.0.duration().as_secs_f32() - expiring.0.elapsed_secs();
+        visibility.is_visible = remaining > EXPIRY_BLINK_TIME || blink_on;
+    }
+}
+
+pub fn spawn_food(commands: &mut Commands, position: Position, kind: FoodKind) -> Entity {
+    let mut food = commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: kind.color(),
+            ..default()
+        },
+        ..default()
+    });
+    food.insert(Food { kind })
+        .insert(position)
+        .insert(Size::square(kind.size()));
+    if kind == FoodKind::Golden {
+        food.insert(Expiring(Timer::from_seconds(GOLDEN_LIFETIME, false)));
+    }
+    food.id()
+}
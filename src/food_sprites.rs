@@ -0,0 +1,164 @@
+//! Food drawn as a shape for its kind instead of a flat square: an apple, a
+//! gem for bonus food, a heart for mega food, a skull for poison and a star
+//! for golden food, so kinds can be told apart by more than their color.
+//!
+//! The shapes come from one atlas, `assets/food.png`: a row of white pieces
+//! in `FoodKind` order, which the sprite's color tints like it did the
+//! squares. Once the atlas has loaded it's cut into an image per kind. Food
+//! stays square until then.
+//!
+//! Food also swells and bobs a little, so it stands out from the walls.
+
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension};
+
+use crate::food::{Food, FoodKind};
+use crate::{position_translation, size_scaling, Grid, Position};
+
+const ATLAS_PATH: &str = "food.png";
+/// Pixels along each side of a piece.
+const PIECE_PIXELS: u32 = 32;
+/// Seconds for food to swell and shrink back.
+const PULSE_PERIOD: f32 = 1.2;
+/// How much bigger food gets at its biggest, and how far it rises, in cells.
+const PULSE_GROWTH: f32 = 0.08;
+const BOB_HEIGHT: f32 = 0.06;
+
+/// The piece for each kind in the atlas, left to right.
+fn piece(kind: FoodKind) -> usize {
+    match kind {
+        FoodKind::Normal => 0,
+        FoodKind::Bonus => 1,
+        FoodKind::Mega => 2,
+        FoodKind::Poison => 3,
+        FoodKind::Golden => 4,
+    }
+}
+
+/// Where a piece of food is in its pulse.
+#[derive(Component)]
+struct Pulse(Timer);
+
+struct FoodImages {
+    atlas: Handle<Image>,
+    /// One per piece, empty until the atlas has loaded.
+    pieces: Vec<Handle<Image>>,
+}
+
+impl FromWorld for FoodImages {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            atlas: world.resource::<AssetServer>().load(ATLAS_PATH),
+            pieces: Vec::new(),
+        }
+    }
+}
+
+/// Labels the food pulse, which scaling for the handheld's big pixels has to
+/// come after.
+#[derive(SystemLabel, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FoodPulse;
+
+pub struct FoodSpritesPlugin;
+
+impl Plugin for FoodSpritesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FoodImages>()
+            .add_system(cut_atlas)
+            .add_system(pick_shapes.after(cut_atlas))
+            .add_system(start_pulse)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                pulse
+                    .label(FoodPulse)
+                    .after(position_translation)
+                    .after(size_scaling),
+            );
+    }
+}
+
+/// Copies each piece out of the atlas into an image of its own.
+fn cut_atlas(mut food_images: ResMut<FoodImages>, mut images: ResMut<Assets<Image>>) {
+    if !food_images.pieces.is_empty() {
+        return;
+    }
+    let atlas = match images.get(&food_images.atlas) {
+        Some(atlas) => atlas,
+        None => return,
+    };
+    let size = atlas.texture_descriptor.size;
+    let format = atlas.texture_descriptor.format;
+    let pixel = format.describe().block_size as usize;
+    let row = PIECE_PIXELS as usize * pixel;
+    let pieces: Vec<Image> = (0..size.width / PIECE_PIXELS)
+        .map(|piece| {
+            let data = (0..PIECE_PIXELS as usize)
+                .flat_map(|y| {
+                    let start = y * size.width as usize * pixel + piece as usize * row;
+                    atlas.data[start..start + row].iter().copied()
+                })
+                .collect();
+            Image::new(
+                Extent3d {
+                    width: PIECE_PIXELS,
+                    height: PIECE_PIXELS,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                format,
+            )
+        })
+        .collect();
+    food_images.pieces = pieces.into_iter().map(|image| images.add(image)).collect();
+}
+
+fn pick_shapes(
+    food_images: Res<FoodImages>,
+    mut food: Query<(&Food, &mut Handle<Image>, &mut Sprite)>,
+) {
+    for (food, mut image, mut sprite) in food.iter_mut() {
+        let shape = match food_images.pieces.get(piece(food.kind)) {
+            Some(shape) => shape,
+            None => continue,
+        };
+        if *image != *shape {
+            *image = shape.clone();
+            // Sized by `Size` like the squares were.
+            sprite.custom_size = Some(Vec2::ONE);
+        }
+    }
+}
+
+/// Neighbouring food starts at different points of the pulse, so it doesn't
+/// all move in step.
+fn start_pulse(mut commands: Commands, food: Query<(Entity, &Position), Added<Food>>) {
+    for (entt, pos) in food.iter() {
+        let mut timer = Timer::from_seconds(PULSE_PERIOD, true);
+        let offset = (pos.x + pos.y).rem_euclid(4) as f32 / 4.0;
+        timer.set_elapsed(Duration::from_secs_f32(PULSE_PERIOD * offset));
+        commands.entity(entt).insert(Pulse(timer));
+    }
+}
+
+/// Runs after food has been placed and sized for its cell, and moves it on
+/// from there.
+fn pulse(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut food: Query<(&mut Pulse, &mut Transform)>,
+) {
+    let cell_size = grid.cell_size(windows.get_primary().unwrap());
+    for (mut pulse, mut transform) in food.iter_mut() {
+        pulse.0.tick(time.delta());
+        let wave = (pulse.0.percent() * TAU).sin();
+        let growth = 1.0 + PULSE_GROWTH * (wave + 1.0) / 2.0;
+        transform.scale.x *= growth;
+        transform.scale.y *= growth;
+        transform.translation.y += BOB_HEIGHT * cell_size * wave;
+    }
+}
@@ -0,0 +1,109 @@
+//! Stepping through a run one movement tick at a time, for chasing down
+//! collision bugs. F7 stops the movement tick, each press of F8 then plays
+//! exactly one tick and logs where the players' heads ended up, which way
+//! they're heading and the turns still queued. F7 again lets the run go on.
+
+use bevy::prelude::*;
+
+use crate::demo::Demo;
+use crate::state::{take_just_pressed, GameState};
+use crate::tick::MovementTick;
+use crate::ui;
+use crate::{snake_growth, Player, Position, SnakeHead};
+
+#[derive(Default)]
+pub struct FrameStep {
+    enabled: bool,
+    /// Set by a key press, taken by the next movement tick.
+    pending: bool,
+    /// Ticks stepped since stepping was turned on.
+    ticks: u32,
+}
+
+impl FrameStep {
+    /// Whether ticks wait for a key press instead of the move timer.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether a tick was asked for since the last one, clearing the request.
+    pub fn take_step(&mut self) -> bool {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[derive(Component)]
+struct FrameStepBanner;
+
+pub struct FrameStepPlugin;
+
+impl Plugin for FrameStepPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameStep>()
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(step_input))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(log_step.after(snake_growth)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(stop_stepping));
+    }
+}
+
+fn step_input(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut frame_step: ResMut<FrameStep>,
+    demo: Res<Demo>,
+    banners: Query<Entity, With<FrameStepBanner>>,
+) {
+    if demo.is_active() {
+        return;
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::F7]) {
+        *frame_step = FrameStep {
+            enabled: !frame_step.enabled,
+            ..default()
+        };
+        if frame_step.enabled {
+            commands
+                .spawn_bundle(ui::text_line(
+                    "Frame step: F8 plays one tick, F7 resumes",
+                    ui::text_style(&asset_server, 24.0),
+                ))
+                .insert(FrameStepBanner);
+        } else {
+            for entt in banners.iter() {
+                commands.entity(entt).despawn_recursive();
+            }
+        }
+    }
+    if frame_step.enabled && take_just_pressed(&mut kbd_input, [KeyCode::F8]) {
+        frame_step.pending = true;
+    }
+}
+
+fn log_step(mut frame_step: ResMut<FrameStep>, players: Query<(&Player, &Position, &SnakeHead)>) {
+    if !frame_step.enabled {
+        return;
+    }
+    frame_step.ticks += 1;
+    for (player, position, head) in players.iter() {
+        info!(
+            "Tick {}: player {} head at ({}, {}) moving {:?}, queued {:?}",
+            frame_step.ticks, player.0, position.x, position.y, head.direction, head.input_queue
+        );
+    }
+}
+
+fn stop_stepping(
+    mut commands: Commands,
+    mut frame_step: ResMut<FrameStep>,
+    banners: Query<Entity, With<FrameStepBanner>>,
+) {
+    *frame_step = FrameStep::default();
+    for entt in banners.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
@@ -0,0 +1,302 @@
+//! The results screen a run ends on, with the run's stats and buttons to
+//! retry or go back to the menu.
+
+use bevy::prelude::*;
+
+use crate::campaign::CampaignRun;
+use crate::highscore::HighScores;
+use crate::input_map::{key_names, InputMap};
+use crate::rng::GameRng;
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::stats::RunStats;
+use crate::ui;
+use crate::versus::VersusOutcome;
+use crate::{player_length, Player, SnakeSegments};
+
+const BUTTON_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.1);
+const SELECTED_COLOR: Color = Color::rgb(0.3, 0.6, 0.3);
+
+/// The button picked, Retry to begin with.
+#[derive(Default)]
+pub struct ResultsChoice {
+    menu: bool,
+}
+
+#[derive(Component)]
+struct GameOverOverlay;
+
+#[derive(Component)]
+pub struct ResultsButton {
+    menu: bool,
+}
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ResultsChoice>()
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(spawn_overlay))
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .with_system(restart_input)
+                    .with_system(button_update.after(restart_input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(despawn_overlay));
+    }
+}
+
+fn spawn_overlay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    score: Res<Score>,
+    players: Query<(&Player, &SnakeSegments)>,
+    rules: Res<GameRules>,
+    high_scores: Res<HighScores>,
+    versus: Res<VersusOutcome>,
+    rng: Res<GameRng>,
+    campaign: Res<CampaignRun>,
+    input_map: Res<InputMap>,
+    run: Res<RunStats>,
+    mut choice: ResMut<ResultsChoice>,
+) {
+    *choice = ResultsChoice::default();
+    let best = high_scores.best(rules.mode).map_or(0, |e| e.score);
+    let length = player_length(&players);
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(GameOverOverlay)
+        .with_children(|parent| match rules.mode {
+            // Zen and casual runs only end from the pause screen and never
+            // get here.
+            GameMode::Classic
+            | GameMode::Survival
+            | GameMode::Expanding
+            | GameMode::Sequence
+            | GameMode::Endless
+            | GameMode::Zen
+            | GameMode::Casual => {
+                parent.spawn_bundle(ui::text_line(
+                    "Game Over",
+                    ui::text_style(&asset_server, 64.0),
+                ));
+                parent.spawn_bundle(ui::text_line(
+                    format!("Score: {}   Best: {}", score.0, best),
+                    ui::text_style(&asset_server, 32.0),
+                ));
+                spawn_run_stats(parent, &asset_server, &run, length);
+                spawn_restart_hint(parent, &asset_server, &input_map, rng.seed());
+            }
+            GameMode::Timed => {
+                parent.spawn_bundle(ui::text_line(
+                    "Challenge Results",
+                    ui::text_style(&asset_server, 64.0),
+                ));
+                parent.spawn_bundle(ui::text_line(
+                    format!("Score: {}", score.0),
+                    ui::text_style(&asset_server, 32.0),
+                ));
+                spawn_run_stats(parent, &asset_server, &run, length);
+                let verdict = if score.0 > 0 && score.0 >= best {
+                    "New challenge record!".to_string()
+                } else {
+                    format!("Challenge best: {}", best)
+                };
+                parent.spawn_bundle(ui::text_line(verdict, ui::text_style(&asset_server, 28.0)));
+                spawn_restart_hint(parent, &asset_server, &input_map, rng.seed());
+            }
+            GameMode::Daily => {
+                parent.spawn_bundle(ui::text_line(
+                    "Daily Challenge",
+                    ui::text_style(&asset_server, 64.0),
+                ));
+                parent.spawn_bundle(ui::text_line(
+                    format!("Score: {}   Today's best: {}", score.0, best),
+                    ui::text_style(&asset_server, 32.0),
+                ));
+                spawn_run_stats(parent, &asset_server, &run, length);
+                spawn_restart_hint(parent, &asset_server, &input_map, rng.seed());
+            }
+            GameMode::Versus => {
+                let result = match versus.winner {
+                    Some(number) => format!("Player {} wins!", number),
+                    None => "Draw!".to_string(),
+                };
+                parent.spawn_bundle(ui::text_line(result, ui::text_style(&asset_server, 64.0)));
+                spawn_restart_hint(parent, &asset_server, &input_map, rng.seed());
+            }
+            GameMode::Coop => {
+                parent.spawn_bundle(ui::text_line(
+                    "Game Over",
+                    ui::text_style(&asset_server, 64.0),
+                ));
+                parent.spawn_bundle(ui::text_line(
+                    format!("Team score: {}", score.0),
+                    ui::text_style(&asset_server, 32.0),
+                ));
+                spawn_run_stats(parent, &asset_server, &run, length);
+                spawn_restart_hint(parent, &asset_server, &input_map, rng.seed());
+            }
+            GameMode::Campaign => {
+                let title = match (campaign.cleared, campaign.has_next()) {
+                    (true, true) => "Level Cleared!",
+                    (true, false) => "Campaign Complete!",
+                    (false, _) => "Game Over",
+                };
+                parent.spawn_bundle(ui::text_line(title, ui::text_style(&asset_server, 64.0)));
+                parent.spawn_bundle(ui::text_line(
+                    format!("Score: {}   Goal: length {}", score.0, campaign.goal()),
+                    ui::text_style(&asset_server, 32.0),
+                ));
+                spawn_run_stats(parent, &asset_server, &run, length);
+                if campaign.cleared && campaign.has_next() {
+                    parent.spawn_bundle(ui::text_line(
+                        format!(
+                            "Press {} for the bonus round or Esc for the menu",
+                            key_names(&input_map.restart)
+                        ),
+                        ui::text_style(&asset_server, 24.0),
+                    ));
+                } else {
+                    spawn_restart_hint(parent, &asset_server, &input_map, rng.seed());
+                }
+            }
+        });
+}
+
+/// Player one's length, how long the run lasted, the food eaten and what
+/// killed the snake, if it died.
+fn spawn_run_stats(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    run: &RunStats,
+    length: usize,
+) {
+    let seconds = run.seconds as u64;
+    parent.spawn_bundle(ui::text_line(
+        format!(
+            "Length: {}   Time: {}:{:02}   Food eaten: {}",
+            length,
+            seconds / 60,
+            seconds % 60,
+            run.food_eaten
+        ),
+        ui::text_style(asset_server, 24.0),
+    ));
+    if let Some(death) = run.death {
+        parent.spawn_bundle(ui::text_line(
+            death.label(),
+            ui::text_style(asset_server, 24.0),
+        ));
+    }
+    for skin in &run.unlocked_skins {
+        parent.spawn_bundle(ui::text_line(
+            format!(
+                "Unlocked the {} skin, U on the menu to wear it",
+                skin.label()
+            ),
+            ui::text_style(asset_server, 24.0),
+        ));
+    }
+}
+
+/// The Retry and Main Menu buttons. Also shows the run's seed, which
+/// `--seed` plays again.
+fn spawn_restart_hint(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    input_map: &InputMap,
+    seed: u64,
+) {
+    parent.spawn_bundle(ui::text_line(
+        format!("Seed: {}", seed),
+        ui::text_style(asset_server, 20.0),
+    ));
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .with_children(|row| {
+            for (menu, label) in [(false, "Retry"), (true, "Main Menu")] {
+                row.spawn_bundle(NodeBundle {
+                    style: Style {
+                        padding: Rect::all(Val::Px(8.0)),
+                        margin: Rect::all(Val::Px(12.0)),
+                        ..default()
+                    },
+                    color: UiColor(BUTTON_COLOR),
+                    ..default()
+                })
+                .insert(ResultsButton { menu })
+                .with_children(|button| {
+                    button.spawn_bundle(ui::text_line(label, ui::text_style(asset_server, 32.0)));
+                });
+            }
+        });
+    parent.spawn_bundle(ui::text_line(
+        format!(
+            "Left/Right to pick, Enter to choose\n{} retries, Esc for the menu",
+            key_names(&input_map.restart)
+        ),
+        ui::text_style(asset_server, 20.0),
+    ));
+}
+
+pub fn restart_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut choice: ResMut<ResultsChoice>,
+    buttons: Query<(), With<ResultsButton>>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    // A cleared campaign level offers the bonus round instead of buttons.
+    // They go first, so binding restart to one of their keys can't take it
+    // over.
+    if !buttons.is_empty() {
+        let arrows = [
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Tab,
+        ];
+        if take_just_pressed(&mut kbd_input, arrows) {
+            choice.menu = !choice.menu;
+        }
+        if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+            writer.send(if choice.menu {
+                GameStateEvent::ReturnToMenu
+            } else {
+                GameStateEvent::StartRun
+            });
+            return;
+        }
+    }
+    if take_just_pressed(&mut kbd_input, input_map.restart.iter().copied()) {
+        writer.send(GameStateEvent::StartRun);
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn button_update(choice: Res<ResultsChoice>, mut buttons: Query<(&ResultsButton, &mut UiColor)>) {
+    for (button, mut color) in buttons.iter_mut() {
+        color.0 = if button.menu == choice.menu {
+            SELECTED_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+    }
+}
+
+fn despawn_overlay(mut commands: Commands, overlays: Query<Entity, With<GameOverOverlay>>) {
+    for entt in overlays.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
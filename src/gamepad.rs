@@ -0,0 +1,198 @@
+//! Controller support. The d-pad or the left stick steers, Start pauses and
+//! A confirms on the menus. Controllers can be plugged in and out at any
+//! time: the first one connected steers player one and the second player
+//! two, or all of them steer the snake when playing alone. Unplugging one
+//! mid-run pauses the game.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::gameover::ResultsButton;
+use crate::input_map::{confirm_key, Controls, InputMap};
+use crate::rules::GameRules;
+use crate::state::{GameState, GameStateEvent};
+use crate::terrain::Terrain;
+use crate::{on_ice, snake_movement_input, Direction, Player, Position, SnakeHead};
+
+/// How far the stick has to be pushed before it turns the snake.
+const STICK_DEADZONE: f32 = 0.5;
+const DPAD: [(GamepadButtonType, Direction); 4] = [
+    (GamepadButtonType::DPadUp, Direction::Up),
+    (GamepadButtonType::DPadLeft, Direction::Left),
+    (GamepadButtonType::DPadRight, Direction::Right),
+    (GamepadButtonType::DPadDown, Direction::Down),
+];
+
+struct Pad {
+    gamepad: Gamepad,
+    /// Where the stick pointed last frame, so holding it only turns once.
+    stick: Option<Direction>,
+}
+
+impl Pad {
+    /// The turn asked for this frame: a d-pad press, or the stick moving to a
+    /// new direction.
+    fn turn(
+        &mut self,
+        buttons: &Input<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> Option<Direction> {
+        let stick = self.stick_direction(axes);
+        let moved = stick.filter(|_| stick != self.stick);
+        self.stick = stick;
+        DPAD.iter()
+            .find(|(button, _)| buttons.just_pressed(GamepadButton(self.gamepad, *button)))
+            .map(|(_, direction)| *direction)
+            .or(moved)
+    }
+
+    /// The stick snapped to whichever of the four directions it's pushed
+    /// furthest along, `None` inside the deadzone.
+    fn stick_direction(&self, axes: &Axis<GamepadAxis>) -> Option<Direction> {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis(self.gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        let (x, y) = (
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        if x.hypot(y) < STICK_DEADZONE {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            })
+        } else {
+            Some(if y > 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            })
+        }
+    }
+
+    fn just_pressed(&self, buttons: &Input<GamepadButton>, button: GamepadButtonType) -> bool {
+        buttons.just_pressed(GamepadButton(self.gamepad, button))
+    }
+}
+
+/// Connected controllers, in the order they were plugged in.
+#[derive(Default)]
+struct Pads(Vec<Pad>);
+
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Pads>()
+            .add_system(connect_pads)
+            .add_system(pause_button)
+            .add_system_to_stage(CoreStage::PreUpdate, confirm_button.after(InputSystem))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(pad_steering.before(snake_movement_input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::BonusRound)
+                    .with_system(pad_steering.before(snake_movement_input)),
+            );
+    }
+}
+
+fn connect_pads(
+    mut pads: ResMut<Pads>,
+    mut events: EventReader<GamepadEvent>,
+    mut state_writer: EventWriter<GameStateEvent>,
+) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                pads.0.push(Pad {
+                    gamepad: *gamepad,
+                    stick: None,
+                });
+                info!("Controller {} steers player {}", gamepad.0, pads.0.len());
+            }
+            GamepadEventType::Disconnected => {
+                pads.0.retain(|pad| pad.gamepad != *gamepad);
+                info!("Controller {} unplugged", gamepad.0);
+                // Ignored outside of a run.
+                state_writer.send(GameStateEvent::Pause);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn pad_steering(
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    rules: Res<GameRules>,
+    mut pads: ResMut<Pads>,
+    // Replays, the demo and online games steer without `Controls`.
+    mut heads: Query<(&Player, &mut SnakeHead, &Position), With<Controls>>,
+    terrain: Query<(&Position, &Terrain)>,
+) {
+    let two_player = rules.mode.two_player();
+    for (index, pad) in pads.0.iter_mut().enumerate() {
+        let direction = match pad.turn(&buttons, &axes) {
+            Some(direction) => direction,
+            None => continue,
+        };
+        let steers = if two_player { index + 1 } else { 1 };
+        for (_, mut head, pos) in heads
+            .iter_mut()
+            .filter(|(player, ..)| player.0 as usize == steers)
+        {
+            if !on_ice(&terrain, *pos) {
+                head.queue_turn(rules.mirror.apply(direction));
+            }
+        }
+    }
+}
+
+fn pause_button(
+    buttons: Res<Input<GamepadButton>>,
+    pads: Res<Pads>,
+    state: Res<State<GameState>>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if !pads
+        .0
+        .iter()
+        .any(|pad| pad.just_pressed(&buttons, GamepadButtonType::Start))
+    {
+        return;
+    }
+    match state.current() {
+        GameState::Playing => writer.send(GameStateEvent::Pause),
+        GameState::Paused => writer.send(GameStateEvent::Resume),
+        _ => {}
+    }
+}
+
+/// A presses whichever key confirms on the current screen, so every screen
+/// that takes Enter takes A as well.
+fn confirm_button(
+    buttons: Res<Input<GamepadButton>>,
+    pads: Res<Pads>,
+    state: Res<State<GameState>>,
+    input_map: Res<InputMap>,
+    results_buttons: Query<(), With<ResultsButton>>,
+    mut kbd_input: ResMut<Input<KeyCode>>,
+) {
+    if !pads
+        .0
+        .iter()
+        .any(|pad| pad.just_pressed(&buttons, GamepadButtonType::South))
+    {
+        return;
+    }
+    if let Some(key) = confirm_key(*state.current(), &input_map, !results_buttons.is_empty()) {
+        kbd_input.press(key);
+        kbd_input.release(key);
+    }
+}
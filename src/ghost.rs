@@ -0,0 +1,167 @@
+//! Racing your best run. When a run starts on the same level and rules as
+//! the best replay for its mode, it takes that replay's seed so the board
+//! plays out the same, and a translucent ghost of the best run moves along
+//! with the player. The ghost is only drawn: it has no `Position`, so
+//! nothing collides with it and food can land under it.
+
+use bevy::prelude::*;
+
+use crate::level::CurrentLevel;
+use crate::replay::{Playback, Replay};
+use crate::rng::{seed_run, GameRng};
+use crate::rules::GameRules;
+use crate::settings::Settings;
+use crate::state::GameState;
+use crate::tick::MovementTick;
+use crate::{cell_center, snake_movement, Grid, Position, Size};
+
+const GHOST_COLOR: Color = Color::rgba(0.4, 1.0, 0.2, 0.3);
+/// Under the player's snake, over the floor.
+const GHOST_Z: f32 = -0.02;
+
+/// The best run being raced, if there is one for the current run.
+#[derive(Default)]
+pub struct Ghost(Option<GhostRun>);
+
+struct GhostRun {
+    replay: Replay,
+    /// Movement ticks played so far.
+    tick: usize,
+}
+
+impl Ghost {
+    /// The seed the run should take for the ghost to be worth racing.
+    pub fn seed(&self) -> Option<u64> {
+        self.0.as_ref().map(|run| run.replay.seed)
+    }
+}
+
+/// One cell of the ghost's body.
+#[derive(Component)]
+struct GhostSegment {
+    cell: Position,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ghost>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(load_ghost.before(seed_run)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(move_ghost.after(snake_movement)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(place_ghost))
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(clear_ghost));
+    }
+}
+
+fn load_ghost(
+    mut commands: Commands,
+    mut ghost: ResMut<Ghost>,
+    settings: Res<Settings>,
+    rules: Res<GameRules>,
+    current: Res<CurrentLevel>,
+    playback: Res<Playback>,
+    segments: Query<Entity, With<GhostSegment>>,
+) {
+    for entt in segments.iter() {
+        commands.entity(entt).despawn();
+    }
+    ghost.0 = None;
+    if !settings.ghost || playback.is_active() || rules.mode.two_player() {
+        return;
+    }
+    ghost.0 = Replay::load_best(rules.mode)
+        .filter(|best| best.rules == *rules && best.level == current.level)
+        .filter(|best| !best.trail.is_empty())
+        .map(|replay| GhostRun { replay, tick: 0 });
+}
+
+/// Steps the ghost to where the best run was after the same number of
+/// ticks, growing or shrinking it to the length it had then.
+fn move_ghost(
+    mut commands: Commands,
+    mut ghost: ResMut<Ghost>,
+    rng: Res<GameRng>,
+    mut segments: Query<(Entity, &mut GhostSegment)>,
+) {
+    let run = match ghost.0.as_mut() {
+        Some(run) => run,
+        None => return,
+    };
+    let tick = run.tick;
+    run.tick += 1;
+    // A seed given on the command line or in the settings wins over the
+    // ghost's, and then the boards don't match.
+    if rng.seed() != run.replay.seed || tick >= run.replay.trail.len() {
+        ghost.0 = None;
+        for (entt, _) in segments.iter() {
+            commands.entity(entt).despawn();
+        }
+        return;
+    }
+    let length = run
+        .replay
+        .lengths
+        .iter()
+        .take_while(|(length_tick, _)| *length_tick as usize <= tick)
+        .last()
+        .map_or(1, |(_, length)| *length);
+    let cells: Vec<Position> = run.replay.trail[..=tick]
+        .iter()
+        .rev()
+        .take(length)
+        .copied()
+        .collect();
+    let mut existing: Vec<(Entity, Mut<GhostSegment>)> = segments.iter_mut().collect();
+    for (i, cell) in cells.iter().enumerate() {
+        match existing.get_mut(i) {
+            Some((_, segment)) => segment.cell = *cell,
+            None => {
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: GHOST_COLOR,
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0.0, 0.0, GHOST_Z),
+                        ..default()
+                    })
+                    .insert(GhostSegment { cell: *cell })
+                    .insert(Size::square(0.8));
+            }
+        }
+    }
+    for (entt, _) in existing.iter().skip(cells.len()) {
+        commands.entity(*entt).despawn();
+    }
+}
+
+fn place_ghost(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut segments: Query<(&GhostSegment, &mut Transform)>,
+) {
+    let window = windows.get_primary().unwrap();
+    for (segment, mut transform) in segments.iter_mut() {
+        let center = cell_center(window, *grid, segment.cell);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
+
+fn clear_ghost(
+    mut commands: Commands,
+    mut ghost: ResMut<Ghost>,
+    segments: Query<Entity, With<GhostSegment>>,
+) {
+    ghost.0 = None;
+    for entt in segments.iter() {
+        commands.entity(entt).despawn();
+    }
+}
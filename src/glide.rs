@@ -0,0 +1,86 @@
+//! Smooth movement. A snake's `Position` still moves a whole cell each tick,
+//! but its sprites slide there from the cell they were on over the wait for
+//! the next tick, so the snake looks to move at any tick rate. Jumps through
+//! a portal or across a wrapped edge aren't slid, as the sprite would cross
+//! the board on the way.
+
+use bevy::prelude::*;
+
+use crate::framestep::FrameStep;
+use crate::tick::MoveTimer;
+use crate::{cell_center, position_translation, Grid, Position, SnakeHead, SnakeSegment};
+
+/// Labels the slide, which anything following a snake's sprites around has
+/// to come after.
+#[derive(SystemLabel, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SpriteGlide;
+
+/// The cells a sprite is sliding between.
+#[derive(Component)]
+struct Glide {
+    from: Position,
+    to: Position,
+}
+
+pub struct GlidePlugin;
+
+impl Plugin for GlidePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            glide.label(SpriteGlide).after(position_translation),
+        );
+    }
+}
+
+fn glide(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    timer: Res<MoveTimer>,
+    frame_step: Res<FrameStep>,
+    mut last_progress: Local<f32>,
+    mut sprites: Query<
+        (Entity, &Position, Option<&mut Glide>, &mut Transform),
+        Or<(With<SnakeHead>, With<SnakeSegment>)>,
+    >,
+) {
+    let window = windows.get_primary().unwrap();
+    // Stepping by hand, each tick lands at once.
+    let progress = if frame_step.enabled() {
+        1.0
+    } else {
+        timer.progress()
+    };
+    // A tick came since the last frame, so anything that didn't move on it
+    // has stopped sliding.
+    let ticked = progress < *last_progress;
+    *last_progress = progress;
+    for (entt, pos, glide, mut transform) in sprites.iter_mut() {
+        let mut glide = match glide {
+            Some(glide) => glide,
+            None => {
+                commands.entity(entt).insert(Glide {
+                    from: *pos,
+                    to: *pos,
+                });
+                continue;
+            }
+        };
+        if glide.to != *pos {
+            glide.from = glide.to;
+            glide.to = *pos;
+        } else if ticked && glide.from != glide.to {
+            glide.from = glide.to;
+        }
+        let (from, to) = (glide.from, glide.to);
+        if (to.x - from.x).abs() + (to.y - from.y).abs() != 1 {
+            continue;
+        }
+        let start = cell_center(window, *grid, from);
+        let end = cell_center(window, *grid, to);
+        let center = start.lerp(end, progress);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
@@ -0,0 +1,146 @@
+//! The look of the classic handheld preset: every sprite is drawn in one of
+//! four shades of green picked by how bright it is, and sized in coarse dots
+//! like an old phone's screen. The shades can also be picked on their own,
+//! as a palette in the settings.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::biomes::Background;
+use crate::colorblind::in_palette;
+use crate::food_sprites::FoodPulse;
+use crate::rules::Difficulty;
+use crate::settings::Settings;
+use crate::{size_scaling, Grid};
+
+/// Darkest first. Each shade's brightness falls in its own band, so a color
+/// that's already been recolored stays the same.
+const SHADES: [Color; 4] = [
+    Color::rgb(0.06, 0.22, 0.06),
+    Color::rgb(0.19, 0.38, 0.19),
+    Color::rgb(0.55, 0.67, 0.06),
+    Color::rgb(0.78, 0.87, 0.45),
+];
+/// Brightness where each shade but the darkest starts.
+const SHADE_BANDS: [f32; 3] = [0.25, 0.45, 0.7];
+/// Dots across a cell. Sprites are sized in whole dots.
+const DOTS_PER_CELL: f32 = 4.0;
+
+/// Colors everything is drawn in, picked in the settings.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    FullColor,
+    /// The handheld preset's greens, at any difficulty.
+    Handheld,
+    /// Colors told apart without telling red from green, see `colorblind`.
+    Deuteranopia,
+    Protanopia,
+    /// Colors told apart without telling blue from green or yellow from
+    /// violet.
+    Tritanopia,
+}
+
+impl Palette {
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::FullColor => "Full color",
+            Palette::Handheld => "Handheld green",
+            Palette::Deuteranopia => "Deuteranopia",
+            Palette::Protanopia => "Protanopia",
+            Palette::Tritanopia => "Tritanopia",
+        }
+    }
+
+    /// The palette after this one in the settings.
+    pub fn next(self) -> Self {
+        match self {
+            Palette::FullColor => Palette::Handheld,
+            Palette::Handheld => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::FullColor,
+        }
+    }
+}
+
+pub struct HandheldPlugin;
+
+impl Plugin for HandheldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            CoreStage::PostUpdate,
+            SystemSet::new()
+                .with_system(recolor)
+                .with_system(chunky_sprites.after(size_scaling).after(FoodPulse)),
+        );
+    }
+}
+
+fn handheld(settings: &Settings) -> bool {
+    settings.difficulty == Difficulty::Handheld
+}
+
+/// Whether to draw in the handheld's shades, which the palette can ask for
+/// without the rest of the look. High contrast mode takes precedence.
+pub fn green(settings: &Settings) -> bool {
+    !settings.high_contrast && (handheld(settings) || settings.palette == Palette::Handheld)
+}
+
+/// The shade standing in for `color`, keeping its transparency.
+fn shade(color: Color) -> Color {
+    let brightness = 0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b();
+    let band = SHADE_BANDS
+        .iter()
+        .filter(|start| brightness >= **start)
+        .count();
+    let mut shade = SHADES[band];
+    shade.set_a(color.a());
+    shade
+}
+
+fn recolor(
+    settings: Res<Settings>,
+    background: Res<Background>,
+    mut clear_color: ResMut<ClearColor>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    let background = if settings.high_contrast {
+        Color::BLACK
+    } else if green(&settings) {
+        shade(background.0)
+    } else {
+        in_palette(settings.palette, background.0).unwrap_or(background.0)
+    };
+    if clear_color.0 != background {
+        clear_color.0 = background;
+    }
+    if !green(&settings) {
+        return;
+    }
+    for mut sprite in sprites.iter_mut() {
+        let shaded = shade(sprite.color);
+        if sprite.color != shaded {
+            sprite.color = shaded;
+        }
+    }
+}
+
+fn chunky_sprites(
+    settings: Res<Settings>,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    // Children are scaled along with their parent.
+    mut sprites: Query<&mut Transform, (With<Sprite>, Without<Parent>)>,
+) {
+    if !handheld(&settings) {
+        return;
+    }
+    let window = windows.get_primary().unwrap();
+    let dot = grid.cell_size(window) / DOTS_PER_CELL;
+    let snap = |length: f32| (length / dot).round().max(1.0) * dot;
+    for mut transform in sprites.iter_mut() {
+        transform.scale.x = snap(transform.scale.x);
+        transform.scale.y = snap(transform.scale.y);
+    }
+}
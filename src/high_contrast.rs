@@ -0,0 +1,345 @@
+//! High contrast mode, turned on in the settings: a black background with a
+//! white grid over it, white walls, snakes and food in bright, fully
+//! saturated colors with a white outline, and the same bright colors for the
+//! text and the rest of the UI. Grays other than the walls keep their color,
+//! as the fog and the darkness are drawn in them.
+//!
+//! Colors are put back as they were when it's turned off again.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::color::{hsla, recolor_text, GRAY_SATURATION};
+use crate::food::Food;
+use crate::food_sprites::FoodPulse;
+use crate::glide::SpriteGlide;
+use crate::settings::Settings;
+use crate::walls::Wall;
+use crate::{cell_center, Grid, Position, SnakeHead, SnakeSegment};
+
+const GRID_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.3);
+/// Pixels across a grid line.
+const GRID_LINE_WIDTH: f32 = 1.0;
+/// Over the checkerboard, under everything else.
+const GRID_Z: f32 = -0.05;
+const WALL_COLOR: Color = Color::WHITE;
+const OUTLINE_COLOR: Color = Color::WHITE;
+/// How much bigger an outline is than its sprite, and how far behind it.
+const OUTLINE_SCALE: f32 = 1.2;
+const OUTLINE_DEPTH: f32 = 0.01;
+/// Lightness colors are kept within, so they stay bright but not washed out.
+const MIN_LIGHTNESS: f32 = 0.5;
+const MAX_LIGHTNESS: f32 = 0.7;
+/// Least opacity of dark UI backgrounds.
+const MIN_PANEL_ALPHA: f32 = 0.85;
+
+/// A line of the grid, down the board between columns or across it between
+/// rows. The line before the first column or row is 0.
+#[derive(Component)]
+enum GridLine {
+    Column(u32),
+    Row(u32),
+}
+
+#[derive(Component)]
+struct Outline;
+
+/// Colors from before high contrast mode, by entity, and the color it was
+/// given so something else changing it can be told apart.
+#[derive(Default)]
+struct Originals(HashMap<Entity, (Color, Color)>);
+
+impl Originals {
+    /// The high contrast version of `color` made by `contrast`, remembering
+    /// the original. `None` if it's that already.
+    fn apply(
+        &mut self,
+        entt: Entity,
+        color: Color,
+        contrast: impl Fn(Color) -> Color,
+    ) -> Option<Color> {
+        let original = match self.0.get(&entt) {
+            Some((original, given)) if *given == color => *original,
+            _ => color,
+        };
+        let given = contrast(original);
+        self.0.insert(entt, (original, given));
+        (color != given).then_some(given)
+    }
+
+    /// The color from before, unless something else has changed it since.
+    fn restore(&mut self, entt: Entity, color: Color) -> Option<Color> {
+        self.0
+            .remove(&entt)
+            .filter(|(_, given)| *given == color)
+            .map(|(original, _)| original)
+    }
+}
+
+pub struct HighContrastPlugin;
+
+impl Plugin for HighContrastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_grid)
+            .add_system(place_grid.after(spawn_grid))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                outline
+                    .after(SpriteGlide)
+                    .after(FoodPulse)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, recolor_sprites)
+            .add_system_to_stage(CoreStage::PostUpdate, recolor_ui);
+    }
+}
+
+/// `color` bright and fully saturated, with grays left alone.
+fn bright(color: Color) -> Color {
+    let (hue, saturation, lightness, alpha) = hsla(color);
+    if saturation < GRAY_SATURATION {
+        return color;
+    }
+    let lightness = lightness.clamp(MIN_LIGHTNESS, MAX_LIGHTNESS);
+    let recolored = Color::hsla(hue, 1.0, lightness, alpha).as_rgba();
+    // Converting back and forth isn't exact, so a color that's close enough
+    // is left as it is.
+    let close = |a: f32, b: f32| (a - b).abs() < 0.002;
+    if close(color.r(), recolored.r())
+        && close(color.g(), recolored.g())
+        && close(color.b(), recolored.b())
+    {
+        color
+    } else {
+        recolored
+    }
+}
+
+/// Dark panels are made black and close to opaque, so text stands out on
+/// them. See-through layout nodes are left alone.
+fn panel(color: Color) -> Color {
+    let (_, saturation, lightness, _) = hsla(color);
+    let gray = saturation < GRAY_SATURATION && lightness < 0.5;
+    if gray && color.a() > 0.0 {
+        Color::rgba(0.0, 0.0, 0.0, color.a().max(MIN_PANEL_ALPHA))
+    } else {
+        bright(color)
+    }
+}
+
+fn spawn_grid(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    grid: Res<Grid>,
+    lines: Query<Entity, With<GridLine>>,
+) {
+    let shown = !lines.is_empty();
+    if settings.high_contrast == shown && !grid.is_changed() {
+        return;
+    }
+    for entt in lines.iter() {
+        commands.entity(entt).despawn();
+    }
+    if !settings.high_contrast {
+        return;
+    }
+    let columns = (0..=grid.width).map(GridLine::Column);
+    let rows = (0..=grid.height).map(GridLine::Row);
+    for line in columns.chain(rows) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: GRID_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, GRID_Z),
+                // Shown once `place_grid` has put it in place.
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(line);
+    }
+}
+
+/// Keeps the lines between the cells as the window is resized.
+fn place_grid(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut lines: Query<(&GridLine, &mut Transform, &mut Visibility)>,
+) {
+    let window = windows.get_primary().unwrap();
+    let cell_size = grid.cell_size(window);
+    // The lower left corner of the board.
+    let corner = cell_center(window, *grid, Position { x: 0, y: 0 }) - Vec2::splat(cell_size / 2.0);
+    for (line, mut transform, mut visibility) in lines.iter_mut() {
+        let (translation, scale) = match *line {
+            GridLine::Column(column) => (
+                Vec2::new(
+                    corner.x + column as f32 * cell_size,
+                    corner.y + grid.height as f32 * cell_size / 2.0,
+                ),
+                Vec2::new(GRID_LINE_WIDTH, grid.height as f32 * cell_size),
+            ),
+            GridLine::Row(row) => (
+                Vec2::new(
+                    corner.x + grid.width as f32 * cell_size / 2.0,
+                    corner.y + row as f32 * cell_size,
+                ),
+                Vec2::new(grid.width as f32 * cell_size, GRID_LINE_WIDTH),
+            ),
+        };
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        transform.scale = scale.extend(1.0);
+        if !visibility.is_visible {
+            visibility.is_visible = true;
+        }
+    }
+}
+
+/// Keeps an outline behind every snake segment and piece of food, in the
+/// same shape and a little bigger. Runs once they've been moved for the
+/// frame, so the outlines keep up.
+fn outline(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut outlines: Local<HashMap<Entity, Entity>>,
+    targets: Query<
+        (Entity, &Transform, &Sprite, &Handle<Image>, &Visibility),
+        (
+            Or<(With<SnakeHead>, With<SnakeSegment>, With<Food>)>,
+            Without<Outline>,
+        ),
+    >,
+    mut sprites: Query<
+        (
+            &mut Transform,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut Visibility,
+        ),
+        With<Outline>,
+    >,
+) {
+    if !settings.high_contrast {
+        for (_, outline) in outlines.drain() {
+            commands.entity(outline).despawn();
+        }
+        return;
+    }
+    for (target, transform, sprite, image, visibility) in targets.iter() {
+        let mut behind = *transform;
+        behind.translation.z -= OUTLINE_DEPTH;
+        behind.scale.x *= OUTLINE_SCALE;
+        behind.scale.y *= OUTLINE_SCALE;
+        let existing = outlines
+            .get(&target)
+            .and_then(|outline| sprites.get_mut(*outline).ok());
+        match existing {
+            Some((mut outline_transform, mut outline_sprite, mut outline_image, mut shown)) => {
+                *outline_transform = behind;
+                if outline_sprite.custom_size != sprite.custom_size {
+                    outline_sprite.custom_size = sprite.custom_size;
+                }
+                if *outline_image != *image {
+                    *outline_image = image.clone();
+                }
+                if shown.is_visible != visibility.is_visible {
+                    shown.is_visible = visibility.is_visible;
+                }
+            }
+            None => {
+                let outline = commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: OUTLINE_COLOR,
+                            custom_size: sprite.custom_size,
+                            ..default()
+                        },
+                        texture: image.clone(),
+                        transform: behind,
+                        visibility: visibility.clone(),
+                        ..default()
+                    })
+                    .insert(Outline)
+                    .id();
+                outlines.insert(target, outline);
+            }
+        }
+    }
+    // Despawned along with what they outlined.
+    outlines.retain(|target, outline| {
+        let kept = targets.contains(*target);
+        if !kept {
+            commands.entity(*outline).despawn();
+        }
+        kept
+    });
+}
+
+fn recolor_sprites(
+    settings: Res<Settings>,
+    mut originals: Local<Originals>,
+    mut sprites: Query<(Entity, &mut Sprite, Option<&Wall>), Without<Outline>>,
+) {
+    if !settings.high_contrast {
+        if !originals.0.is_empty() {
+            for (entt, mut sprite, _) in sprites.iter_mut() {
+                if let Some(color) = originals.restore(entt, sprite.color) {
+                    sprite.color = color;
+                }
+            }
+            originals.0.clear();
+        }
+        return;
+    }
+    for (entt, mut sprite, wall) in sprites.iter_mut() {
+        let recolored = if wall.is_some() {
+            originals.apply(entt, sprite.color, |color| {
+                let mut wall = WALL_COLOR;
+                wall.set_a(color.a());
+                wall
+            })
+        } else {
+            originals.apply(entt, sprite.color, bright)
+        };
+        if let Some(color) = recolored {
+            sprite.color = color;
+        }
+    }
+    originals.0.retain(|entt, _| sprites.contains(*entt));
+}
+
+/// Text is brightened where it's colored, without being put back afterwards,
+/// as the HUD rewrites its colors all the time anyway.
+fn recolor_ui(
+    settings: Res<Settings>,
+    mut originals: Local<Originals>,
+    mut nodes: Query<(Entity, &mut UiColor)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !settings.high_contrast {
+        if !originals.0.is_empty() {
+            for (entt, mut color) in nodes.iter_mut() {
+                if let Some(original) = originals.restore(entt, color.0) {
+                    color.0 = original;
+                }
+            }
+            originals.0.clear();
+        }
+        return;
+    }
+    for (entt, mut color) in nodes.iter_mut() {
+        if let Some(recolored) = originals.apply(entt, color.0, panel) {
+            color.0 = recolored;
+        }
+    }
+    originals.0.retain(|entt, _| nodes.contains(*entt));
+    for text in texts.iter_mut() {
+        recolor_text(text, |color| {
+            Some(bright(color)).filter(|bright| *bright != color)
+        });
+    }
+}
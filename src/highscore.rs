@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::persistence;
+use crate::rules::GameMode;
+
+const HIGH_SCORES_FILE: &str = "highscores.ron";
+const MAX_HIGH_SCORES: usize = 10;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub score: u32,
+    pub length: usize,
+    pub date: String,
+}
+
+/// Best runs so far, sorted from highest to lowest score. Each scored game
+/// mode keeps its own table.
+#[derive(Default, Serialize, Deserialize)]
+pub struct HighScores {
+    entries: Vec<HighScoreEntry>,
+    #[serde(default)]
+    timed: Vec<HighScoreEntry>,
+    #[serde(default)]
+    survival: Vec<HighScoreEntry>,
+    #[serde(default)]
+    expanding: Vec<HighScoreEntry>,
+    #[serde(default)]
+    sequence: Vec<HighScoreEntry>,
+    #[serde(default)]
+    endless: Vec<HighScoreEntry>,
+    /// Only ever holds today's runs, older ones are dropped on the next
+    /// submission.
+    #[serde(default)]
+    daily: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        persistence::load(HIGH_SCORES_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = persistence::save(HIGH_SCORES_FILE, self) {
+            warn!("Failed to save high scores: {}", err);
+        }
+    }
+
+    /// The table for `mode`, `None` for modes that aren't scored.
+    pub fn table(&self, mode: GameMode) -> Option<&Vec<HighScoreEntry>> {
+        match mode {
+            GameMode::Classic => Some(&self.entries),
+            GameMode::Timed => Some(&self.timed),
+            GameMode::Survival => Some(&self.survival),
+            GameMode::Expanding => Some(&self.expanding),
+            GameMode::Sequence => Some(&self.sequence),
+            GameMode::Endless => Some(&self.endless),
+            GameMode::Daily => Some(&self.daily),
+            GameMode::Zen
+            | GameMode::Casual
+            | GameMode::Versus
+            | GameMode::Coop
+            | GameMode::Campaign => None,
+        }
+    }
+
+    fn table_mut(&mut self, mode: GameMode) -> Option<&mut Vec<HighScoreEntry>> {
+        match mode {
+            GameMode::Classic => Some(&mut self.entries),
+            GameMode::Timed => Some(&mut self.timed),
+            GameMode::Survival => Some(&mut self.survival),
+            GameMode::Expanding => Some(&mut self.expanding),
+            GameMode::Sequence => Some(&mut self.sequence),
+            GameMode::Endless => Some(&mut self.endless),
+            GameMode::Daily => Some(&mut self.daily),
+            GameMode::Zen
+            | GameMode::Casual
+            | GameMode::Versus
+            | GameMode::Coop
+            | GameMode::Campaign => None,
+        }
+    }
+
+    /// The top entry for `mode`. For the daily challenge only today's runs
+    /// count.
+    pub fn best(&self, mode: GameMode) -> Option<&HighScoreEntry> {
+        let today = today();
+        self.table(mode)?
+            .iter()
+            .find(|e| mode != GameMode::Daily || e.date == today)
+    }
+
+    /// Inserts a finished run into the table for `mode`, returning its rank
+    /// if it made the cut.
+    pub fn submit(&mut self, mode: GameMode, score: u32, length: usize) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let today = today();
+        let entries = self.table_mut(mode)?;
+        if mode == GameMode::Daily {
+            entries.retain(|e| e.date == today);
+        }
+        let rank = entries.iter().take_while(|e| e.score >= score).count();
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        entries.insert(
+            rank,
+            HighScoreEntry {
+                score,
+                length,
+                date: today,
+            },
+        );
+        entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    /// Submits a finished run and saves the table if it placed.
+    pub fn record(&mut self, mode: GameMode, score: u32, length: usize) {
+        if let Some(rank) = self.submit(mode, score, length) {
+            info!("New {} high score #{}: {}", mode.label(), rank + 1, score);
+            self.save();
+        }
+    }
+}
+
+/// The date in UTC, so daily entries go out of date when the daily board
+/// changes.
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load());
+    }
+}
@@ -0,0 +1,586 @@
+use bevy::prelude::*;
+
+use crate::arena::Arena;
+use crate::autopilot::{Autopilot, SearchStats, Strategy};
+use crate::biomes::{Biomes, CurrentBiome};
+use crate::bonus::BonusClock;
+use crate::campaign::CampaignRun;
+use crate::combo::{Combo, COMBO_WINDOW};
+use crate::dash::{Dash, MAX_ENERGY};
+use crate::highscore::HighScores;
+use crate::hunger::Hunger;
+use crate::lives::Lives;
+use crate::objectives::Objectives;
+use crate::pathfinding::SearchOptions;
+use crate::powerups::{ActiveEffects, PowerUpKind};
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::sprint::Sprint;
+use crate::tick::{MoveTimer, BASE_TICK_INTERVAL};
+use crate::timed::ChallengeClock;
+use crate::ui;
+use crate::world_events::EventScheduler;
+use crate::{player_length, Player, SnakeSegments};
+
+const HUD_FONT_SIZE: f32 = 28.0;
+const HUD_DETAIL_FONT_SIZE: f32 = 22.0;
+const EFFECTS_COLOR: Color = Color::rgb(1.0, 0.9, 0.4);
+const BADGE_FONT_SIZE: f32 = 40.0;
+const BADGE_COLOR: Color = Color::rgb(0.4, 1.0, 0.4);
+const CLOCK_FONT_SIZE: f32 = 40.0;
+/// The clock turns red for the last few seconds of a challenge or before the
+/// arena shrinks.
+const CLOCK_WARNING_TIME: f32 = 10.0;
+const CLOCK_WARNING_COLOR: Color = Color::rgb(1.0, 0.3, 0.2);
+const WORLD_EVENT_COLOR: Color = Color::rgb(1.0, 0.6, 0.2);
+const METER_WIDTH: f32 = 160.0;
+const METER_HEIGHT: f32 = 10.0;
+const METER_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.5);
+const COMBO_COLOR: Color = Color::rgb(0.3, 0.8, 1.0);
+const HUNGER_FULL_COLOR: Color = Color::rgb(0.9, 0.7, 0.2);
+const HUNGER_STARVING_COLOR: Color = Color::rgb(0.9, 0.2, 0.1);
+const ENERGY_COLOR: Color = Color::rgb(0.5, 0.7, 1.0);
+
+#[derive(Component)]
+struct HudText;
+
+#[derive(Component)]
+struct EffectsText;
+
+#[derive(Component)]
+struct MultiplierBadge;
+
+/// Cells explored by each search algorithm, shown while the autopilot
+/// drives.
+#[derive(Component)]
+struct SearchStatsText;
+
+/// The frame of the hunger bar, hidden unless hunger is on.
+#[derive(Component)]
+struct HungerBar;
+
+#[derive(Component)]
+struct HungerFill;
+
+#[derive(Component)]
+struct ClockText;
+
+#[derive(Component)]
+struct ObjectivesText;
+
+/// Announces the world event going on, under the clock.
+#[derive(Component)]
+struct WorldEventText;
+
+#[derive(Component)]
+struct ComboText;
+
+/// Player one's dash energy, hidden while there's no player to dash.
+#[derive(Component)]
+struct EnergyBar;
+
+#[derive(Component)]
+struct EnergyFill;
+
+/// The frame of the combo meter, hidden unless a streak is running.
+#[derive(Component)]
+struct ComboMeter;
+
+#[derive(Component)]
+struct ComboFill;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_hud)
+            .add_system(hud_update)
+            .add_system(effects_update)
+            .add_system(multiplier_badge_update)
+            .add_system(hunger_bar_update)
+            .add_system(energy_bar_update)
+            .add_system(combo_meter_update)
+            .add_system(objectives_update)
+            .add_system(clock_update)
+            .add_system(world_event_update)
+            .add_system(search_stats_update);
+    }
+}
+
+fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = ui::text_style(&asset_server, HUD_FONT_SIZE);
+
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(8.0),
+                    left: Val::Px(12.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_stats_text(parent, style);
+            let mut effects_style = ui::text_style(&asset_server, HUD_DETAIL_FONT_SIZE);
+            effects_style.color = EFFECTS_COLOR;
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("", effects_style, TextAlignment::default()),
+                    ..default()
+                })
+                .insert(EffectsText);
+            spawn_meter(parent, HungerBar, HungerFill, HUNGER_FULL_COLOR);
+            spawn_meter(parent, EnergyBar, EnergyFill, ENERGY_COLOR);
+            let mut combo_style = ui::text_style(&asset_server, HUD_DETAIL_FONT_SIZE);
+            combo_style.color = COMBO_COLOR;
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("", combo_style, TextAlignment::default()),
+                    ..default()
+                })
+                .insert(ComboText);
+            spawn_meter(parent, ComboMeter, ComboFill, COMBO_COLOR);
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        ui::text_style(&asset_server, HUD_DETAIL_FONT_SIZE),
+                        TextAlignment::default(),
+                    ),
+                    ..default()
+                })
+                .insert(ObjectivesText);
+        });
+
+    let mut badge_style = ui::text_style(&asset_server, BADGE_FONT_SIZE);
+    badge_style.color = BADGE_COLOR;
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(8.0),
+                    right: Val::Px(12.0),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::with_section("", badge_style, TextAlignment::default()),
+            ..default()
+        })
+        .insert(MultiplierBadge);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(8.0),
+                    left: Val::Px(12.0),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::with_section(
+                "",
+                ui::text_style(&asset_server, HUD_DETAIL_FONT_SIZE),
+                TextAlignment::default(),
+            ),
+            ..default()
+        })
+        .insert(SearchStatsText);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(8.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        ui::text_style(&asset_server, CLOCK_FONT_SIZE),
+                        TextAlignment::default(),
+                    ),
+                    ..default()
+                })
+                .insert(ClockText);
+            let mut event_style = ui::text_style(&asset_server, HUD_FONT_SIZE);
+            event_style.color = WORLD_EVENT_COLOR;
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("", event_style, TextAlignment::default()),
+                    ..default()
+                })
+                .insert(WorldEventText);
+        });
+}
+
+fn spawn_stats_text(parent: &mut ChildBuilder, style: TextStyle) {
+    parent
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: "Score: ".to_string(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: "   Length: ".to_string(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: "   Best: ".to_string(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: "   Lives: ".to_string(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: "   Speed: ".to_string(),
+                        style: style.clone(),
+                    },
+                    TextSection {
+                        value: String::new(),
+                        style,
+                    },
+                ],
+                ..default()
+            },
+            ..default()
+        })
+        .insert(HudText);
+}
+
+/// A hidden horizontal bar. `fill` marks the inner node that gets resized to
+/// show how full the meter is.
+fn spawn_meter(
+    parent: &mut ChildBuilder,
+    frame: impl Component,
+    fill: impl Component,
+    color: Color,
+) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(METER_WIDTH), Val::Px(METER_HEIGHT)),
+                margin: Rect {
+                    top: Val::Px(4.0),
+                    ..default()
+                },
+                display: Display::None,
+                ..default()
+            },
+            color: UiColor(METER_BACKGROUND),
+            ..default()
+        })
+        .insert(frame)
+        .with_children(|bar| {
+            bar.spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    ..default()
+                },
+                color: UiColor(color),
+                ..default()
+            })
+            .insert(fill);
+        });
+}
+
+fn display_if(visible: bool) -> Display {
+    if visible {
+        Display::Flex
+    } else {
+        Display::None
+    }
+}
+
+fn hud_update(
+    score: Res<Score>,
+    players: Query<(&Player, &SnakeSegments)>,
+    high_scores: Res<HighScores>,
+    rules: Res<GameRules>,
+    lives: Res<Lives>,
+    timer: Res<MoveTimer>,
+    mut texts: Query<&mut Text, With<HudText>>,
+) {
+    for mut text in texts.iter_mut() {
+        text.sections[1].value = score.0.to_string();
+        text.sections[3].value = player_length(&players).to_string();
+        text.sections[5].value = high_scores
+            .best(rules.mode)
+            .map_or(0, |e| e.score)
+            .to_string();
+        text.sections[7].value = lives.0.to_string();
+        text.sections[9].value = format!("x{:.1}", BASE_TICK_INTERVAL / timer.interval());
+    }
+}
+
+fn effects_update(
+    effects: Res<ActiveEffects>,
+    strategy: Res<Strategy>,
+    sprint: Res<Sprint>,
+    autopilots: Query<(), (With<Autopilot>, With<Player>)>,
+    mut texts: Query<&mut Text, With<EffectsText>>,
+) {
+    let autopilot = (!autopilots.is_empty()).then(|| format!("Autopilot: {}", strategy.label()));
+    let sprinting = sprint.active.then(|| "Sprinting: half points".to_string());
+    let summary = autopilot
+        .into_iter()
+        .chain(sprinting)
+        .chain(
+            effects
+                .remaining()
+                .iter()
+                // Multipliers get their own badge.
+                .filter(|(kind, _)| {
+                    !matches!(kind, PowerUpKind::DoubleScore | PowerUpKind::TripleScore)
+                })
+                .map(|(kind, seconds)| match seconds {
+                    Some(seconds) => format!("{} {:.1}s", kind.label(), seconds),
+                    None => kind.label().to_string(),
+                }),
+        )
+        .collect::<Vec<_>>()
+        .join("   ");
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = summary.clone();
+    }
+}
+
+fn search_stats_update(
+    stats: Res<SearchStats>,
+    options: Res<SearchOptions>,
+    mut texts: Query<&mut Text, With<SearchStatsText>>,
+) {
+    let summary = if stats.0.is_empty() {
+        String::new()
+    } else {
+        let counts = stats
+            .0
+            .iter()
+            .map(|(algorithm, explored)| format!("{} {}", algorithm.label(), explored))
+            .collect::<Vec<_>>()
+            .join("   ");
+        format!(
+            "Explored: {}   Heuristic: {} x{}",
+            counts,
+            options.heuristic.label(),
+            options.weight
+        )
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = summary.clone();
+    }
+}
+
+fn multiplier_badge_update(
+    effects: Res<ActiveEffects>,
+    mut texts: Query<&mut Text, With<MultiplierBadge>>,
+) {
+    let badge = [PowerUpKind::TripleScore, PowerUpKind::DoubleScore]
+        .into_iter()
+        .find_map(|kind| {
+            let seconds = effects.time_left(kind)?;
+            Some(format!("{} {:.0}", kind.label(), seconds.ceil()))
+        })
+        .unwrap_or_default();
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = badge.clone();
+    }
+}
+
+fn hunger_bar_update(
+    rules: Res<GameRules>,
+    hunger: Res<Hunger>,
+    mut bars: Query<&mut Style, (With<HungerBar>, Without<HungerFill>)>,
+    mut fills: Query<(&mut Style, &mut UiColor), With<HungerFill>>,
+) {
+    for mut style in bars.iter_mut() {
+        style.display = display_if(rules.hunger);
+    }
+    let fraction = hunger.ticks_left as f32 / rules.hunger_interval.max(1) as f32;
+    for (mut style, mut color) in fills.iter_mut() {
+        style.size.width = Val::Percent(fraction * 100.0);
+        // Shifts from the full color to red as the snake starves.
+        let [r, g, b, _] = HUNGER_STARVING_COLOR.as_rgba_f32();
+        let [full_r, full_g, full_b, _] = HUNGER_FULL_COLOR.as_rgba_f32();
+        color.0 = Color::rgb(
+            r + (full_r - r) * fraction,
+            g + (full_g - g) * fraction,
+            b + (full_b - b) * fraction,
+        );
+    }
+}
+
+fn energy_bar_update(
+    players: Query<(&Player, &Dash)>,
+    mut bars: Query<&mut Style, (With<EnergyBar>, Without<EnergyFill>)>,
+    mut fills: Query<&mut Style, With<EnergyFill>>,
+) {
+    let energy = players
+        .iter()
+        .find(|(player, _)| player.0 == 1)
+        .map(|(_, dash)| dash.energy);
+    for mut style in bars.iter_mut() {
+        style.display = display_if(energy.is_some());
+    }
+    let fraction = energy.unwrap_or(0) as f32 / MAX_ENERGY as f32;
+    for mut style in fills.iter_mut() {
+        style.size.width = Val::Percent(fraction * 100.0);
+    }
+}
+
+fn combo_meter_update(
+    combo: Res<Combo>,
+    mut texts: Query<&mut Text, With<ComboText>>,
+    mut meters: Query<&mut Style, (With<ComboMeter>, Without<ComboFill>)>,
+    mut fills: Query<&mut Style, With<ComboFill>>,
+) {
+    // A single piece of food isn't a combo yet.
+    let active = combo.streak >= 2;
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = if active {
+            format!("Combo x{}  +{}", combo.streak, combo.bonus())
+        } else {
+            String::new()
+        };
+    }
+    for mut style in meters.iter_mut() {
+        style.display = display_if(active);
+    }
+    let fraction = combo.ticks_left as f32 / COMBO_WINDOW as f32;
+    for mut style in fills.iter_mut() {
+        style.size.width = Val::Percent(fraction * 100.0);
+    }
+}
+
+fn objectives_update(
+    objectives: Res<Objectives>,
+    mut texts: Query<&mut Text, With<ObjectivesText>>,
+) {
+    if !objectives.is_changed() {
+        return;
+    }
+    let lines = objectives
+        .list
+        .iter()
+        .map(|objective| objective.describe())
+        .collect::<Vec<_>>()
+        .join("\n");
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.clone();
+    }
+}
+
+fn clock_update(
+    rules: Res<GameRules>,
+    clock: Res<ChallengeClock>,
+    arena: Res<Arena>,
+    campaign: Res<CampaignRun>,
+    biomes: Res<Biomes>,
+    current_biome: Res<CurrentBiome>,
+    bonus: Res<BonusClock>,
+    players: Query<(&Player, &SnakeSegments)>,
+    mut texts: Query<&mut Text, With<ClockText>>,
+) {
+    // Modes without a clock show how far off the goal is, or where the run
+    // has got to.
+    let progress = match rules.mode {
+        GameMode::Campaign if bonus.seconds_left().is_some() => None,
+        GameMode::Campaign => Some(format!(
+            "Length {}/{}",
+            player_length(&players),
+            campaign.goal()
+        )),
+        GameMode::Endless => current_biome.get(&biomes).map(|biome| biome.name.clone()),
+        _ => None,
+    };
+    if let Some(progress) = progress {
+        for mut text in texts.iter_mut() {
+            let section = &mut text.sections[0];
+            section.value = progress.clone();
+            section.style.color = ui::TEXT_COLOR;
+        }
+        return;
+    }
+    let countdown = match rules.mode {
+        GameMode::Campaign => bonus.seconds_left().map(|seconds| ("Bonus ", seconds)),
+        GameMode::Timed => Some(("", clock.seconds_left())),
+        GameMode::Survival if arena.is_shrinking() => Some((
+            "Shrinks in ",
+            (arena.timer.duration() - arena.timer.elapsed()).as_secs_f32(),
+        )),
+        _ => None,
+    };
+    for mut text in texts.iter_mut() {
+        let section = &mut text.sections[0];
+        let (label, seconds_left) = match countdown {
+            Some(countdown) => countdown,
+            None => {
+                section.value.clear();
+                continue;
+            }
+        };
+        let seconds = seconds_left.ceil() as u32;
+        section.value = format!("{}{}:{:02}", label, seconds / 60, seconds % 60);
+        section.style.color = if seconds_left < CLOCK_WARNING_TIME {
+            CLOCK_WARNING_COLOR
+        } else {
+            ui::TEXT_COLOR
+        };
+    }
+}
+
+fn world_event_update(
+    scheduler: Res<EventScheduler>,
+    mut texts: Query<&mut Text, With<WorldEventText>>,
+) {
+    if !scheduler.is_changed() {
+        return;
+    }
+    let announcement = scheduler.active().map_or("", |event| event.announcement());
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != announcement {
+            text.sections[0].value = announcement.to_string();
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Optional hunger: a snake that goes too long without eating loses its tail.
+
+use bevy::prelude::*;
+
+use crate::rules::GameRules;
+use crate::state::GameState;
+use crate::tick::MovementTick;
+use crate::{snake_eating, snake_shrink, FoodEatenEvent, Player, ShrinkEvent};
+
+/// Ticks left before the snake starves again.
+#[derive(Default)]
+pub struct Hunger {
+    pub ticks_left: u32,
+}
+
+pub struct HungerPlugin;
+
+impl Plugin for HungerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hunger>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_hunger))
+            .add_system_set(SystemSet::on_enter(GameState::Respawning).with_system(reset_hunger))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(starve.after(snake_eating).before(snake_shrink)),
+            );
+    }
+}
+
+fn reset_hunger(mut hunger: ResMut<Hunger>, rules: Res<GameRules>) {
+    hunger.ticks_left = rules.hunger_interval;
+}
+
+fn starve(
+    rules: Res<GameRules>,
+    mut hunger: ResMut<Hunger>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+    mut shrink_writer: EventWriter<ShrinkEvent>,
+    players: Query<Entity, With<Player>>,
+) {
+    if !rules.hunger {
+        return;
+    }
+    if eaten_reader.iter().next().is_some() {
+        hunger.ticks_left = rules.hunger_interval;
+        return;
+    }
+    hunger.ticks_left = hunger.ticks_left.saturating_sub(1);
+    if hunger.ticks_left == 0 {
+        for snake in players.iter() {
+            shrink_writer.send(ShrinkEvent { snake, segments: 1 });
+        }
+        hunger.ticks_left = rules.hunger_interval;
+    }
+}
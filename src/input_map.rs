@@ -0,0 +1,452 @@
+//! Keys for everything the player does in a run, and a screen to change them
+//! from the menu (I). The bindings are kept in `settings.ron` under
+//! `controls` and loaded into the `InputMap` resource, which the input
+//! systems read instead of fixed keys.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::ui;
+use crate::Direction;
+
+const PLAYERS: [&str; 3] = ["Single player", "Player one", "Player two"];
+const ACTIONS: [&str; 5] = ["Up", "Left", "Right", "Down", "Dash"];
+/// Keys shared by everyone, listed after the players'.
+const SHARED: [&str; 5] = [
+    "Pause",
+    "Sprint",
+    "Restart",
+    "Switch access next",
+    "Switch access turn",
+];
+/// Each player's actions in turn, then the shared keys.
+const ROWS: usize = PLAYERS.len() * ACTIONS.len() + SHARED.len();
+
+/// Keys for each of a player's actions. Any number of keys can be bound to
+/// the same action.
+#[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Controls {
+    pub up: Vec<KeyCode>,
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub dash: Vec<KeyCode>,
+}
+
+impl Controls {
+    /// Which way `key` turns the snake, if it's one of its turn keys.
+    pub fn direction(&self, key: KeyCode) -> Option<Direction> {
+        [
+            (&self.up, Direction::Up),
+            (&self.left, Direction::Left),
+            (&self.right, Direction::Right),
+            (&self.down, Direction::Down),
+        ]
+        .into_iter()
+        .find(|(keys, _)| keys.contains(&key))
+        .map(|(_, direction)| direction)
+    }
+
+    /// Every key that turns the snake.
+    pub fn turn_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.up
+            .iter()
+            .chain(&self.left)
+            .chain(&self.right)
+            .chain(&self.down)
+            .copied()
+    }
+
+    fn action(&self, action: usize) -> &Vec<KeyCode> {
+        match action {
+            0 => &self.up,
+            1 => &self.left,
+            2 => &self.right,
+            3 => &self.down,
+            _ => &self.dash,
+        }
+    }
+
+    fn action_mut(&mut self, action: usize) -> &mut Vec<KeyCode> {
+        match action {
+            0 => &mut self.up,
+            1 => &mut self.left,
+            2 => &mut self.right,
+            3 => &mut self.down,
+            _ => &mut self.dash,
+        }
+    }
+}
+
+/// Ready-made sets of a player's keys, picked with Tab on the binding screen.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    ArrowsAndWasd,
+    Arrows,
+    Wasd,
+    /// Vim's keys, with `;` to dash and Space left to sprint.
+    Hjkl,
+}
+
+impl Preset {
+    const ALL: [Preset; 4] = [
+        Preset::ArrowsAndWasd,
+        Preset::Arrows,
+        Preset::Wasd,
+        Preset::Hjkl,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Preset::ArrowsAndWasd => "Arrows and WASD",
+            Preset::Arrows => "Arrows",
+            Preset::Wasd => "WASD",
+            Preset::Hjkl => "hjkl",
+        }
+    }
+
+    fn controls(self) -> Controls {
+        let keys = |up, left, right, down, dash: &[KeyCode]| Controls {
+            up: vec![up],
+            left: vec![left],
+            right: vec![right],
+            down: vec![down],
+            dash: dash.to_vec(),
+        };
+        match self {
+            Preset::ArrowsAndWasd => Controls {
+                up: vec![KeyCode::Up, KeyCode::W],
+                left: vec![KeyCode::Left, KeyCode::A],
+                right: vec![KeyCode::Right, KeyCode::D],
+                down: vec![KeyCode::Down, KeyCode::S],
+                dash: vec![KeyCode::LShift, KeyCode::RShift],
+            },
+            Preset::Arrows => keys(
+                KeyCode::Up,
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::Down,
+                &[KeyCode::RShift],
+            ),
+            Preset::Wasd => keys(
+                KeyCode::W,
+                KeyCode::A,
+                KeyCode::D,
+                KeyCode::S,
+                &[KeyCode::LShift],
+            ),
+            Preset::Hjkl => keys(
+                KeyCode::K,
+                KeyCode::H,
+                KeyCode::L,
+                KeyCode::J,
+                &[KeyCode::Semicolon],
+            ),
+        }
+    }
+
+    /// The preset `controls` are, `None` once any key has been changed.
+    fn of(controls: &Controls) -> Option<Preset> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.controls() == *controls)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    /// Player one's keys when playing alone.
+    pub single_player: Controls,
+    /// Each player's keys when two share the keyboard.
+    pub player_one: Controls,
+    pub player_two: Controls,
+    pub pause: Vec<KeyCode>,
+    /// Held to speed the game up for half the points, see `sprint`.
+    pub sprint: Vec<KeyCode>,
+    /// Plays again from the game over screen.
+    pub restart: Vec<KeyCode>,
+    /// Moves the highlight on and takes the turn it shows, see
+    /// `switch_access`.
+    pub switch_next: Vec<KeyCode>,
+    pub switch_turn: Vec<KeyCode>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            single_player: Preset::ArrowsAndWasd.controls(),
+            player_one: Preset::Arrows.controls(),
+            player_two: Preset::Wasd.controls(),
+            pause: vec![KeyCode::P],
+            sprint: vec![KeyCode::Space],
+            restart: vec![KeyCode::Space],
+            switch_next: vec![KeyCode::Space],
+            switch_turn: vec![KeyCode::Return],
+        }
+    }
+}
+
+impl InputMap {
+    pub fn player(&self, player: u8, two_player: bool) -> &Controls {
+        match (player, two_player) {
+            (1, false) => &self.single_player,
+            (1, true) => &self.player_one,
+            _ => &self.player_two,
+        }
+    }
+
+    /// The keys of the player `row` of the binding screen belongs to, if
+    /// it's one of a player's rows.
+    fn row_player(&self, row: usize) -> Option<&Controls> {
+        match row / ACTIONS.len() {
+            0 => Some(&self.single_player),
+            1 => Some(&self.player_one),
+            2 => Some(&self.player_two),
+            _ => None,
+        }
+    }
+
+    fn row_player_mut(&mut self, row: usize) -> Option<&mut Controls> {
+        match row / ACTIONS.len() {
+            0 => Some(&mut self.single_player),
+            1 => Some(&mut self.player_one),
+            2 => Some(&mut self.player_two),
+            _ => None,
+        }
+    }
+
+    /// The keys bound on `row` of the binding screen.
+    fn row(&self, row: usize) -> &Vec<KeyCode> {
+        match self.row_player(row) {
+            Some(controls) => controls.action(row % ACTIONS.len()),
+            None => match row - PLAYERS.len() * ACTIONS.len() {
+                0 => &self.pause,
+                1 => &self.sprint,
+                2 => &self.restart,
+                3 => &self.switch_next,
+                _ => &self.switch_turn,
+            },
+        }
+    }
+
+    fn row_mut(&mut self, row: usize) -> &mut Vec<KeyCode> {
+        match row.checked_sub(PLAYERS.len() * ACTIONS.len()) {
+            None => self
+                .row_player_mut(row)
+                .expect("rows before the shared keys are players'")
+                .action_mut(row % ACTIONS.len()),
+            Some(0) => &mut self.pause,
+            Some(1) => &mut self.sprint,
+            Some(2) => &mut self.restart,
+            Some(3) => &mut self.switch_next,
+            Some(_) => &mut self.switch_turn,
+        }
+    }
+}
+
+fn row_label(row: usize) -> String {
+    match row / ACTIONS.len() {
+        player if player < PLAYERS.len() => {
+            format!("{} {}", PLAYERS[player], ACTIONS[row % ACTIONS.len()])
+        }
+        _ => SHARED[row - PLAYERS.len() * ACTIONS.len()].to_string(),
+    }
+}
+
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Escape => "Esc".to_string(),
+        KeyCode::Return => "Enter".to_string(),
+        KeyCode::Back => "Backspace".to_string(),
+        KeyCode::LShift => "Left Shift".to_string(),
+        KeyCode::RShift => "Right Shift".to_string(),
+        _ => {
+            // The number row is `Key1` to `Key0`.
+            let name = format!("{:?}", key);
+            name.strip_prefix("Key").unwrap_or(&name).to_string()
+        }
+    }
+}
+
+/// The keys as they're shown in hints, like "P or Esc".
+pub fn key_names(keys: &[KeyCode]) -> String {
+    if keys.is_empty() {
+        return "(unbound)".to_string();
+    }
+    keys.iter()
+        .map(|key| key_name(*key))
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+/// The key that confirms on the screen for `state`, which other kinds of
+/// input stand in for. `buttons` is whether the screen shows buttons to pick
+/// between, which Enter presses.
+pub fn confirm_key(state: GameState, input_map: &InputMap, buttons: bool) -> Option<KeyCode> {
+    match state {
+        GameState::GameOver if buttons => Some(KeyCode::Return),
+        GameState::GameOver => input_map.restart.first().copied(),
+        // The controls screen binds keyboard keys, standing in for Enter
+        // would only bind Enter.
+        GameState::Bindings => None,
+        _ => Some(KeyCode::Return),
+    }
+}
+
+/// The highlighted row on the binding screen, and whether it's waiting for a
+/// key to bind.
+#[derive(Default)]
+struct Cursor {
+    row: usize,
+    listening: bool,
+}
+
+#[derive(Component)]
+struct BindingsScreen;
+
+#[derive(Component)]
+struct BindingList;
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Cursor>()
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_input))
+            .add_system_set(SystemSet::on_enter(GameState::Bindings).with_system(spawn_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::Bindings)
+                    .with_system(bindings_input)
+                    .with_system(binding_list_update.after(bindings_input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Bindings).with_system(despawn_screen));
+    }
+}
+
+fn open_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<Cursor>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::I]) {
+        *cursor = Cursor::default();
+        writer.send(GameStateEvent::OpenBindings);
+    }
+}
+
+/// Up and down pick an action and Enter waits for the key to bind to it,
+/// which replaces its old keys. Esc while waiting leaves them as they were.
+fn bindings_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<Cursor>,
+    mut input_map: ResMut<InputMap>,
+    mut settings: ResMut<Settings>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if cursor.listening {
+        let key = match kbd_input.get_just_pressed().next() {
+            Some(key) => *key,
+            None => return,
+        };
+        kbd_input.reset(key);
+        cursor.listening = false;
+        if key != KeyCode::Escape {
+            *input_map.row_mut(cursor.row) = vec![key];
+        }
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Up]) {
+        cursor.row = (cursor.row + ROWS - 1) % ROWS;
+        return;
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Down]) {
+        cursor.row = (cursor.row + 1) % ROWS;
+        return;
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+        cursor.listening = true;
+        return;
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Tab]) {
+        let controls = match input_map.row_player_mut(cursor.row) {
+            Some(controls) => controls,
+            None => return,
+        };
+        // Changed keys start over from the first preset.
+        let next = Preset::of(controls).map_or(0, |preset| {
+            let index = Preset::ALL.iter().position(|p| *p == preset).unwrap();
+            (index + 1) % Preset::ALL.len()
+        });
+        *controls = Preset::ALL[next].controls();
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Back]) {
+        *input_map = InputMap::default();
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+        return;
+    } else {
+        return;
+    }
+    settings.controls = input_map.clone();
+    settings.save();
+}
+
+fn spawn_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(BindingsScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line(
+                "Controls",
+                ui::text_style(&asset_server, 64.0),
+            ));
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 20.0)))
+                .insert(BindingList);
+            parent.spawn_bundle(ui::text_line(
+                "Up/Down to pick, Enter then a key to bind it\n\
+                 Tab switches a player's preset\n\
+                 Backspace puts back the defaults, Esc goes back",
+                ui::text_style(&asset_server, 20.0),
+            ));
+        });
+}
+
+fn binding_list(input_map: &InputMap, cursor: &Cursor) -> String {
+    (0..ROWS)
+        .map(|row| {
+            let marker = if row == cursor.row { ">" } else { " " };
+            let keys = if row == cursor.row && cursor.listening {
+                "press a key...".to_string()
+            } else {
+                key_names(input_map.row(row))
+            };
+            format!("{} {}: {}", marker, row_label(row), keys)
+        })
+        .chain(input_map.row_player(cursor.row).map(|controls| {
+            let preset = Preset::of(controls).map_or("Custom", Preset::label);
+            format!(
+                "\n{} preset: {}",
+                PLAYERS[cursor.row / ACTIONS.len()],
+                preset
+            )
+        }))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn binding_list_update(
+    input_map: Res<InputMap>,
+    cursor: Res<Cursor>,
+    mut texts: Query<&mut Text, With<BindingList>>,
+) {
+    if !input_map.is_changed() && !cursor.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = binding_list(&input_map, &cursor);
+    }
+}
+
+fn despawn_screen(mut commands: Commands, screens: Query<Entity, With<BindingsScreen>>) {
+    for entt in screens.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
@@ -0,0 +1,54 @@
+//! The invisible tail modifier. Only the first few segments of each player's
+//! snake are drawn, the rest still being there to run into, and the whole
+//! snake shows again once the run is over.
+
+use bevy::prelude::*;
+
+use crate::rules::GameRules;
+use crate::state::GameState;
+use crate::{Player, SnakeSegments};
+
+/// Segments drawn, counting the head.
+const VISIBLE_SEGMENTS: usize = 3;
+
+pub struct InvisibleTailPlugin;
+
+impl Plugin for InvisibleTailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Playing).with_system(hide_tails))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(show_tails));
+    }
+}
+
+fn hide_tails(
+    rules: Res<GameRules>,
+    players: Query<&SnakeSegments, With<Player>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !rules.invisible_tail {
+        return;
+    }
+    for segments in players.iter() {
+        for (i, segment) in segments.iter().enumerate() {
+            if let Ok(mut visibility) = visibility.get_mut(*segment) {
+                let visible = i < VISIBLE_SEGMENTS;
+                if visibility.is_visible != visible {
+                    visibility.is_visible = visible;
+                }
+            }
+        }
+    }
+}
+
+fn show_tails(
+    players: Query<&SnakeSegments, With<Player>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    for segments in players.iter() {
+        for segment in segments.iter() {
+            if let Ok(mut visibility) = visibility.get_mut(*segment) {
+                visibility.is_visible = true;
+            }
+        }
+    }
+}
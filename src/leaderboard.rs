@@ -0,0 +1,239 @@
+//! Online leaderboard, built with the `online-leaderboard` feature. Scores are
+//! posted as JSON to `Settings::leaderboard_url` at game over, and a GET to
+//! the same URL is expected to return the top entries, best first.
+//!
+//! Requests go over plain HTTP on a background thread so a slow server never
+//! stalls the game. `https://` endpoints aren't supported.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::Playback;
+use crate::rng::GameRng;
+use crate::rules::GameRules;
+use crate::score::Score;
+use crate::settings::Settings;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::ui;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Entries shown on the leaderboard screen.
+const SHOWN_ENTRIES: usize = 10;
+
+#[derive(Serialize)]
+struct Submission {
+    name: String,
+    score: u32,
+    mode: String,
+    /// The seed the run started from, so it can be played again.
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    name: String,
+    score: u32,
+}
+
+/// The leaderboard request in flight while the screen is open.
+struct Fetch(Mutex<Receiver<Result<Vec<Entry>, String>>>);
+
+#[derive(Component)]
+struct LeaderboardScreen;
+
+#[derive(Component)]
+struct EntriesText;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_input))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(submit_score))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Leaderboard)
+                    .with_system(spawn_screen)
+                    .with_system(start_fetch),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Leaderboard)
+                    .with_system(show_entries)
+                    .with_system(close_input),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Leaderboard).with_system(despawn_screen));
+    }
+}
+
+fn open_input(mut kbd_input: ResMut<Input<KeyCode>>, mut writer: EventWriter<GameStateEvent>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::L]) {
+        writer.send(GameStateEvent::OpenLeaderboard);
+    }
+}
+
+fn close_input(mut kbd_input: ResMut<Input<KeyCode>>, mut writer: EventWriter<GameStateEvent>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::Escape, KeyCode::Return]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn submit_score(
+    settings: Res<Settings>,
+    rules: Res<GameRules>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+    playback: Res<Playback>,
+) {
+    let url = match &settings.leaderboard_url {
+        Some(url) => url.clone(),
+        None => return,
+    };
+    // Two player runs don't have a score of their own, and a watched replay
+    // was already submitted when it was played.
+    if rules.mode.two_player() || playback.is_active() || score.0 == 0 {
+        return;
+    }
+    let submission = Submission {
+        name: settings.player_name.clone(),
+        score: score.0,
+        mode: rules.mode.label().to_string(),
+        seed: Some(rng.seed()),
+    };
+    thread::spawn(move || {
+        let result = serde_json::to_string(&submission)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            .and_then(|body| request("POST", &url, Some(&body)));
+        match result {
+            Ok(_) => info!("Submitted score {} to {}", submission.score, url),
+            Err(err) => warn!("Failed to submit score to {}: {}", url, err),
+        }
+    });
+}
+
+fn start_fetch(mut commands: Commands, settings: Res<Settings>) {
+    let (sender, receiver) = mpsc::channel();
+    match settings.leaderboard_url.clone() {
+        Some(url) => {
+            thread::spawn(move || {
+                let entries = request("GET", &url, None).and_then(|body| {
+                    serde_json::from_str(&body)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+                });
+                let _ = sender.send(entries.map_err(|err| err.to_string()));
+            });
+        }
+        None => {
+            let _ = sender.send(Err("No leaderboard URL in the settings file".to_string()));
+        }
+    }
+    commands.insert_resource(Fetch(Mutex::new(receiver)));
+}
+
+fn show_entries(fetch: Res<Fetch>, mut texts: Query<&mut Text, With<EntriesText>>) {
+    let result = match fetch.0.lock().unwrap().try_recv() {
+        Ok(result) => result,
+        Err(TryRecvError::Empty | TryRecvError::Disconnected) => return,
+    };
+    let lines = match result {
+        Ok(entries) if entries.is_empty() => "No scores yet".to_string(),
+        Ok(entries) => entries
+            .iter()
+            .take(SHOWN_ENTRIES)
+            .enumerate()
+            .map(|(i, entry)| format!("{:>2}. {:<16} {:>6}", i + 1, entry.name, entry.score))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(err) => format!("Couldn't load the leaderboard: {}", err),
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.clone();
+    }
+}
+
+fn spawn_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(LeaderboardScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line(
+                "Leaderboard",
+                ui::text_style(&asset_server, 64.0),
+            ));
+            parent
+                .spawn_bundle(ui::text_line(
+                    "Loading...",
+                    ui::text_style(&asset_server, 24.0),
+                ))
+                .insert(EntriesText);
+            parent.spawn_bundle(ui::text_line(
+                "Press Esc to go back",
+                ui::text_style(&asset_server, 24.0),
+            ));
+        });
+}
+
+fn despawn_screen(mut commands: Commands, screens: Query<Entity, With<LeaderboardScreen>>) {
+    for entt in screens.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    commands.remove_resource::<Fetch>();
+}
+
+/// Sends an HTTP/1.0 request, which keeps the response free of chunked
+/// encoding, and returns the body of a successful response.
+fn request(method: &str, url: &str, body: Option<&str>) -> io::Result<String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "only http:// leaderboard URLs are supported",
+        )
+    })?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host didn't resolve"))?;
+
+    let mut stream = TcpStream::connect_timeout(&address, REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let body = body.unwrap_or("");
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        authority,
+        body.len(),
+        body
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed response"))?;
+    let status = head.lines().next().unwrap_or_default();
+    let success = status
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|code| code.starts_with('2'));
+    if !success {
+        return Err(io::Error::other(format!("server answered {}", status)));
+    }
+    Ok(body.to_string())
+}
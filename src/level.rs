@@ -0,0 +1,483 @@
+//! Level files describing the board layout, spawn point and food rules.
+//!
+//! Levels are RON files. The `layout` lists the rows of the board from top to
+//! bottom, one character per cell: `#` is a wall and `.` is empty floor. The
+//! digits `1` to `9` are portals, each digit marking the two ends of a pair.
+//! `:` is mud and `~` is water, floor that's slower to cross, and `=` is ice,
+//! which a snake can't turn on. A lowercase letter is a key and the same
+//! letter in uppercase a door it opens. `^` is a spike, which comes up on the
+//! timing in the level's `spikes`, and the arrows `←↑→↓` are speed pads
+//! pushing the snake that way.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{MAX_SIZE, MIN_SIZE};
+use crate::cli::CliArgs;
+use crate::doors::{spawn_doors, DoorKey};
+use crate::obstacles;
+use crate::pads::{pad_direction, pad_tile, spawn_pads, SpeedPad};
+use crate::portals::{spawn_portals, Portal};
+use crate::rng::{seed_run, GameRng};
+use crate::rules::GameRules;
+use crate::spikes::{spawn_spike, Spike};
+use crate::state::GameState;
+use crate::terrain::{spawn_terrain, Terrain};
+use crate::walls::{spawn_wall, Wall};
+use crate::{Direction, Position};
+
+/// Levels shipped with the game, in menu order.
+const BUNDLED_LEVELS: [(&str, &str); 8] = [
+    ("open", include_str!("../assets/levels/open.ron")),
+    ("bars", include_str!("../assets/levels/bars.ron")),
+    ("box", include_str!("../assets/levels/box.ron")),
+    ("cross", include_str!("../assets/levels/cross.ron")),
+    ("warp", include_str!("../assets/levels/warp.ron")),
+    ("marsh", include_str!("../assets/levels/marsh.ron")),
+    ("vault", include_str!("../assets/levels/vault.ron")),
+    ("gauntlet", include_str!("../assets/levels/gauntlet.ron")),
+];
+const DEFAULT_LEVEL: &str = "bars";
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoodRules {
+    /// How many pieces of food can be on the board at once. Eaten food is
+    /// replaced immediately.
+    #[serde(default = "default_max_food")]
+    pub max_food: u32,
+}
+
+fn default_max_food() -> u32 {
+    1
+}
+
+/// How many movement ticks spikes spend in each part of their cycle.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpikeTiming {
+    pub safe_ticks: u32,
+    /// Ticks of warning before they come up.
+    pub warning_ticks: u32,
+    pub deadly_ticks: u32,
+}
+
+impl Default for SpikeTiming {
+    fn default() -> Self {
+        Self {
+            safe_ticks: 6,
+            warning_ticks: 2,
+            deadly_ticks: 4,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub start: Position,
+    pub start_direction: Direction,
+    pub food: FoodRules,
+    /// Only matters for levels with spikes.
+    #[serde(default)]
+    pub spikes: SpikeTiming,
+    pub layout: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Parse(ron::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Parse(err) => write!(f, "{}", err),
+            Self::Invalid(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl Level {
+    pub fn parse(source: &str) -> Result<Self, LevelError> {
+        let level: Level = ron::from_str(source).map_err(LevelError::Parse)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, LevelError> {
+        Self::parse(&fs::read_to_string(path).map_err(LevelError::Io)?)
+    }
+
+    /// Looks up a bundled level by name, falling back to reading `name` as a
+    /// path to a level file.
+    pub fn find(name: &str) -> Result<Self, LevelError> {
+        match BUNDLED_LEVELS.iter().find(|(bundled, _)| *bundled == name) {
+            Some((_, source)) => Self::parse(source),
+            None => Self::load(Path::new(name)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), LevelError> {
+        let sizes = MIN_SIZE..=MAX_SIZE;
+        if !sizes.contains(&self.width) || !sizes.contains(&self.height) {
+            return Err(LevelError::Invalid(format!(
+                "level is {}x{} but boards are {} to {} cells each way",
+                self.width, self.height, MIN_SIZE, MAX_SIZE
+            )));
+        }
+        if self.layout.len() != self.height as usize
+            || self
+                .layout
+                .iter()
+                .any(|row| row.chars().count() != self.width as usize)
+        {
+            return Err(LevelError::Invalid(
+                "layout doesn't match the level size".to_string(),
+            ));
+        }
+        if let Some(c) = self.layout.iter().flat_map(|row| row.chars()).find(|c| {
+            !matches!(c, '#' | '.' | '^' | '1'..='9' | 'a'..='z' | 'A'..='Z')
+                && Terrain::from_tile(*c).is_none()
+                && pad_direction(*c).is_none()
+        }) {
+            return Err(LevelError::Invalid(format!("unknown tile '{}'", c)));
+        }
+        for (pair, ends) in self.portal_ends() {
+            if ends.len() != 2 {
+                return Err(LevelError::Invalid(format!(
+                    "portal {} has {} ends instead of 2",
+                    pair,
+                    ends.len()
+                )));
+            }
+        }
+        if let Some((_, key)) = self
+            .doors()
+            .find(|(_, key)| !self.keys().any(|(_, k)| k == *key))
+        {
+            return Err(LevelError::Invalid(format!(
+                "door {} has no key",
+                key.to_ascii_uppercase()
+            )));
+        }
+        if !self.is_floor(self.start) {
+            return Err(LevelError::Invalid("start position is blocked".to_string()));
+        }
+        Ok(())
+    }
+
+    /// An empty board, for sizes none of the bundled levels are made for.
+    pub fn open(width: u32, height: u32) -> Self {
+        Self {
+            name: format!("Open {}x{}", width, height),
+            width,
+            height,
+            start: Position { x: 3, y: 3 },
+            start_direction: Direction::Up,
+            food: FoodRules { max_food: 3 },
+            spikes: SpikeTiming::default(),
+            layout: vec![".".repeat(width as usize); height as usize],
+        }
+    }
+
+    /// Writes the level as RON, the format `Level::load` reads.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, contents)
+    }
+
+    /// Where the second snake starts in two player modes: the start point
+    /// mirrored through the middle of the board, facing the other way. If
+    /// that isn't open floor, the nearest spot that is.
+    pub fn second_start(&self) -> (Position, Direction) {
+        let direction = self.start_direction.opposite();
+        let mirrored = Position {
+            x: self.width as i32 - 1 - self.start.x,
+            y: self.height as i32 - 1 - self.start.y,
+        };
+        let clear = |pos: Position| {
+            [pos, pos.step(direction), pos.step(direction.opposite())]
+                .iter()
+                .all(|cell| self.is_floor(*cell) && *cell != self.start)
+        };
+        let start = self
+            .tiles()
+            .map(|(pos, _)| pos)
+            .filter(|pos| clear(*pos))
+            .min_by_key(|pos| (pos.x - mirrored.x).abs() + (pos.y - mirrored.y).abs())
+            .unwrap_or(mirrored);
+        (start, direction)
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as u32) < self.width && (pos.y as u32) < self.height
+    }
+
+    /// Whether `pos` is plain floor, with no wall or portal on it.
+    pub fn is_floor(&self, pos: Position) -> bool {
+        self.tile(pos) == Some('.')
+    }
+
+    /// Puts a speed pad pushing towards `direction` at `pos`.
+    pub fn set_pad(&mut self, pos: Position, direction: Direction) {
+        self.set_tile(pos, pad_tile(direction));
+    }
+
+    /// Puts a spike at `pos`.
+    pub fn set_spike(&mut self, pos: Position) {
+        self.set_tile(pos, '^');
+    }
+
+    /// Marks `pos` as a wall or as empty floor. Positions outside the level
+    /// are ignored.
+    pub fn set_wall(&mut self, pos: Position, wall: bool) {
+        self.set_tile(pos, if wall { '#' } else { '.' });
+    }
+
+    /// Covers `pos` with `terrain`.
+    pub fn set_terrain(&mut self, pos: Position, terrain: Terrain) {
+        self.set_tile(pos, terrain.tile());
+    }
+
+    /// Puts an end of portal pair `pair` (1 to 9) at `pos`.
+    pub fn set_portal(&mut self, pos: Position, pair: u32) {
+        if let Some(tile) = char::from_digit(pair, 10) {
+            self.set_tile(pos, tile);
+        }
+    }
+
+    /// How many ends of portal pair `pair` are placed.
+    pub fn portal_count(&self, pair: u32) -> usize {
+        self.portal_ends().get(&pair).map_or(0, Vec::len)
+    }
+
+    fn set_tile(&mut self, pos: Position, tile: char) {
+        if !self.contains(pos) {
+            return;
+        }
+        let row = (self.height as i32 - 1 - pos.y) as usize;
+        self.layout[row] = self.layout[row]
+            .chars()
+            .enumerate()
+            .map(|(x, c)| if x as i32 == pos.x { tile } else { c })
+            .collect();
+    }
+
+    fn tile(&self, pos: Position) -> Option<char> {
+        if !self.contains(pos) {
+            return None;
+        }
+        let row = (self.height as i32 - 1 - pos.y) as usize;
+        self.layout[row].chars().nth(pos.x as usize)
+    }
+
+    /// Every tile in the layout along with its position.
+    fn tiles(&self) -> impl Iterator<Item = (Position, char)> + '_ {
+        self.layout
+            .iter()
+            .enumerate()
+            .flat_map(move |(row, tiles)| {
+                let y = self.height as i32 - 1 - row as i32;
+                tiles
+                    .chars()
+                    .enumerate()
+                    .map(move |(x, tile)| (Position { x: x as i32, y }, tile))
+            })
+    }
+
+    /// Positions of every wall tile in the layout.
+    pub fn walls(&self) -> impl Iterator<Item = Position> + '_ {
+        self.tiles()
+            .filter(|(_, tile)| *tile == '#')
+            .map(|(pos, _)| pos)
+    }
+
+    /// Positions of every spike tile in the layout.
+    pub fn spikes(&self) -> impl Iterator<Item = Position> + '_ {
+        self.tiles()
+            .filter(|(_, tile)| *tile == '^')
+            .map(|(pos, _)| pos)
+    }
+
+    /// Every speed pad in the layout along with the way it pushes.
+    pub fn pads(&self) -> impl Iterator<Item = (Position, Direction)> + '_ {
+        self.tiles()
+            .filter_map(|(pos, tile)| pad_direction(tile).map(|direction| (pos, direction)))
+    }
+
+    /// Every mud, water and ice tile in the layout.
+    pub fn terrain(&self) -> impl Iterator<Item = (Position, Terrain)> + '_ {
+        self.tiles()
+            .filter_map(|(pos, tile)| Terrain::from_tile(tile).map(|terrain| (pos, terrain)))
+    }
+
+    /// Every key tile along with its letter.
+    pub fn keys(&self) -> impl Iterator<Item = (Position, char)> + '_ {
+        self.tiles().filter(|(_, tile)| tile.is_ascii_lowercase())
+    }
+
+    /// Every door tile along with the letter of the key that opens it.
+    pub fn doors(&self) -> impl Iterator<Item = (Position, char)> + '_ {
+        self.tiles()
+            .filter(|(_, tile)| tile.is_ascii_uppercase())
+            .map(|(pos, tile)| (pos, tile.to_ascii_lowercase()))
+    }
+
+    /// Positions of every portal tile, grouped by pair.
+    fn portal_ends(&self) -> BTreeMap<u32, Vec<Position>> {
+        let mut ends: BTreeMap<u32, Vec<Position>> = BTreeMap::new();
+        for (pos, tile) in self.tiles() {
+            if let Some(pair) = tile.to_digit(10) {
+                ends.entry(pair).or_default().push(pos);
+            }
+        }
+        ends
+    }
+
+    /// Both ends of every complete portal pair along with the pair's digit.
+    pub fn portals(&self) -> Vec<(u32, [Position; 2])> {
+        self.portal_ends()
+            .into_iter()
+            .filter_map(|(pair, ends)| match ends[..] {
+                [a, b] => Some((pair, [a, b])),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The level played by the next run, plus its index in the bundled list so
+/// the menu can cycle through them.
+pub struct CurrentLevel {
+    pub level: Level,
+    bundled_index: Option<usize>,
+}
+
+impl CurrentLevel {
+    /// A level that isn't part of the bundled set, e.g. one made in the
+    /// editor.
+    pub fn custom(level: Level) -> Self {
+        Self {
+            level,
+            bundled_index: None,
+        }
+    }
+
+    fn bundled(index: usize) -> Self {
+        let (_, source) = BUNDLED_LEVELS[index];
+        Self {
+            level: Level::parse(source).expect("bundled levels are valid"),
+            bundled_index: Some(index),
+        }
+    }
+
+    /// The bundled level called `name`, if there is one.
+    pub fn bundled_named(name: &str) -> Option<Self> {
+        BUNDLED_LEVELS
+            .iter()
+            .position(|(bundled, _)| *bundled == name)
+            .map(Self::bundled)
+    }
+
+    /// The bundled level played when no other one was asked for.
+    pub fn default_level() -> Self {
+        let default = BUNDLED_LEVELS
+            .iter()
+            .position(|(name, _)| *name == DEFAULT_LEVEL)
+            .unwrap();
+        Self::bundled(default)
+    }
+
+    /// Switches to the next bundled level.
+    pub fn cycle(&mut self) {
+        let next = self
+            .bundled_index
+            .map_or(0, |i| (i + 1) % BUNDLED_LEVELS.len());
+        *self = Self::bundled(next);
+    }
+}
+
+impl FromWorld for CurrentLevel {
+    fn from_world(world: &mut World) -> Self {
+        let requested = world
+            .get_resource::<CliArgs>()
+            .and_then(|cli| cli.level.clone());
+        if let Some(name) = requested {
+            match Level::find(&name) {
+                Ok(level) => {
+                    let bundled_index = BUNDLED_LEVELS.iter().position(|(n, _)| *n == name);
+                    return Self {
+                        level,
+                        bundled_index,
+                    };
+                }
+                Err(err) => error!("Failed to load level {}: {}", name, err),
+            }
+        }
+        Self::default_level()
+    }
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>().add_system_set(
+            SystemSet::on_enter(GameState::Playing).with_system(spawn_level.after(seed_run)),
+        );
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    current: Res<CurrentLevel>,
+    rules: Res<GameRules>,
+    mut rng: ResMut<GameRng>,
+    tiles: Query<
+        Entity,
+        Or<(
+            With<Wall>,
+            With<Portal>,
+            With<Terrain>,
+            With<DoorKey>,
+            With<Spike>,
+            With<SpeedPad>,
+        )>,
+    >,
+) {
+    for entt in tiles.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    for position in current.level.walls() {
+        spawn_wall(&mut commands, position);
+    }
+    spawn_doors(&mut commands, &current.level);
+    for (position, terrain) in current.level.terrain() {
+        spawn_terrain(&mut commands, position, terrain);
+    }
+    for position in current.level.spikes() {
+        spawn_spike(&mut commands, position);
+    }
+    spawn_pads(&mut commands, &current.level);
+    spawn_portals(&mut commands, &current.level);
+    if rules.obstacle_density > 0.0 {
+        for position in obstacles::generate(&current.level, rules.obstacle_density, rng.board()) {
+            spawn_wall(&mut commands, position);
+        }
+    }
+}
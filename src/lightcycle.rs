@@ -0,0 +1,34 @@
+//! Light cycle rules. Players' snakes grow by a segment on every step, so
+//! every cell they've been through stays behind them as a trail to crash
+//! into, and nothing makes them shrink. The run lasts as long as there's
+//! room left to move.
+
+use bevy::prelude::*;
+
+use crate::rules::GameRules;
+use crate::tick::MovementTick;
+use crate::{snake_growth, snake_movement, GrowthEvent, Player};
+
+pub struct LightCyclePlugin;
+
+impl Plugin for LightCyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(MovementTick)
+                .with_system(lay_trails.after(snake_movement).before(snake_growth)),
+        );
+    }
+}
+
+fn lay_trails(
+    rules: Res<GameRules>,
+    mut growth_writer: EventWriter<GrowthEvent>,
+    players: Query<Entity, With<Player>>,
+) {
+    if !rules.light_cycle {
+        return;
+    }
+    // Trail isn't worth any points, only food is.
+    growth_writer.send_batch(players.iter().map(|snake| GrowthEvent { snake, value: 0 }));
+}
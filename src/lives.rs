@@ -0,0 +1,144 @@
+//! Extra lives: losing one respawns the snake at the level's start after a
+//! short countdown, keeping the score.
+
+use bevy::prelude::*;
+
+use crate::input_map::InputMap;
+use crate::level::CurrentLevel;
+use crate::rules::GameRules;
+use crate::state::{GameState, GameStateEvent};
+use crate::ui;
+use crate::walls::Wall;
+use crate::{
+    despawn_snake, spawn_snake, Direction, Grid, Player, Position, SnakeSegments, SNAKE_COLOR,
+};
+
+/// Seconds between losing a life and the snake moving again.
+const RESPAWN_DELAY: f32 = 3.0;
+
+/// Lives left in the current run, including the one being played.
+#[derive(Default)]
+pub struct Lives(pub u32);
+
+struct RespawnTimer(Timer);
+
+#[derive(Component)]
+struct RespawnOverlay;
+
+#[derive(Component)]
+struct CountdownText;
+
+pub struct LivesPlugin;
+
+impl Plugin for LivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lives>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_lives))
+            .add_system_set(SystemSet::on_enter(GameState::Respawning).with_system(respawn_snake))
+            .add_system_set(SystemSet::on_update(GameState::Respawning).with_system(countdown))
+            .add_system_set(SystemSet::on_exit(GameState::Respawning).with_system(despawn_overlay));
+    }
+}
+
+fn reset_lives(mut lives: ResMut<Lives>, rules: Res<GameRules>) {
+    lives.0 = rules.lives;
+}
+
+/// Where to put the snake back: the level's start unless walls have been
+/// added around it since, e.g. by the shrinking arena. Otherwise the clear
+/// spot closest to the middle of the board.
+fn respawn_point(
+    grid: Grid,
+    start: Position,
+    direction: Direction,
+    walls: &[Position],
+) -> Position {
+    let clear = |pos: Position| {
+        [pos, pos.step(direction), pos.step(direction.opposite())]
+            .iter()
+            .all(|cell| !walls.contains(cell))
+    };
+    if clear(start) {
+        return start;
+    }
+    let (mid_x, mid_y) = (grid.width as i32 / 2, grid.height as i32 / 2);
+    (1..grid.width as i32 - 1)
+        .flat_map(|x| (1..grid.height as i32 - 1).map(move |y| Position { x, y }))
+        .filter(|pos| clear(*pos))
+        .min_by_key(|pos| (pos.x - mid_x).abs() + (pos.y - mid_y).abs())
+        .unwrap_or(start)
+}
+
+/// Puts a fresh snake on the start position and shows the countdown before
+/// it starts moving.
+fn respawn_snake(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current: Res<CurrentLevel>,
+    rules: Res<GameRules>,
+    input_map: Res<InputMap>,
+    grid: Res<Grid>,
+    lives: Res<Lives>,
+    players: Query<&SnakeSegments, With<Player>>,
+    walls: Query<&Position, With<Wall>>,
+) {
+    for segments in players.iter() {
+        despawn_snake(&mut commands, segments);
+    }
+    commands.insert_resource(RespawnTimer(Timer::from_seconds(RESPAWN_DELAY, false)));
+    commands
+        .spawn_bundle(NodeBundle {
+            color: UiColor(Color::NONE),
+            ..ui::overlay_node()
+        })
+        .insert(RespawnOverlay)
+        .with_children(|parent| {
+            let lives_left = match lives.0 {
+                1 => "Last life!".to_string(),
+                n => format!("{} lives left", n),
+            };
+            parent.spawn_bundle(ui::text_line(
+                lives_left,
+                ui::text_style(&asset_server, 40.0),
+            ));
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 96.0)))
+                .insert(CountdownText);
+        });
+    let walls: Vec<Position> = walls.iter().copied().collect();
+    let direction = current.level.start_direction;
+    let player = spawn_snake(
+        &mut commands,
+        respawn_point(*grid, current.level.start, direction, &walls),
+        direction,
+        SNAKE_COLOR,
+    );
+    commands
+        .entity(player)
+        .insert(Player(1))
+        .insert(input_map.player(1, rules.mode.two_player()).clone());
+}
+
+fn countdown(
+    time: Res<Time>,
+    mut timer: ResMut<RespawnTimer>,
+    mut state_writer: EventWriter<GameStateEvent>,
+    mut texts: Query<&mut Text, With<CountdownText>>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        state_writer.send(GameStateEvent::Resume);
+    }
+    let seconds_left = (timer.0.duration() - timer.0.elapsed())
+        .as_secs_f32()
+        .ceil();
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = seconds_left.to_string();
+    }
+}
+
+fn despawn_overlay(mut commands: Commands, overlays: Query<Entity, With<RespawnOverlay>>) {
+    for entt in overlays.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    commands.remove_resource::<RespawnTimer>();
+}
@@ -1,25 +1,246 @@
+// Bevy systems take their resources and queries as parameters.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+use bevy::ecs::query::{FilterFetch, WorldQuery};
 use bevy::prelude::*;
-use bevy::{core::FixedTimestep, render::camera::ScalingMode};
-use rand::prelude::random;
+use bevy::render::camera::ScalingMode;
+use bevy::window::WindowPlugin;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+mod arena;
+mod autopilot;
+mod biomes;
+mod board;
+mod bonus;
+mod boss;
+mod campaign;
+mod cli;
+mod color;
+mod colorblind;
+mod combo;
+mod daily;
+mod dash;
+mod death;
+mod debugger;
+mod demo;
+mod doors;
+mod editor;
+mod enemies;
+mod expanding;
+mod fog;
+mod food;
+mod food_sprites;
+mod framestep;
+mod gameover;
+mod gamepad;
+mod ghost;
+mod glide;
+mod handheld;
+mod high_contrast;
+mod highscore;
+mod hud;
+mod hunger;
+mod input_map;
+mod invisible;
+#[cfg(feature = "online-leaderboard")]
+mod leaderboard;
+mod level;
+mod lightcycle;
+mod lives;
+mod maze;
+mod menu;
+mod mouse;
+mod net;
+mod objectives;
+mod obstacles;
+mod pads;
+mod particles;
+mod pathfinding;
+mod pause;
+mod persistence;
+mod portals;
+mod powerups;
+mod quit;
+mod replay;
+mod rewind;
+mod rng;
+mod rules;
+mod score;
+mod sequence;
+mod settings;
+mod shake;
+mod skins;
+mod snake_sprites;
+mod sound;
+mod spikes;
+mod sprint;
+mod state;
+mod stats;
+mod switch_access;
+mod terrain;
+mod theme;
+mod tick;
+mod timed;
+mod touch;
+mod tutorial;
+mod ui;
+mod versus;
+mod walls;
+mod world_events;
+
+use arena::ArenaPlugin;
+use autopilot::AutopilotPlugin;
+use biomes::BiomesPlugin;
+use board::BoardPlugin;
+use bonus::BonusRoundPlugin;
+use boss::BossPlugin;
+use campaign::CampaignPlugin;
+use cli::CliArgs;
+use colorblind::ColorblindPlugin;
+use combo::ComboPlugin;
+use daily::DailyPlugin;
+use dash::{Dash, DashPlugin};
+use death::{DeathPlugin, Dying};
+use debugger::DebuggerPlugin;
+use demo::{Demo, DemoPlugin};
+use doors::DoorPlugin;
+use editor::EditorPlugin;
+use enemies::EnemyPlugin;
+use expanding::ExpandingPlugin;
+use fog::FogPlugin;
+use food::{Food, FoodKind, FoodPlugin};
+use food_sprites::FoodSpritesPlugin;
+use framestep::FrameStepPlugin;
+use gameover::GameOverPlugin;
+use gamepad::GamepadPlugin;
+use ghost::GhostPlugin;
+use glide::GlidePlugin;
+use handheld::HandheldPlugin;
+use high_contrast::HighContrastPlugin;
+use highscore::{HighScorePlugin, HighScores};
+use hud::HudPlugin;
+use hunger::HungerPlugin;
+use input_map::{Controls, InputMap, InputMapPlugin};
+use invisible::InvisibleTailPlugin;
+use level::{CurrentLevel, Level, LevelPlugin};
+use lightcycle::LightCyclePlugin;
+use lives::{Lives, LivesPlugin};
+use menu::MenuPlugin;
+use mouse::MouseSteeringPlugin;
+use net::NetPlugin;
+use objectives::ObjectivesPlugin;
+use pads::SpeedPad;
+use particles::ParticlesPlugin;
+use pause::PausePlugin;
+use portals::Portal;
+use powerups::{ActiveEffects, PowerUpKind, PowerUpPlugin};
+use quit::{ConfirmCloseWindowPlugin, QuitPlugin};
+use replay::{Playback, ReplayPlugin};
+use rewind::RewindPlugin;
+use rng::RngPlugin;
+use rules::{GameMode, GameRules};
+use score::{Score, ScorePlugin};
+use sequence::{FoodNumber, Sequence, SequencePlugin};
+use settings::Settings;
+use shake::ShakePlugin;
+use skins::SkinsPlugin;
+use snake_sprites::SnakeSpritesPlugin;
+use sound::SoundPlugin;
+use spikes::SpikePlugin;
+use sprint::SprintPlugin;
+use state::{GameState, GameStateEvent, StatePlugin};
+use stats::StatsPlugin;
+use switch_access::SwitchAccessPlugin;
+use terrain::Terrain;
+use theme::ThemePlugin;
+use tick::{movement_tick, MovementTick, TickPlugin};
+use timed::TimedPlugin;
+use touch::TouchPlugin;
+use tutorial::TutorialPlugin;
+use versus::VersusPlugin;
+use walls::Wall;
+use world_events::WorldEventsPlugin;
 
-const GRID_WIDTH: u32 = 20;
-const GRID_HEIGHT: u32 = 20;
 const CLEAR_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
-const ASPECT_RATIO: f32 = 1.0 / 1.0;
+const ASPECT_RATIO: f32 = 1.0;
 const SNAKE_COLOR: Color = Color::rgb(0.4, 1.0, 0.2);
-const FOOD_COLOR: Color = Color::rgb(1.0, 0.65, 0.0);
-// const WALL_COLOR: Color = Color::BLACK;
-// const PATH_COLOR: Color = Color::WHITE;
+const INPUT_QUEUE_LEN: usize = 2;
+/// Segments lost when eating poison.
+const POISON_SHRINK: usize = 2;
 
 #[derive(Component)]
 struct SnakeHead {
     direction: Direction,
+    /// Turns requested since the last movement tick, applied one per tick.
+    input_queue: VecDeque<Direction>,
+    /// Color of the whole snake, used for segments added as it grows.
+    color: Color,
+}
+
+impl SnakeHead {
+    fn new(direction: Direction, color: Color) -> Self {
+        Self {
+            direction,
+            input_queue: VecDeque::with_capacity(INPUT_QUEUE_LEN),
+            color,
+        }
+    }
+
+    /// The way the snake goes once the turns already queued are taken.
+    fn heading(&self) -> Direction {
+        self.input_queue.back().copied().unwrap_or(self.direction)
+    }
+
+    /// Buffers a turn unless the queue is full or the turn would repeat or
+    /// reverse the direction the snake will be travelling in at that point.
+    fn queue_turn(&mut self, dir: Direction) {
+        let last = self.heading();
+        if self.input_queue.len() < INPUT_QUEUE_LEN && dir != last && dir != last.opposite() {
+            self.input_queue.push_back(dir);
+        }
+    }
+
+    fn apply_next_turn(&mut self) {
+        if let Some(dir) = self.input_queue.pop_front() {
+            if dir != self.direction.opposite() {
+                self.direction = dir;
+            }
+        }
+    }
+
+    /// Recolors the whole snake unless it's `color` already. Segments grown
+    /// from now on take the head's color.
+    fn paint<F>(
+        &mut self,
+        segments: &SnakeSegments,
+        sprites: &mut Query<&mut Sprite, F>,
+        color: Color,
+    ) where
+        F: WorldQuery,
+        F::Fetch: FilterFetch,
+    {
+        if self.color == color {
+            return;
+        }
+        self.color = color;
+        for entt in segments.iter() {
+            if let Ok(mut sprite) = sprites.get_mut(*entt) {
+                sprite.color = color;
+            }
+        }
+    }
 }
 
+/// Marks the head of a snake controlled by a player, numbered from 1.
+#[derive(Component)]
+struct Player(u8);
+
 #[derive(Component)]
 struct SnakeSegment;
 
-#[derive(Default)]
+/// Every entity making up a snake, head first. Kept on the head.
+#[derive(Component, Default)]
 struct SnakeSegments(Vec<Entity>);
 
 impl SnakeSegments {
@@ -30,20 +251,55 @@ impl SnakeSegments {
     fn push(&mut self, value: Entity) {
         self.0.push(value);
     }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Detaches the last `count` segments, returning their entities.
+    fn remove_tail(&mut self, count: usize) -> Vec<Entity> {
+        let keep = self.0.len().saturating_sub(count);
+        self.0.split_off(keep)
+    }
 }
 
-#[derive(Default)]
+/// Where a snake's tail was before its last step, so it can grow into it.
+/// Kept on the head.
+#[derive(Component, Default)]
 struct LastSnakeSegmentPosition(Option<Position>);
 
-#[derive(Component)]
-struct Food;
-
-#[derive(Component, Clone, Copy, PartialEq, Eq)]
+/// A cell on the board. Anything with one takes up its cell as far as
+/// spawning goes, see `food::Occupying`.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 struct Position {
     x: i32,
     y: i32,
 }
 
+impl Position {
+    /// The neighbouring cell in `direction`.
+    fn step(self, direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Self {
+                y: self.y + 1,
+                ..self
+            },
+            Direction::Left => Self {
+                x: self.x - 1,
+                ..self
+            },
+            Direction::Right => Self {
+                x: self.x + 1,
+                ..self
+            },
+            Direction::Down => Self {
+                y: self.y - 1,
+                ..self
+            },
+        }
+    }
+}
+
 #[derive(Component)]
 struct Size {
     width: f32,
@@ -58,7 +314,54 @@ impl Size {
     }
 }
 
-#[derive(PartialEq, Copy, Clone)]
+/// The size of the board in cells. It starts out as the current level's size
+/// and only a run in `GameMode::Expanding` changes it, see `expanding`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Grid {
+    width: u32,
+    height: u32,
+}
+
+impl FromWorld for Grid {
+    fn from_world(world: &mut World) -> Self {
+        Self::of(&world.resource::<CurrentLevel>().level)
+    }
+}
+
+impl Grid {
+    fn of(level: &Level) -> Self {
+        Self {
+            width: level.width,
+            height: level.height,
+        }
+    }
+
+    fn contains(self, pos: Position) -> bool {
+        pos.x >= 0 && pos.y >= 0 && (pos.x as u32) < self.width && (pos.y as u32) < self.height
+    }
+
+    /// `pos` brought back onto the board from the opposite side.
+    fn wrap(self, pos: Position) -> Position {
+        Position {
+            x: pos.x.rem_euclid(self.width as i32),
+            y: pos.y.rem_euclid(self.height as i32),
+        }
+    }
+
+    /// Every cell on the board, column by column.
+    fn cells(self) -> impl Iterator<Item = Position> {
+        (0..self.width as i32)
+            .flat_map(move |x| (0..self.height as i32).map(move |y| Position { x, y }))
+    }
+
+    /// The side of a cell in pixels. Cells stay square, so a board that's a
+    /// different shape from the window leaves a border on two sides.
+    fn cell_size(self, window: &Window) -> f32 {
+        (window.width() / self.width as f32).min(window.height() / self.height as f32)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 enum Direction {
     Up,
     Left,
@@ -74,14 +377,71 @@ impl Direction {
             Self::Down => Self::Up,
         }
     }
+
+    /// The next way round, clockwise.
+    fn clockwise(self) -> Self {
+        match self {
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+            Self::Left => Self::Up,
+        }
+    }
+}
+
+struct GrowthEvent {
+    /// Head of the snake that grows.
+    snake: Entity,
+    /// Points earned by the food that caused the growth.
+    value: u32,
+}
+/// A player's snake died.
+struct GameOverEvent {
+    snake: Entity,
+    cause: DeathCause,
 }
 
-struct GrowthEvent;
-struct GameOverEvent;
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DeathCause {
+    /// Ran into a wall or off the board.
+    Wall,
+    OwnBody,
+    /// Ran into another snake, or head to head with one.
+    OtherSnake,
+    /// Shrank away to nothing from poison or hunger.
+    Shrank,
+    Spikes,
+    /// Ate numbered food out of order, see `sequence`.
+    WrongNumber,
+}
+
+impl DeathCause {
+    fn label(self) -> &'static str {
+        match self {
+            DeathCause::Wall => "Hit a wall",
+            DeathCause::OwnBody => "Ran into itself",
+            DeathCause::OtherSnake => "Ran into another snake",
+            DeathCause::Shrank => "Shrank away",
+            DeathCause::Spikes => "Caught on spikes",
+            DeathCause::WrongNumber => "Ate the wrong number",
+        }
+    }
+}
+
+/// The player ate a piece of food.
+struct FoodEatenEvent {
+    kind: FoodKind,
+}
+
+struct ShrinkEvent {
+    snake: Entity,
+    segments: usize,
+}
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(CLEAR_COLOR))
+    let settings = Settings::load();
+    let mut app = App::new();
+    app.insert_resource(ClearColor(CLEAR_COLOR))
         .insert_resource(WindowDescriptor {
             title: "Pathfinding Visualizer".to_string(),
             width: 800.0,
@@ -93,12 +453,15 @@ fn main() {
             cursor_visible: true,
             ..Default::default()
         })
-        .insert_resource(SnakeSegments::default())
-        .insert_resource(LastSnakeSegmentPosition::default())
+        .insert_resource(settings.rules())
+        .insert_resource(settings.controls.clone())
+        .insert_resource(settings)
+        .insert_resource(CliArgs::parse())
         .add_event::<GrowthEvent>()
         .add_event::<GameOverEvent>()
+        .add_event::<ShrinkEvent>()
+        .add_event::<FoodEatenEvent>()
         .add_startup_system(setup_camera)
-        .add_startup_system(spawn_snake)
         .add_system_set_to_stage(
             CoreStage::PostUpdate,
             SystemSet::new()
@@ -107,20 +470,91 @@ fn main() {
         )
         .add_system_set(
             SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(0.2))
+                .with_run_criteria(movement_tick.label(MovementTick))
                 .with_system(snake_movement)
-                .with_system(game_over.after(snake_movement))
-                .with_system(snake_eating.after(game_over))
-                .with_system(snake_growth.after(snake_eating)),
+                .with_system(snake_eating.after(snake_movement))
+                .with_system(snake_shrink.after(snake_eating))
+                .with_system(game_over.after(snake_shrink))
+                .with_system(snake_growth.after(game_over)),
         )
         .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(1.0))
-                .with_system(spawn_food),
+            SystemSet::on_update(GameState::Playing)
+                .with_system(snake_movement_input.before(snake_movement)),
         )
-        .add_system(snake_movement_input.before(snake_movement))
-        .add_plugins(DefaultPlugins)
-        .run();
+        .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_game))
+        .add_plugins_with(DefaultPlugins, |group| {
+            group
+                .add_before::<WindowPlugin, _>(ConfirmCloseWindowPlugin)
+                .disable::<WindowPlugin>()
+        })
+        .add_plugin(StatePlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(PausePlugin)
+        .add_plugin(QuitPlugin)
+        .add_plugin(ScorePlugin)
+        .add_plugin(HighScorePlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(InputMapPlugin)
+        .add_plugin(GamepadPlugin)
+        .add_plugin(TouchPlugin)
+        .add_plugin(MouseSteeringPlugin)
+        .add_plugin(SwitchAccessPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(RngPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(BoardPlugin)
+        .add_plugin(DoorPlugin)
+        .add_plugin(SpikePlugin)
+        .add_plugin(FoodPlugin)
+        .add_plugin(BossPlugin)
+        .add_plugin(TickPlugin)
+        .add_plugin(PowerUpPlugin)
+        .add_plugin(HungerPlugin)
+        .add_plugin(LivesPlugin)
+        .add_plugin(ComboPlugin)
+        .add_plugin(ObjectivesPlugin)
+        .add_plugin(TimedPlugin)
+        .add_plugin(ArenaPlugin)
+        .add_plugin(ExpandingPlugin)
+        .add_plugin(SequencePlugin)
+        .add_plugin(BiomesPlugin)
+        .add_plugin(LightCyclePlugin)
+        .add_plugin(DashPlugin)
+        .add_plugin(SprintPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(SnakeSpritesPlugin)
+        .add_plugin(FoodSpritesPlugin)
+        .add_plugin(GlidePlugin)
+        .add_plugin(ParticlesPlugin)
+        .add_plugin(DeathPlugin)
+        .add_plugin(ShakePlugin)
+        .add_plugin(ThemePlugin)
+        .add_plugin(ColorblindPlugin)
+        .add_plugin(HighContrastPlugin)
+        .add_plugin(SkinsPlugin)
+        .add_plugin(WorldEventsPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(VersusPlugin)
+        .add_plugin(NetPlugin)
+        .add_plugin(AutopilotPlugin)
+        .add_plugin(DemoPlugin)
+        .add_plugin(DailyPlugin)
+        .add_plugin(CampaignPlugin)
+        .add_plugin(BonusRoundPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(GhostPlugin)
+        .add_plugin(FogPlugin)
+        .add_plugin(HandheldPlugin)
+        .add_plugin(InvisibleTailPlugin)
+        .add_plugin(RewindPlugin)
+        .add_plugin(FrameStepPlugin)
+        .add_plugin(TutorialPlugin)
+        .add_plugin(DebuggerPlugin)
+        .add_plugin(EditorPlugin)
+        .add_plugin(GameOverPlugin);
+    #[cfg(feature = "online-leaderboard")]
+    app.add_plugin(leaderboard::LeaderboardPlugin);
+    app.run();
 }
 
 fn setup_camera(mut commands: Commands) {
@@ -135,108 +569,218 @@ fn setup_camera(mut commands: Commands) {
     commands.spawn_bundle(camera);
 }
 
-fn spawn_snake(mut commands: Commands, mut segments: ResMut<SnakeSegments>) {
-    *segments = SnakeSegments(vec![
-        commands
-            .spawn_bundle(SpriteBundle {
-                sprite: Sprite {
-                    color: SNAKE_COLOR,
-                    ..default()
-                },
-                ..default()
-            })
-            .insert(SnakeHead {
-                direction: Direction::Up,
-            })
-            .insert(Position { x: 3, y: 3 })
-            .insert(Size::square(0.8))
-            .id(),
-        spawn_snake_segment(commands, Position { x: 3, y: 2 }),
-    ]);
+/// Spawns a two segment snake with its head on `start`, returning the head.
+fn spawn_snake(
+    commands: &mut Commands,
+    start: Position,
+    direction: Direction,
+    color: Color,
+) -> Entity {
+    let head = commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            ..default()
+        })
+        .insert(SnakeHead::new(direction, color))
+        .insert(start)
+        .insert(Size::square(0.8))
+        .id();
+    let tail = spawn_snake_segment(commands, start.step(direction.opposite()), color);
+    commands
+        .entity(head)
+        .insert(SnakeSegments(vec![head, tail]))
+        .insert(LastSnakeSegmentPosition::default());
+    head
+}
+
+fn despawn_snake(commands: &mut Commands, segments: &SnakeSegments) {
+    for entt in segments.iter() {
+        commands.entity(*entt).despawn();
+    }
 }
 
 fn snake_movement(
-    segments: ResMut<SnakeSegments>,
-    mut heads: Query<(Entity, &SnakeHead)>,
-    mut positions: Query<&mut Position>,
-    mut last_segment_pos: ResMut<LastSnakeSegmentPosition>,
+    mut commands: Commands,
+    mut heads: Query<(
+        Entity,
+        &mut SnakeHead,
+        &SnakeSegments,
+        &mut LastSnakeSegmentPosition,
+        Option<&Player>,
+        Option<&mut Dash>,
+    )>,
+    mut positions: Query<
+        &mut Position,
+        (
+            Without<Wall>,
+            Without<Portal>,
+            Without<Terrain>,
+            Without<SpeedPad>,
+        ),
+    >,
+    walls: Query<&Position, With<Wall>>,
+    portals: Query<(&Position, &Portal)>,
+    terrain: Query<(&Position, &Terrain)>,
+    pads: Query<(&Position, &SpeedPad)>,
     mut game_over_writer: EventWriter<GameOverEvent>,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    mut effects: ResMut<ActiveEffects>,
 ) {
-    if let Some((head_entity, head)) = heads.iter_mut().next() {
-        let segment_positions = segments
+    // Every snake collides with where the others were before this tick, so
+    // the order they move in doesn't matter.
+    let bodies: Vec<(Entity, Vec<Position>)> = heads
+        .iter()
+        .map(|(snake, _, segments, ..)| {
+            let body = segments
+                .iter()
+                .map(|e| *positions.get(*e).unwrap())
+                .collect();
+            (snake, body)
+        })
+        .collect();
+    let zen = rules.mode == GameMode::Zen;
+    // Co-op players can be allowed to slide through each other.
+    let friendly_pass = rules.mode == GameMode::Coop && !rules.friendly_collisions;
+    let players: Vec<Entity> = heads
+        .iter()
+        .filter(|(.., player, _)| player.is_some())
+        .map(|(snake, ..)| snake)
+        .collect();
+    let solid = |snake: &Entity, other: &Entity| {
+        !(friendly_pass && other != snake && players.contains(snake) && players.contains(other))
+    };
+    let wrap = |pos: Position| {
+        if rules.wrap_edges || zen {
+            grid.wrap(pos)
+        } else {
+            pos
+        }
+    };
+
+    let mut moves = Vec::with_capacity(bodies.len());
+    for (snake, body) in bodies.iter() {
+        let (_, mut head, .., dash) = heads.get_mut(*snake).unwrap();
+        // A head on ice slides on the way it was going, and any turn queued
+        // on the way onto it is lost.
+        if on_ice(&terrain, body[0]) {
+            head.input_queue.clear();
+        } else {
+            head.apply_next_turn();
+        }
+        // A head standing on a portal comes out next to its partner. The body
+        // follows through on its own since segments take the positions in
+        // front of them.
+        let from = portals
             .iter()
-            .map(|e| *positions.get_mut(*e).unwrap())
-            .collect::<Vec<Position>>();
-        let mut head_pos = positions.get_mut(head_entity).unwrap();
-        match &head.direction {
-            Direction::Up => {
-                head_pos.y += 1;
+            .find(|(pos, _)| **pos == body[0])
+            .map_or(body[0], |(_, portal)| portal.exit);
+        let next = wrap(from.step(head.direction));
+        // Moving onto a speed pad carries on a further cell the pad's way.
+        // Both cells have to be clear.
+        let mut path = vec![next];
+        if let Some((_, pad)) = pads.iter().find(|(pos, _)| **pos == next) {
+            path.push(wrap(next.step(pad.0)));
+        }
+        // A dash carries on one more cell the way the snake is heading.
+        if dash.is_some_and(|mut dash| dash.take()) {
+            path.push(wrap(path[path.len() - 1].step(head.direction)));
+        }
+        moves.push((*snake, path));
+    }
+
+    for (snake, body) in bodies.iter() {
+        let (_, _, segments, mut last_segment_pos, player, _) = heads.get_mut(*snake).unwrap();
+        let path = &moves.iter().find(|(s, _)| s == snake).unwrap().1;
+        let ghost = player.is_some() && effects.is_active(PowerUpKind::Ghost);
+        let collision = |next: &Position| {
+            let hit_snake = bodies
+                .iter()
+                .find(|(other, body)| {
+                    body.contains(next) && !(other == snake && ghost) && solid(snake, other)
+                })
+                .map(|(other, _)| other)
+                .or_else(|| {
+                    moves
+                        .iter()
+                        .find(|(other, path)| {
+                            other != snake && path.contains(next) && solid(snake, other)
+                        })
+                        .map(|(other, _)| other)
+                });
+            if !grid.contains(*next) || walls.iter().any(|wall_pos| wall_pos == next) {
+                Some(DeathCause::Wall)
+            } else if hit_snake == Some(snake) {
+                Some(DeathCause::OwnBody)
+            } else {
+                hit_snake.map(|_| DeathCause::OtherSnake)
             }
-            Direction::Left => {
-                head_pos.x -= 1;
+        };
+        let cause = path.iter().find_map(collision);
+        if let Some(cause) = cause {
+            if player.is_none() {
+                despawn_snake(&mut commands, segments);
+                continue;
             }
-            Direction::Right => {
-                head_pos.x += 1;
+            if zen || effects.consume_shield() {
+                // Stay in the cell the head came from and skip this step,
+                // giving the player a chance to turn away. In zen mode the
+                // snake just waits there until it's turned.
+                continue;
             }
-            Direction::Down => {
-                head_pos.y -= 1;
+            game_over_writer.send(GameOverEvent {
+                snake: *snake,
+                cause,
+            });
+            // A casual run waits at the crash to be rewound, so the snake
+            // stays on the board.
+            if rules.mode == GameMode::Casual {
+                continue;
             }
-        };
-        if head_pos.x < 0
-            || head_pos.y < 0
-            || head_pos.x as u32 >= GRID_WIDTH
-            || head_pos.y as u32 >= GRID_HEIGHT
-            || segment_positions.contains(&head_pos)
-        {
-            game_over_writer.send(GameOverEvent);
         }
-        segment_positions
+        // Every cell the head passes through, newest first, followed by the
+        // body. Each segment takes the next position along, so a head pushed
+        // two cells pulls the whole body along two.
+        let trail: Vec<Position> = path.iter().rev().chain(body.iter()).copied().collect();
+        trail
             .iter()
-            // Skip first so each segment gets paired with the position of the
-            // segment in front.
-            .zip(segments.iter().skip(1))
+            .zip(segments.iter())
             .for_each(|(pos, segment)| {
                 *positions.get_mut(*segment).unwrap() = *pos;
             });
-        *last_segment_pos = LastSnakeSegmentPosition(Some(
-            *segment_positions
-                .last()
-                .expect("Snake is at least one segment long"),
-        ));
+        *last_segment_pos = LastSnakeSegmentPosition(Some(trail[segments.len()]));
     }
 }
 
-#[rustfmt::skip]
+/// Whether `pos` is on ice, where snakes can't turn.
+fn on_ice(terrain: &Query<(&Position, &Terrain)>, pos: Position) -> bool {
+    terrain
+        .iter()
+        .any(|(tile_pos, terrain)| *tile_pos == pos && terrain.slippery())
+}
+
 fn snake_movement_input(
     kbd_input: Res<Input<KeyCode>>,
-    mut heads: Query<&mut SnakeHead>,
+    rules: Res<GameRules>,
+    mut heads: Query<(&mut SnakeHead, &Position, &Controls)>,
+    terrain: Query<(&Position, &Terrain)>,
 ) {
-    // Only one entity has the SnakeHead component
-    if let Some(mut head) = heads.iter_mut().next() {
-        let dir: Direction = if kbd_input.pressed(KeyCode::Up) {
-            Direction::Up
-        } else if kbd_input.pressed(KeyCode::Left) {
-            Direction::Left
-        } else if kbd_input.pressed(KeyCode::Right) {
-            Direction::Right
-        } else if kbd_input.pressed(KeyCode::Down) {
-            Direction::Down
-        } else {
-            head.direction
-        };
-        if dir != head.direction.opposite() {
-            head.direction = dir;
+    for (mut head, pos, controls) in heads.iter_mut() {
+        if on_ice(&terrain, *pos) {
+            continue;
+        }
+        for key in kbd_input.get_just_pressed() {
+            if let Some(direction) = controls.direction(*key) {
+                head.queue_turn(rules.mirror.apply(direction));
+            }
         }
     }
 }
 
-fn spawn_snake_segment(mut commands: Commands, position: Position) -> Entity {
+fn spawn_snake_segment(commands: &mut Commands, position: Position, color: Color) -> Entity {
     commands
         .spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: SNAKE_COLOR,
-                ..default()
-            },
+            sprite: Sprite { color, ..default() },
             ..default()
         })
         .insert(SnakeSegment)
@@ -248,84 +792,233 @@ fn spawn_snake_segment(mut commands: Commands, position: Position) -> Entity {
 fn snake_eating(
     mut commands: Commands,
     mut growth_writer: EventWriter<GrowthEvent>,
-    food_positions: Query<(Entity, &Position), With<Food>>,
-    head_positions: Query<&Position, With<SnakeHead>>,
+    mut shrink_writer: EventWriter<ShrinkEvent>,
+    mut eaten_writer: EventWriter<FoodEatenEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+    mut sequence: ResMut<Sequence>,
+    food_positions: Query<(Entity, &Position, &Food, Option<&FoodNumber>)>,
+    heads: Query<(Entity, &Position, Option<&Player>), With<SnakeHead>>,
 ) {
-    for head_pos in head_positions.iter() {
-        for (entt, food_pos) in food_positions.iter() {
+    for (snake, head_pos, player) in heads.iter() {
+        for (entt, food_pos, food, number) in food_positions.iter() {
             if food_pos == head_pos {
+                // Numbered food is the players' to eat, in order.
+                if let Some(FoodNumber(number)) = number {
+                    if player.is_none() {
+                        continue;
+                    }
+                    if *number != sequence.next {
+                        game_over_writer.send(GameOverEvent {
+                            snake,
+                            cause: DeathCause::WrongNumber,
+                        });
+                        continue;
+                    }
+                    sequence.next += 1;
+                }
                 commands.entity(entt).despawn();
-                growth_writer.send(GrowthEvent);
+                if player.is_some() {
+                    eaten_writer.send(FoodEatenEvent { kind: food.kind });
+                }
+                if food.kind == FoodKind::Poison {
+                    shrink_writer.send(ShrinkEvent {
+                        snake,
+                        segments: POISON_SHRINK,
+                    });
+                } else {
+                    // The first event carries the points, any extra ones
+                    // just add length.
+                    growth_writer.send_batch((0..food.kind.growth()).map(|i| GrowthEvent {
+                        snake,
+                        value: if i == 0 { food.kind.value() } else { 0 },
+                    }));
+                }
             }
         }
     }
 }
 
+fn snake_shrink(
+    mut commands: Commands,
+    mut snakes: Query<(&mut SnakeSegments, Option<&Player>)>,
+    mut shrink_reader: EventReader<ShrinkEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+    rules: Res<GameRules>,
+) {
+    for shrink in shrink_reader.iter() {
+        let (mut segments, player) = match snakes.get_mut(shrink.snake) {
+            Ok(snake) => snake,
+            Err(_) => continue,
+        };
+        // Light cycle trails stay where they're laid.
+        if rules.light_cycle && player.is_some() {
+            continue;
+        }
+        // The head alone is the shortest a snake can get.
+        let mut count = shrink.segments;
+        if segments.len() <= count {
+            if player.is_none() {
+                despawn_snake(&mut commands, &segments);
+                continue;
+            }
+            if rules.mode != GameMode::Zen {
+                game_over_writer.send(GameOverEvent {
+                    snake: shrink.snake,
+                    cause: DeathCause::Shrank,
+                });
+                return;
+            }
+            count = segments.len() - 1;
+        }
+        for entt in segments.remove_tail(count) {
+            commands.entity(entt).despawn();
+        }
+    }
+}
+
 fn snake_growth(
-    commands: Commands,
-    last_segment_pos: Res<LastSnakeSegmentPosition>,
-    mut segments: ResMut<SnakeSegments>,
+    mut commands: Commands,
+    mut snakes: Query<(&SnakeHead, &mut SnakeSegments, &LastSnakeSegmentPosition)>,
     mut growth_reader: EventReader<GrowthEvent>,
 ) {
-    if growth_reader.iter().next().is_some() {
-        segments.push(spawn_snake_segment(commands, last_segment_pos.0.unwrap()));
+    // New segments stack on the old tail position and untangle as the snake
+    // moves on.
+    for growth in growth_reader.iter() {
+        if let Ok((head, mut segments, LastSnakeSegmentPosition(Some(tail)))) =
+            snakes.get_mut(growth.snake)
+        {
+            segments.push(spawn_snake_segment(&mut commands, *tail, head.color));
+        }
     }
 }
 
-fn spawn_food(mut commands: Commands) {
-    commands
-        .spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: FOOD_COLOR,
-                ..default()
-            },
-            ..default()
-        })
-        .insert(Food)
-        .insert(Position {
-            x: (random::<f32>() * GRID_WIDTH as f32) as i32,
-            y: (random::<f32>() * GRID_HEIGHT as f32) as i32,
-        })
-        .insert(Size::square(0.8));
+fn game_over(
+    mut reader: EventReader<GameOverEvent>,
+    mut state_writer: EventWriter<GameStateEvent>,
+    players: Query<(&Player, &SnakeSegments)>,
+    score: Res<Score>,
+    mut lives: ResMut<Lives>,
+    rules: Res<GameRules>,
+    mut high_scores: ResMut<HighScores>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+    mut dying: ResMut<Dying>,
+) {
+    // Several collisions in one tick still only cost one life.
+    let snakes: Vec<Entity> = reader.iter().map(|game_over| game_over.snake).collect();
+    if snakes.is_empty() {
+        return;
+    }
+    // Nothing is recorded for the demo, it goes straight back to the menu.
+    if demo.is_active() && lives.0 <= 1 {
+        state_writer.send(GameStateEvent::ReturnToMenu);
+        return;
+    }
+    // Casual runs don't end. The rewind module stops the snake where it
+    // crashed instead.
+    if rules.mode == GameMode::Casual {
+        return;
+    }
+    // Two player runs end with the first death. The versus module works out
+    // who won.
+    let then = if rules.mode.two_player() {
+        GameStateEvent::EndRun
+    } else {
+        lives.0 = lives.0.saturating_sub(1);
+        if lives.0 > 0 {
+            GameStateEvent::LoseLife
+        } else {
+            // A replay's score was recorded when it was played.
+            if !playback.is_active() {
+                high_scores.record(rules.mode, score.0, player_length(&players));
+            }
+            GameStateEvent::EndRun
+        }
+    };
+    // The snakes die on screen first.
+    dying.start(snakes, then);
+    state_writer.send(GameStateEvent::Die);
 }
 
-fn game_over(
+/// Length of player one's snake, `0` while there is none.
+fn player_length(players: &Query<(&Player, &SnakeSegments)>) -> usize {
+    players
+        .iter()
+        .find(|(player, _)| player.0 == 1)
+        .map_or(0, |(_, segments)| segments.len())
+}
+
+fn reset_game(
     mut commands: Commands,
-    mut reader: EventReader<GameOverEvent>,
-    segments_res: ResMut<SnakeSegments>,
+    mut score: ResMut<Score>,
+    current: Res<CurrentLevel>,
+    rules: Res<GameRules>,
+    input_map: Res<InputMap>,
     food: Query<Entity, With<Food>>,
     segments: Query<Entity, With<SnakeSegment>>,
+    heads: Query<Entity, With<SnakeHead>>,
 ) {
-    if reader.iter().next().is_some() { // GameOver event has geen sent
-        for entt in food.iter().chain(segments.iter()) {
-            commands.entity(entt).despawn();
-        }
-        spawn_snake(commands, segments_res);
+    for entt in food.iter().chain(segments.iter()).chain(heads.iter()) {
+        commands.entity(entt).despawn();
     }
+    *score = Score::default();
+    let player = spawn_snake(
+        &mut commands,
+        current.level.start,
+        current.level.start_direction,
+        SNAKE_COLOR,
+    );
+    commands
+        .entity(player)
+        .insert(Player(1))
+        .insert(input_map.player(1, rules.mode.two_player()).clone());
 }
 
-fn size_scaling(windows: Res<Windows>, mut q: Query<(&Size, &mut Transform)>) {
+fn size_scaling(windows: Res<Windows>, grid: Res<Grid>, mut q: Query<(&Size, &mut Transform)>) {
     let window = windows.get_primary().unwrap();
+    let cell_size = grid.cell_size(window);
     for (sprite_size, mut transform) in q.iter_mut() {
         transform.scale = Vec3::new(
-            sprite_size.width * (window.width() / GRID_WIDTH as f32),
-            sprite_size.height * (window.height() / GRID_HEIGHT as f32),
+            sprite_size.width * cell_size,
+            sprite_size.height * cell_size,
             1.0,
         )
     }
 }
 
-fn position_translation(windows: Res<Windows>, mut q: Query<(&Position, &mut Transform)>) {
-    fn convert(pos: f32, length: f32, tile_count: f32) -> f32 {
-        let tile_size = length / tile_count;
-        pos * tile_size - length / 2.0 + tile_size / 2.0
-    }
+/// The grid cell under the mouse cursor, if it's inside the window.
+fn cursor_to_grid(window: &Window, grid: Grid) -> Option<Position> {
+    let cursor = window.cursor_position()?;
+    let cell_size = grid.cell_size(window);
+    let cell = |cursor: f32, length: f32, tile_count: u32| {
+        ((cursor - length / 2.0) / cell_size + tile_count as f32 / 2.0).floor() as i32
+    };
+    let pos = Position {
+        x: cell(cursor.x, window.width(), grid.width),
+        y: cell(cursor.y, window.height(), grid.height),
+    };
+    grid.contains(pos).then_some(pos)
+}
+
+fn position_translation(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut q: Query<(&Position, &mut Transform)>,
+) {
     let window = windows.get_primary().unwrap();
     for (pos, mut transform) in q.iter_mut() {
-        transform.translation = Vec3::new(
-            convert(pos.x as f32, window.width() as f32, GRID_WIDTH as f32),
-            convert(pos.y as f32, window.height() as f32, GRID_HEIGHT as f32),
-            0.0,
-        );
+        // Depth is left as spawned so floor tiles can sit behind the rest.
+        let center = cell_center(window, *grid, *pos);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
     }
 }
+
+/// Where the middle of a grid cell is in world coordinates.
+fn cell_center(window: &Window, grid: Grid, pos: Position) -> Vec2 {
+    let cell_size = grid.cell_size(window);
+    let convert = |pos: i32, tile_count: u32| {
+        (pos as f32 + 0.5) * cell_size - tile_count as f32 * cell_size / 2.0
+    };
+    Vec2::new(convert(pos.x, grid.width), convert(pos.y, grid.height))
+}
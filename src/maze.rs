@@ -0,0 +1,245 @@
+//! Maze levels. The board is split into rooms on every other cell, with walls
+//! in between, and a generator knocks walls down until every room is
+//! connected by exactly one way. They can be played like any other level
+//! and loaded into the search debugger.
+
+use std::collections::HashSet;
+
+use bevy::prelude::KeyCode;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::level::{FoodRules, Level, SpikeTiming};
+use crate::{Direction, Grid, Position};
+
+/// Rooms across and down. With an even board size the last row and column
+/// are left as wall.
+#[derive(Clone, Copy)]
+struct Rooms {
+    wide: i32,
+    high: i32,
+}
+
+impl Rooms {
+    fn fitting(grid: Grid) -> Self {
+        Self {
+            wide: (grid.width as i32 + 1) / 2,
+            high: (grid.height as i32 + 1) / 2,
+        }
+    }
+
+    fn contains(self, room: Position) -> bool {
+        room.x >= 0 && room.y >= 0 && room.x < self.wide && room.y < self.high
+    }
+
+    /// Every room, in room coordinates.
+    fn all(self) -> impl Iterator<Item = Position> {
+        (0..self.high).flat_map(move |y| (0..self.wide).map(move |x| Position { x, y }))
+    }
+
+    fn neighbours(self, room: Position) -> impl Iterator<Item = Position> {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .into_iter()
+        .map(move |direction| room.step(direction))
+        .filter(move |next| self.contains(*next))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MazeAlgorithm {
+    /// Digs as far as it can before backing up, leaving long winding
+    /// corridors.
+    RecursiveBacktracker,
+    /// Grows the maze from one room out, leaving lots of short dead ends.
+    Prim,
+    /// Joins random neighbouring rooms that aren't connected yet.
+    Kruskal,
+}
+
+impl MazeAlgorithm {
+    pub const ALL: [MazeAlgorithm; 3] = [
+        MazeAlgorithm::RecursiveBacktracker,
+        MazeAlgorithm::Prim,
+        MazeAlgorithm::Kruskal,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MazeAlgorithm::RecursiveBacktracker => "recursive backtracker",
+            MazeAlgorithm::Prim => "Prim's",
+            MazeAlgorithm::Kruskal => "Kruskal's",
+        }
+    }
+
+    /// The key that generates a maze this way, on the menu and in the
+    /// debugger.
+    pub fn key(self) -> KeyCode {
+        match self {
+            MazeAlgorithm::RecursiveBacktracker => KeyCode::B,
+            MazeAlgorithm::Prim => KeyCode::P,
+            MazeAlgorithm::Kruskal => KeyCode::K,
+        }
+    }
+
+    /// A new random maze as a level filling `grid`.
+    pub fn generate(self, grid: Grid, rng: &mut impl Rng) -> Level {
+        let rooms = Rooms::fitting(grid);
+        let passages = match self {
+            MazeAlgorithm::RecursiveBacktracker => backtracker(rooms, rng),
+            MazeAlgorithm::Prim => prim(rooms, rng),
+            MazeAlgorithm::Kruskal => kruskal(rooms, rng),
+        };
+        let mut floor: HashSet<Position> = rooms.all().map(cell).collect();
+        // Rooms sit on even cells, so the wall between two neighbours is at
+        // the sum of their room coordinates.
+        for (a, b) in passages {
+            floor.insert(Position {
+                x: a.x + b.x,
+                y: a.y + b.y,
+            });
+        }
+        let layout = (0..grid.height as i32)
+            .rev()
+            .map(|y| {
+                (0..grid.width as i32)
+                    .map(|x| {
+                        if floor.contains(&Position { x, y }) {
+                            '.'
+                        } else {
+                            '#'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut level = Level {
+            name: format!("Maze ({})", self.label()),
+            width: grid.width,
+            height: grid.height,
+            start: Position { x: 0, y: 0 },
+            start_direction: Direction::Right,
+            food: FoodRules { max_food: 1 },
+            spikes: SpikeTiming::default(),
+            layout,
+        };
+        // The snake needs a straight bit of corridor to start in: a cell
+        // for its tail, one for its head and one to move to.
+        let start = (0..grid.height as i32)
+            .flat_map(|y| (0..grid.width as i32).map(move |x| Position { x, y }))
+            .flat_map(|pos| [(pos, Direction::Right), (pos, Direction::Up)])
+            .find(|(pos, direction)| {
+                [*pos, pos.step(*direction), pos.step(direction.opposite())]
+                    .iter()
+                    .all(|cell| level.is_floor(*cell))
+            });
+        if let Some((start, direction)) = start {
+            level.start = start;
+            level.start_direction = direction;
+        }
+        level
+    }
+}
+
+/// The board cell a room sits on.
+fn cell(room: Position) -> Position {
+    Position {
+        x: room.x * 2,
+        y: room.y * 2,
+    }
+}
+
+fn backtracker(rooms: Rooms, rng: &mut impl Rng) -> Vec<(Position, Position)> {
+    let mut passages = Vec::new();
+    let first = Position { x: 0, y: 0 };
+    let mut visited = HashSet::from([first]);
+    let mut stack = vec![first];
+    while let Some(&room) = stack.last() {
+        let unvisited: Vec<Position> = rooms
+            .neighbours(room)
+            .filter(|next| !visited.contains(next))
+            .collect();
+        match unvisited.choose(rng) {
+            Some(&next) => {
+                visited.insert(next);
+                passages.push((room, next));
+                stack.push(next);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    passages
+}
+
+fn prim(rooms: Rooms, rng: &mut impl Rng) -> Vec<(Position, Position)> {
+    let mut passages = Vec::new();
+    let first = Position {
+        x: rng.gen_range(0..rooms.wide),
+        y: rng.gen_range(0..rooms.high),
+    };
+    let mut visited = HashSet::from([first]);
+    let mut frontier: Vec<(Position, Position)> =
+        rooms.neighbours(first).map(|next| (first, next)).collect();
+    while !frontier.is_empty() {
+        let (room, next) = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+        if !visited.insert(next) {
+            continue;
+        }
+        passages.push((room, next));
+        frontier.extend(
+            rooms
+                .neighbours(next)
+                .filter(|other| !visited.contains(other))
+                .map(|other| (next, other)),
+        );
+    }
+    passages
+}
+
+fn kruskal(rooms: Rooms, rng: &mut impl Rng) -> Vec<(Position, Position)> {
+    let index = |room: Position| (room.y * rooms.wide + room.x) as usize;
+    // Each room points towards the root of the set of rooms it's joined to.
+    let mut parent: Vec<usize> = (0..(rooms.wide * rooms.high) as usize).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut walls: Vec<(Position, Position)> = rooms
+        .all()
+        .flat_map(|room| {
+            [
+                Position {
+                    x: room.x + 1,
+                    y: room.y,
+                },
+                Position {
+                    x: room.x,
+                    y: room.y + 1,
+                },
+            ]
+            .into_iter()
+            .filter(move |next| rooms.contains(*next))
+            .map(move |next| (room, next))
+        })
+        .collect();
+    walls.shuffle(rng);
+    let mut passages = Vec::new();
+    for (a, b) in walls {
+        let (root_a, root_b) = (root(&mut parent, index(a)), root(&mut parent, index(b)));
+        if root_a != root_b {
+            parent[root_a] = root_b;
+            passages.push((a, b));
+        }
+    }
+    passages
+}
@@ -0,0 +1,579 @@
+//! The main menu the game starts on. Its main page leads to a run, a page to
+//! pick the mode, the settings with the options under them and one with the
+//! high scores. The options and the other screens' keys work from any page.
+//! Settings are saved as soon as they change.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::board::BoardConfig;
+use crate::highscore::HighScores;
+use crate::level::CurrentLevel;
+use crate::maze::MazeAlgorithm;
+use crate::net::NetSession;
+use crate::rng::GameRng;
+use crate::rules::{Difficulty, GameMode, GameRules};
+use crate::settings::Settings;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::theme::Themes;
+use crate::ui;
+
+const MENU_BACKGROUND: Color = Color::rgba(0.1, 0.1, 0.1, 0.9);
+const OBSTACLE_DENSITIES: [(f32, &str); 4] =
+    [(0.0, "Off"), (0.05, "Low"), (0.1, "Medium"), (0.2, "High")];
+const MAX_LIVES: u32 = 5;
+const MAX_ENEMIES: u32 = 3;
+const MAIN_ITEMS: [&str; 5] = ["Play", "Modes", "Settings", "High Scores", "Quit"];
+const SETTINGS_ITEMS: usize = 9;
+const GAME_SPEEDS: [f32; 5] = [0.5, 0.75, 1.0, 1.25, 1.5];
+const VOLUME_STEP: f32 = 0.1;
+const MAIN_HINT: &str = if cfg!(feature = "online-leaderboard") {
+    "Up/Down to pick, Enter to choose\n\
+     E edits the level, S for stats, I for controls, U for skins, L for the leaderboard\n\
+     V opens the pathfinding sandbox, B, P or K generates a maze\n\
+     R watches your last run, T your best in this mode"
+} else {
+    "Up/Down to pick, Enter to choose\n\
+     E edits the level, S for stats, I for controls, U for skins\n\
+     V opens the pathfinding sandbox, B, P or K generates a maze\n\
+     R watches your last run, T your best in this mode"
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MenuPage {
+    Main,
+    Modes,
+    Settings,
+    HighScores,
+}
+
+impl MenuPage {
+    fn hint(self) -> &'static str {
+        match self {
+            MenuPage::Main => MAIN_HINT,
+            MenuPage::Modes => "Up/Down to pick a mode, Enter to choose it, Esc goes back",
+            MenuPage::Settings => {
+                "Up/Down to pick, Left/Right to change, Esc goes back\n\
+                 Press a key to change an option"
+            }
+            MenuPage::HighScores => "Left/Right for another mode, Esc goes back",
+        }
+    }
+}
+
+/// The page of the menu showing and the item picked on it.
+pub struct MenuCursor {
+    page: MenuPage,
+    item: usize,
+    /// The mode whose high scores are listed.
+    scores_mode: GameMode,
+}
+
+impl Default for MenuCursor {
+    fn default() -> Self {
+        Self {
+            page: MenuPage::Main,
+            item: 0,
+            scores_mode: GameMode::Classic,
+        }
+    }
+}
+
+impl MenuCursor {
+    /// Whether Enter starts a run, for the modes that start theirs
+    /// differently.
+    pub fn on_play(&self) -> bool {
+        self.page == MenuPage::Main && self.item == 0
+    }
+
+    fn open(&mut self, page: MenuPage, item: usize) {
+        self.page = page;
+        self.item = item;
+    }
+
+    /// Back to the main page, on the item that led to `self.page`.
+    fn back(&mut self) {
+        let item = match self.page {
+            MenuPage::Main => 0,
+            MenuPage::Modes => 1,
+            MenuPage::Settings => 2,
+            MenuPage::HighScores => 3,
+        };
+        self.open(MenuPage::Main, item);
+    }
+}
+
+#[derive(Component)]
+struct MenuScreen;
+
+#[derive(Component)]
+struct PageText;
+
+#[derive(Component)]
+struct HintText;
+
+#[derive(Component)]
+struct OptionsText;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuCursor>()
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(spawn_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu)
+                    .with_system(menu_input)
+                    .with_system(settings_input.before(menu_input))
+                    .with_system(options_input)
+                    .with_system(page_text_update.after(menu_input))
+                    .with_system(options_text_update.after(options_input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(despawn_menu));
+    }
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut cursor: ResMut<MenuCursor>,
+) {
+    *cursor = MenuCursor::default();
+    commands
+        .spawn_bundle(NodeBundle {
+            color: UiColor(MENU_BACKGROUND),
+            ..ui::overlay_node()
+        })
+        .insert(MenuScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line("Snake", ui::text_style(&asset_server, 96.0)));
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 32.0)))
+                .insert(PageText);
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 24.0)))
+                .insert(HintText);
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 22.0)))
+                .insert(OptionsText);
+        });
+}
+
+pub fn menu_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut rules: ResMut<GameRules>,
+    high_scores: Res<HighScores>,
+    mut writer: EventWriter<GameStateEvent>,
+    mut exit_writer: EventWriter<AppExit>,
+    net: Option<Res<NetSession>>,
+) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::E]) {
+        writer.send(GameStateEvent::OpenEditor);
+        return;
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::V]) {
+        writer.send(GameStateEvent::OpenDebugger);
+        return;
+    }
+    let items = match cursor.page {
+        MenuPage::Main => MAIN_ITEMS.len(),
+        MenuPage::Modes => GameMode::ALL.len(),
+        MenuPage::Settings => SETTINGS_ITEMS,
+        MenuPage::HighScores => 0,
+    };
+    if items > 0 && take_just_pressed(&mut kbd_input, [KeyCode::Up]) {
+        cursor.item = (cursor.item + items - 1) % items;
+    } else if items > 0 && take_just_pressed(&mut kbd_input, [KeyCode::Down]) {
+        cursor.item = (cursor.item + 1) % items;
+    }
+    if cursor.page == MenuPage::HighScores {
+        let step = if take_just_pressed(&mut kbd_input, [KeyCode::Right]) {
+            1
+        } else if take_just_pressed(&mut kbd_input, [KeyCode::Left]) {
+            GameMode::ALL.len() - 1
+        } else {
+            0
+        };
+        if step > 0 {
+            cursor.scores_mode = scored_mode(&high_scores, cursor.scores_mode, step);
+        }
+    }
+    if cursor.page != MenuPage::Main && take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        cursor.back();
+        return;
+    }
+    if !take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+        return;
+    }
+    // An online game can't start before the other player has joined.
+    let waiting = net.is_some_and(|session| session.waiting_for_peer());
+    match (cursor.page, cursor.item) {
+        (MenuPage::Main, 0) if !waiting => writer.send(GameStateEvent::StartRun),
+        (MenuPage::Main, 1) => {
+            let item = GameMode::ALL.iter().position(|mode| *mode == rules.mode);
+            cursor.open(MenuPage::Modes, item.unwrap_or(0));
+        }
+        (MenuPage::Main, 2) => cursor.open(MenuPage::Settings, 0),
+        (MenuPage::Main, 3) => {
+            cursor.scores_mode = if high_scores.table(rules.mode).is_some() {
+                rules.mode
+            } else {
+                GameMode::Classic
+            };
+            cursor.open(MenuPage::HighScores, 0);
+        }
+        (MenuPage::Main, 4) => exit_writer.send(AppExit),
+        (MenuPage::Modes, item) => {
+            // Straight back to Play with the new mode.
+            rules.mode = GameMode::ALL[item];
+            cursor.open(MenuPage::Main, 0);
+        }
+        _ => {}
+    }
+}
+
+/// Changes the setting picked on the settings page: Left goes back a step,
+/// Right or Enter forward. Enter on Controls opens the controls screen.
+fn settings_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    cursor: Res<MenuCursor>,
+    themes: Res<Themes>,
+    mut settings: ResMut<Settings>,
+    mut rules: ResMut<GameRules>,
+    mut board: ResMut<BoardConfig>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if cursor.page != MenuPage::Settings {
+        return;
+    }
+    if cursor.item == SETTINGS_ITEMS - 1 {
+        if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+            writer.send(GameStateEvent::OpenBindings);
+        }
+        return;
+    }
+    let forward = if take_just_pressed(&mut kbd_input, [KeyCode::Right, KeyCode::Return]) {
+        true
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Left]) {
+        false
+    } else {
+        return;
+    };
+    match cursor.item {
+        0 => {
+            let index = GAME_SPEEDS
+                .iter()
+                .position(|speed| *speed == settings.game_speed)
+                .unwrap_or(2);
+            let index = if forward {
+                (index + 1).min(GAME_SPEEDS.len() - 1)
+            } else {
+                index.saturating_sub(1)
+            };
+            settings.game_speed = GAME_SPEEDS[index];
+            settings.save();
+        }
+        1 => cycle_board(&mut board, &mut current_level, &mut settings),
+        2 => toggle_wrap(&mut rules, &mut settings),
+        3 => {
+            let step = if forward { VOLUME_STEP } else { -VOLUME_STEP };
+            // Rounded to whole steps so they add up to exactly silent.
+            let volume = ((settings.volume + step) / VOLUME_STEP).round() * VOLUME_STEP;
+            settings.volume = volume.clamp(0.0, 1.0);
+            settings.save();
+        }
+        4 => {
+            settings.palette = settings.palette.next();
+            settings.save();
+        }
+        5 => {
+            settings.theme = themes.next(&settings.theme).name.clone();
+            settings.save();
+        }
+        6 => {
+            settings.high_contrast = !settings.high_contrast;
+            settings.save();
+        }
+        _ => {
+            settings.screen_shake = !settings.screen_shake;
+            settings.save();
+        }
+    }
+}
+
+/// Switches to the next board size and keeps it for next time. Any other
+/// level sizes the board for itself, see `board`.
+fn cycle_board(board: &mut BoardConfig, current_level: &mut CurrentLevel, settings: &mut Settings) {
+    board.cycle();
+    if !board.fits(&current_level.level) {
+        *current_level = CurrentLevel::custom(board.open_level());
+    }
+    settings.board = Some((board.width, board.height));
+    settings.save();
+}
+
+fn toggle_wrap(rules: &mut GameRules, settings: &mut Settings) {
+    rules.wrap_edges = !rules.wrap_edges;
+    settings.wrap_edges = Some(rules.wrap_edges);
+    settings.save();
+}
+
+/// The next mode `step` places round from `mode` that keeps high scores.
+fn scored_mode(high_scores: &HighScores, mode: GameMode, step: usize) -> GameMode {
+    let index = GameMode::ALL.iter().position(|m| *m == mode).unwrap_or(0);
+    (1..GameMode::ALL.len())
+        .map(|offset| GameMode::ALL[(index + offset * step) % GameMode::ALL.len()])
+        .find(|mode| high_scores.table(*mode).is_some())
+        .unwrap_or(mode)
+}
+
+fn page_text_update(
+    cursor: Res<MenuCursor>,
+    rules: Res<GameRules>,
+    settings: Res<Settings>,
+    themes: Res<Themes>,
+    board: Res<BoardConfig>,
+    high_scores: Res<HighScores>,
+    mut pages: Query<&mut Text, (With<PageText>, Without<HintText>)>,
+    mut hints: Query<&mut Text, (With<HintText>, Without<PageText>)>,
+) {
+    let list = |labels: &[&str]| {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(item, label)| {
+                let marker = if item == cursor.item { ">" } else { " " };
+                format!("{} {}", marker, label)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let page = match cursor.page {
+        MenuPage::Main => list(&MAIN_ITEMS),
+        MenuPage::Modes => {
+            let labels = GameMode::ALL.map(|mode| {
+                if mode == rules.mode {
+                    format!("{} (selected)", mode.label())
+                } else {
+                    mode.label().to_string()
+                }
+            });
+            list(&labels.iter().map(String::as_str).collect::<Vec<_>>())
+        }
+        MenuPage::Settings => {
+            let labels = [
+                format!("Speed: {}%", (settings.game_speed * 100.0).round()),
+                format!("Board size: {}", board.label()),
+                format!("Wrap edges: {}", on_off(rules.wrap_edges)),
+                format!("Volume: {}%", (settings.volume * 100.0).round()),
+                format!("Palette: {}", settings.palette.label()),
+                format!("Theme: {}", themes.get(&settings.theme).name),
+                format!("High contrast: {}", on_off(settings.high_contrast)),
+                format!("Screen shake: {}", on_off(settings.screen_shake)),
+                "Controls".to_string(),
+            ];
+            list(&labels.iter().map(String::as_str).collect::<Vec<_>>())
+        }
+        MenuPage::HighScores => {
+            let entries = high_scores
+                .table(cursor.scores_mode)
+                .map_or(&[][..], Vec::as_slice);
+            let rows = if entries.is_empty() {
+                "No runs yet".to_string()
+            } else {
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, entry)| {
+                        format!(
+                            "{}. {}   length {}   {}",
+                            rank + 1,
+                            entry.score,
+                            entry.length,
+                            entry.date
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            format!("{}\n\n{}", cursor.scores_mode.label(), rows)
+        }
+    };
+    for mut text in pages.iter_mut() {
+        text.sections[0].value = page.clone();
+    }
+    for mut text in hints.iter_mut() {
+        text.sections[0].value = cursor.page.hint().to_string();
+    }
+}
+
+fn options_input(
+    kbd_input: Res<Input<KeyCode>>,
+    mut rules: ResMut<GameRules>,
+    mut settings: ResMut<Settings>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut board: ResMut<BoardConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    if kbd_input.just_pressed(KeyCode::Key1) {
+        toggle_wrap(&mut rules, &mut settings);
+    }
+    if kbd_input.just_pressed(KeyCode::Key2) {
+        current_level.cycle();
+    }
+    if kbd_input.just_pressed(KeyCode::Z) {
+        cycle_board(&mut board, &mut current_level, &mut settings);
+    }
+    if let Some(algorithm) = MazeAlgorithm::ALL
+        .into_iter()
+        .find(|algorithm| kbd_input.just_pressed(algorithm.key()))
+    {
+        *current_level = CurrentLevel::custom(algorithm.generate(board.grid(), rng.board()));
+    }
+    if kbd_input.just_pressed(KeyCode::Key3) {
+        let index = OBSTACLE_DENSITIES
+            .iter()
+            .position(|(density, _)| *density == rules.obstacle_density)
+            .map_or(0, |i| (i + 1) % OBSTACLE_DENSITIES.len());
+        rules.obstacle_density = OBSTACLE_DENSITIES[index].0;
+    }
+    if kbd_input.just_pressed(KeyCode::Key4) {
+        rules.hunger = !rules.hunger;
+    }
+    if kbd_input.just_pressed(KeyCode::Key5) {
+        rules.lives = rules.lives % MAX_LIVES + 1;
+    }
+    if kbd_input.just_pressed(KeyCode::Key6) {
+        rules.mode = rules.mode.next();
+    }
+    if kbd_input.just_pressed(KeyCode::Key7) {
+        // Customised rules start over from the saved preset.
+        let difficulty = rules
+            .difficulty()
+            .map_or(settings.difficulty, Difficulty::next);
+        rules.apply_difficulty(difficulty);
+        settings.difficulty = difficulty;
+        settings.wrap_edges = None;
+        settings.save();
+    }
+    if kbd_input.just_pressed(KeyCode::Key8) {
+        rules.enemies = (rules.enemies + 1) % (MAX_ENEMIES + 1);
+    }
+    if kbd_input.just_pressed(KeyCode::Key9) {
+        rules.friendly_collisions = !rules.friendly_collisions;
+    }
+    if kbd_input.just_pressed(KeyCode::Key0) {
+        rules.terrain_slows = !rules.terrain_slows;
+    }
+    if kbd_input.just_pressed(KeyCode::F) {
+        rules.fog = !rules.fog;
+    }
+    if kbd_input.just_pressed(KeyCode::M) {
+        rules.mirror = rules.mirror.next();
+    }
+    if kbd_input.just_pressed(KeyCode::H) {
+        rules.invisible_tail = !rules.invisible_tail;
+    }
+    if kbd_input.just_pressed(KeyCode::C) {
+        rules.light_cycle = !rules.light_cycle;
+    }
+    if kbd_input.just_pressed(KeyCode::W) {
+        rules.world_events = !rules.world_events;
+    }
+    if kbd_input.just_pressed(KeyCode::G) {
+        settings.ghost = !settings.ghost;
+        settings.save();
+    }
+    if kbd_input.just_pressed(KeyCode::O) {
+        settings.mouse_steering = !settings.mouse_steering;
+        settings.save();
+    }
+    if kbd_input.just_pressed(KeyCode::A) {
+        settings.switch_access = settings.switch_access.next();
+        settings.save();
+    }
+}
+
+fn options_text_update(
+    cursor: Res<MenuCursor>,
+    rules: Res<GameRules>,
+    settings: Res<Settings>,
+    current_level: Res<CurrentLevel>,
+    board: Res<BoardConfig>,
+    net: Option<Res<NetSession>>,
+    mut texts: Query<&mut Text, With<OptionsText>>,
+) {
+    for mut text in texts.iter_mut() {
+        let waiting = if net
+            .as_ref()
+            .is_some_and(|session| session.waiting_for_peer())
+        {
+            "\n\nWaiting for player 2 to join"
+        } else {
+            ""
+        };
+        let note = match rules.mode {
+            GameMode::Daily => {
+                "\n\nToday's board is the same for everyone, the other options don't apply"
+            }
+            GameMode::Campaign => {
+                "\n\nEnter picks a campaign level, the level option doesn't apply"
+            }
+            _ => "",
+        };
+        if cursor.page != MenuPage::Settings {
+            text.sections[0].value = format!("Mode: {}{}{}", rules.mode.label(), note, waiting);
+            continue;
+        }
+        text.sections[0].value = format!(
+            "[1] Wrap edges: {}\n[2] Level: {}\n[Z] Board size: {}\n[3] Obstacles: {}\n[4] Hunger: {}\n[5] Lives: {}\n[6] Mode: {}\n[7] Difficulty: {}\n[8] Enemy snakes: {}\n[9] Co-op collisions: {}\n[0] Mud and water slow the snake: {}\n[F] Fog of war: {}\n[M] Mirrored controls: {}\n[H] Invisible tail: {}\n[C] Light cycle trails: {}\n[W] Random world events: {}\n[G] Race the ghost of your best run: {}\n[O] Steer with the mouse: {}\n[A] Switch access: {}{}{}",
+            on_off(rules.wrap_edges),
+            current_level.level.name,
+            board.label(),
+            obstacles_label(rules.obstacle_density),
+            on_off(rules.hunger),
+            rules.lives,
+            rules.mode.label(),
+            rules.difficulty().map_or("Custom", Difficulty::label),
+            rules.enemies,
+            if rules.friendly_collisions {
+                "Deadly"
+            } else {
+                "Pass through"
+            },
+            on_off(rules.terrain_slows),
+            on_off(rules.fog),
+            rules.mirror.label(),
+            on_off(rules.invisible_tail),
+            on_off(rules.light_cycle),
+            on_off(rules.world_events),
+            on_off(settings.ghost),
+            on_off(settings.mouse_steering),
+            settings.switch_access.label(),
+            note,
+            waiting,
+        );
+    }
+}
+
+fn obstacles_label(density: f32) -> &'static str {
+    OBSTACLE_DENSITIES
+        .iter()
+        .find(|(d, _)| *d == density)
+        .map_or("Custom", |(_, label)| label)
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn despawn_menu(mut commands: Commands, screens: Query<Entity, With<MenuScreen>>) {
+    for entt in screens.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
@@ -0,0 +1,88 @@
+//! Mouse steering, turned on from the menu (O). Every movement tick player
+//! one's snake turns toward the cell under the cursor, along whichever axis
+//! it's further away on. The keys still work while the cursor is outside the
+//! window.
+
+use bevy::prelude::*;
+
+use crate::autopilot::Autopilot;
+use crate::input_map::Controls;
+use crate::replay::Playback;
+use crate::rules::GameRules;
+use crate::settings::Settings;
+use crate::terrain::Terrain;
+use crate::tick::MovementTick;
+use crate::{cursor_to_grid, on_ice, snake_movement, Direction, Grid, Player, Position, SnakeHead};
+
+pub struct MouseSteeringPlugin;
+
+impl Plugin for MouseSteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(MovementTick)
+                .with_system(mouse_steering.before(snake_movement)),
+        );
+    }
+}
+
+/// The way from `from` toward `target` for a snake heading `heading`. With
+/// the target straight behind, it turns aside to come round to it.
+fn toward(from: Position, target: Position, heading: Direction) -> Option<Direction> {
+    let (dx, dy) = (target.x - from.x, target.y - from.y);
+    let horizontal = match dx {
+        0 => None,
+        dx if dx > 0 => Some(Direction::Right),
+        _ => Some(Direction::Left),
+    };
+    let vertical = match dy {
+        0 => None,
+        dy if dy > 0 => Some(Direction::Up),
+        _ => Some(Direction::Down),
+    };
+    let axes = if dx.abs() >= dy.abs() {
+        [horizontal, vertical]
+    } else {
+        [vertical, horizontal]
+    };
+    let mut turns = axes.into_iter().flatten();
+    match turns.next()? {
+        direction if direction == heading.opposite() => {
+            Some(turns.next().unwrap_or(match heading {
+                Direction::Up | Direction::Down => Direction::Right,
+                Direction::Left | Direction::Right => Direction::Up,
+            }))
+        }
+        direction => Some(direction),
+    }
+}
+
+fn mouse_steering(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    settings: Res<Settings>,
+    rules: Res<GameRules>,
+    playback: Res<Playback>,
+    mut heads: Query<(&Player, &mut SnakeHead, &Position), (With<Controls>, Without<Autopilot>)>,
+    terrain: Query<(&Position, &Terrain)>,
+) {
+    if !settings.mouse_steering || playback.is_active() {
+        return;
+    }
+    let target = match windows
+        .get_primary()
+        .and_then(|window| cursor_to_grid(window, *grid))
+    {
+        Some(target) => target,
+        None => return,
+    };
+    for (player, mut head, pos) in heads.iter_mut() {
+        if player.0 != 1 || on_ice(&terrain, *pos) {
+            continue;
+        }
+        if let Some(direction) = toward(*pos, target, head.direction) {
+            head.input_queue.clear();
+            head.queue_turn(rules.mirror.apply(direction));
+        }
+    }
+}
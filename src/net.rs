@@ -0,0 +1,414 @@
+//! Online versus over UDP. The host runs the game as usual with player two
+//! steered from the network, and sends the whole board to the client after
+//! every tick. The client only draws what it receives and sends its turns
+//! back.
+//!
+//! Start a host with `--host <port>` and join it with `--join <address>`.
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::CliArgs;
+use crate::food::{spawn_food, Food, FoodKind};
+use crate::input_map::InputMap;
+use crate::rules::{GameMode, GameRules};
+use crate::state::{GameState, GameStateEvent};
+use crate::tick::MovementTick;
+use crate::ui;
+use crate::versus::VersusOutcome;
+use crate::walls::{spawn_wall, Wall};
+use crate::{
+    snake_growth, snake_movement, spawn_snake_segment, Controls, Direction, Player, Position, Size,
+    SnakeHead, SnakeSegment, SnakeSegments,
+};
+
+/// Large enough for a snapshot of a full board.
+const MAX_PACKET_SIZE: usize = 65_507;
+/// Seconds between join requests while the client waits for the host.
+const JOIN_RETRY_INTERVAL: f64 = 1.0;
+/// Weight of the latest round trip measurement in the running average.
+const RTT_SMOOTHING: f64 = 0.1;
+
+#[derive(Serialize, Deserialize)]
+enum ClientMessage {
+    /// Sent until the first snapshot arrives.
+    Join,
+    Turn(Direction),
+    /// Echoes `Snapshot::sent_at` so the host can measure the round trip.
+    Ack(f64),
+}
+
+#[derive(Serialize, Deserialize)]
+struct RemoteSnake {
+    color: Color,
+    /// Head first.
+    body: Vec<Position>,
+}
+
+/// Everything the client draws, sent by the host after every tick.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    /// Host clock when the snapshot was sent.
+    sent_at: f64,
+    snakes: Vec<RemoteSnake>,
+    food: Vec<(Position, FoodKind)>,
+    walls: Vec<Position>,
+    over: bool,
+    winner: Option<u8>,
+}
+
+enum NetRole {
+    Host { peer: Option<SocketAddr> },
+    Client { host: SocketAddr, joined: bool },
+}
+
+pub struct NetSession {
+    socket: UdpSocket,
+    role: NetRole,
+    /// Smoothed round trip time to the other side in seconds.
+    rtt: f64,
+}
+
+impl NetSession {
+    fn open(cli: &CliArgs) -> io::Result<Option<Self>> {
+        let (socket, role) = if let Some(port) = cli.host {
+            (
+                UdpSocket::bind(("0.0.0.0", port))?,
+                NetRole::Host { peer: None },
+            )
+        } else if let Some(address) = &cli.join {
+            let host = address.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "host address didn't resolve")
+            })?;
+            (
+                UdpSocket::bind(("0.0.0.0", 0))?,
+                NetRole::Client {
+                    host,
+                    joined: false,
+                },
+            )
+        } else {
+            return Ok(None);
+        };
+        socket.set_nonblocking(true)?;
+        Ok(Some(Self {
+            socket,
+            role,
+            rtt: 0.0,
+        }))
+    }
+
+    /// Whether this is a host that nobody has joined yet.
+    pub fn waiting_for_peer(&self) -> bool {
+        matches!(self.role, NetRole::Host { peer: None })
+    }
+
+    fn send<T: Serialize>(&self, message: &T, to: SocketAddr) {
+        let result = ron::to_string(message)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            .and_then(|encoded| self.socket.send_to(encoded.as_bytes(), to));
+        if let Err(err) = result {
+            warn!("Failed to send to {}: {}", to, err);
+        }
+    }
+
+    /// Every message waiting on the socket along with its sender.
+    fn receive<T: for<'de> Deserialize<'de>>(&self) -> Vec<(T, SocketAddr)> {
+        let mut messages = Vec::new();
+        let mut buf = vec![0; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => match std::str::from_utf8(&buf[..len])
+                    .ok()
+                    .and_then(|text| ron::from_str(text).ok())
+                {
+                    Some(message) => messages.push((message, from)),
+                    None => warn!("Ignoring malformed packet from {}", from),
+                },
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    // A previous packet bouncing off a closed port shows up
+                    // here on some platforms, later packets are still fine.
+                    debug!("Receive failed: {}", err);
+                    break;
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// Marks the host's snake steered by turns arriving from the client.
+#[derive(Component)]
+struct RemotePlayer;
+
+/// The host's own controls, held back by the one way latency so both players'
+/// turns take effect equally late.
+#[derive(Component)]
+struct DelayedControls(Controls);
+
+/// Host turns waiting out the delay, with the time they take effect.
+#[derive(Default)]
+struct PendingTurns(VecDeque<(f64, Direction)>);
+
+#[derive(Component)]
+struct RemoteStatusText;
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        let session = match app.world.get_resource::<CliArgs>().map(NetSession::open) {
+            Some(Ok(Some(session))) => session,
+            Some(Err(err)) => {
+                error!("Failed to start networking: {}", err);
+                return;
+            }
+            _ => return,
+        };
+        match session.role {
+            NetRole::Host { .. } => {
+                // Online play is always versus.
+                if let Some(mut rules) = app.world.get_resource_mut::<GameRules>() {
+                    rules.mode = GameMode::Versus;
+                }
+                app.init_resource::<PendingTurns>()
+                    .add_system(host_receive)
+                    .add_system_set(
+                        SystemSet::on_enter(GameState::Playing).with_system(reset_pending_turns),
+                    )
+                    .add_system_set(
+                        SystemSet::on_update(GameState::Playing)
+                            .with_system(claim_snakes)
+                            .with_system(delayed_input.before(snake_movement)),
+                    )
+                    .add_system_set(
+                        SystemSet::new()
+                            .with_run_criteria(MovementTick)
+                            .with_system(send_snapshot.after(snake_growth)),
+                    )
+                    .add_system_set(
+                        SystemSet::on_enter(GameState::GameOver).with_system(send_snapshot),
+                    );
+            }
+            NetRole::Client { .. } => {
+                app.add_system_set(SystemSet::on_enter(GameState::Menu).with_system(join_remote))
+                    .add_system_set(
+                        SystemSet::on_enter(GameState::Remote).with_system(spawn_status_text),
+                    )
+                    .add_system_set(
+                        SystemSet::on_update(GameState::Remote)
+                            .with_system(request_join)
+                            .with_system(client_input)
+                            .with_system(client_receive),
+                    );
+            }
+        }
+        app.insert_resource(session);
+    }
+}
+
+fn host_receive(
+    time: Res<Time>,
+    rules: Res<GameRules>,
+    mut session: ResMut<NetSession>,
+    mut heads: Query<&mut SnakeHead, With<RemotePlayer>>,
+) {
+    for (message, from) in session.receive::<ClientMessage>() {
+        if let NetRole::Host { peer } = &mut session.role {
+            match *peer {
+                None => {
+                    info!("Player 2 joined from {}", from);
+                    *peer = Some(from);
+                }
+                // Only one client can play at a time.
+                Some(addr) if addr != from => continue,
+                Some(_) => {}
+            }
+        }
+        match message {
+            ClientMessage::Join => {}
+            ClientMessage::Turn(direction) => {
+                for mut head in heads.iter_mut() {
+                    head.queue_turn(rules.mirror.apply(direction));
+                }
+            }
+            ClientMessage::Ack(sent_at) => {
+                let rtt = time.seconds_since_startup() - sent_at;
+                session.rtt += (rtt - session.rtt) * RTT_SMOOTHING;
+            }
+        }
+    }
+}
+
+/// Hands player two over to the network and delays the host's own keys.
+fn claim_snakes(
+    mut commands: Commands,
+    players: Query<(Entity, &Player, &Controls), Without<DelayedControls>>,
+) {
+    for (snake, player, controls) in players.iter() {
+        let mut snake = commands.entity(snake);
+        snake.remove::<Controls>();
+        if player.0 == 1 {
+            snake.insert(DelayedControls(controls.clone()));
+        } else {
+            snake.insert(RemotePlayer);
+        }
+    }
+}
+
+fn reset_pending_turns(mut pending: ResMut<PendingTurns>) {
+    pending.0.clear();
+}
+
+fn delayed_input(
+    time: Res<Time>,
+    kbd_input: Res<Input<KeyCode>>,
+    rules: Res<GameRules>,
+    session: Res<NetSession>,
+    mut pending: ResMut<PendingTurns>,
+    mut heads: Query<(&mut SnakeHead, &DelayedControls)>,
+) {
+    let now = time.seconds_since_startup();
+    for (mut head, controls) in heads.iter_mut() {
+        for key in kbd_input.get_just_pressed() {
+            if let Some(direction) = controls.0.direction(*key) {
+                pending
+                    .0
+                    .push_back((now + session.rtt / 2.0, rules.mirror.apply(direction)));
+            }
+        }
+        while let Some((_, direction)) = pending.0.front().filter(|(due, _)| *due <= now) {
+            head.queue_turn(*direction);
+            pending.0.pop_front();
+        }
+    }
+}
+
+fn send_snapshot(
+    time: Res<Time>,
+    session: Res<NetSession>,
+    state: Res<State<GameState>>,
+    outcome: Res<VersusOutcome>,
+    snakes: Query<(&SnakeHead, &SnakeSegments)>,
+    positions: Query<&Position>,
+    food: Query<(&Position, &Food)>,
+    walls: Query<&Position, With<Wall>>,
+) {
+    let peer = match session.role {
+        NetRole::Host { peer: Some(peer) } => peer,
+        _ => return,
+    };
+    let snapshot = Snapshot {
+        sent_at: time.seconds_since_startup(),
+        snakes: snakes
+            .iter()
+            .map(|(head, segments)| RemoteSnake {
+                color: head.color,
+                body: segments
+                    .iter()
+                    .filter_map(|e| positions.get(*e).ok())
+                    .copied()
+                    .collect(),
+            })
+            .collect(),
+        food: food.iter().map(|(pos, food)| (*pos, food.kind)).collect(),
+        walls: walls.iter().copied().collect(),
+        over: *state.current() == GameState::GameOver,
+        winner: outcome.winner,
+    };
+    session.send(&snapshot, peer);
+}
+
+fn join_remote(mut writer: EventWriter<GameStateEvent>) {
+    writer.send(GameStateEvent::JoinRemote);
+}
+
+fn spawn_status_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(ui::text_line(
+            "Waiting for the host",
+            ui::text_style(&asset_server, 28.0),
+        ))
+        .insert(RemoteStatusText);
+}
+
+fn request_join(time: Res<Time>, session: Res<NetSession>, mut last_request: Local<f64>) {
+    let now = time.seconds_since_startup();
+    if let NetRole::Client {
+        host,
+        joined: false,
+    } = session.role
+    {
+        if now - *last_request >= JOIN_RETRY_INTERVAL {
+            session.send(&ClientMessage::Join, host);
+            *last_request = now;
+        }
+    }
+}
+
+/// The client plays alone on its own keyboard, so it steers with the single
+/// player keys.
+fn client_input(
+    kbd_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    session: Res<NetSession>,
+) {
+    if let NetRole::Client { host, .. } = session.role {
+        for key in kbd_input.get_just_pressed() {
+            if let Some(direction) = input_map.single_player.direction(*key) {
+                session.send(&ClientMessage::Turn(direction), host);
+            }
+        }
+    }
+}
+
+/// Redraws the board from the newest snapshot.
+fn client_receive(
+    mut commands: Commands,
+    mut session: ResMut<NetSession>,
+    board: Query<Entity, Or<(With<SnakeSegment>, With<Food>, With<Wall>)>>,
+    mut status: Query<&mut Text, With<RemoteStatusText>>,
+) {
+    let snapshot = match session.receive::<Snapshot>().pop() {
+        Some((snapshot, _)) => snapshot,
+        None => return,
+    };
+    if let NetRole::Client { host, joined } = &mut session.role {
+        *joined = true;
+        let host = *host;
+        session.send(&ClientMessage::Ack(snapshot.sent_at), host);
+    }
+
+    for entt in board.iter() {
+        commands.entity(entt).despawn();
+    }
+    for position in snapshot.walls {
+        spawn_wall(&mut commands, position);
+    }
+    for (position, kind) in snapshot.food {
+        spawn_food(&mut commands, position, kind);
+    }
+    for snake in snapshot.snakes {
+        for (i, position) in snake.body.into_iter().enumerate() {
+            let segment = spawn_snake_segment(&mut commands, position, snake.color);
+            if i == 0 {
+                commands.entity(segment).insert(Size::square(0.8));
+            }
+        }
+    }
+
+    let status_line = match (snapshot.over, snapshot.winner) {
+        (false, _) => "You are player 2, steer with the arrow keys or WASD".to_string(),
+        (true, Some(2)) => "You win!".to_string(),
+        (true, Some(_)) => "You lose".to_string(),
+        (true, None) => "Draw!".to_string(),
+    };
+    for mut text in status.iter_mut() {
+        text.sections[0].value = status_line.clone();
+    }
+}
@@ -0,0 +1,209 @@
+//! Objectives: one to three goals rolled at the start of each run and shown
+//! on the HUD, each paying a score bonus when it's met. They are rolled from
+//! the run's seed, so replays and the daily challenge get the same ones.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::rng::{seed_run, GameRng};
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::state::GameState;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::{
+    player_length, snake_growth, FoodEatenEvent, GameOverEvent, Grid, Player, Position,
+    SnakeSegments,
+};
+
+const MAX_OBJECTIVES: usize = 3;
+
+#[derive(Clone, Copy)]
+enum Goal {
+    /// Eat `food` pieces in a row without the head touching the edge of the
+    /// board.
+    AwayFromEdges { food: u32 },
+    /// Grow to `length` segments within `seconds` of the start of the run.
+    ReachLength { length: usize, seconds: u32 },
+    /// Earn `points` without dying in between.
+    ScoreInOneLife { points: u32 },
+}
+
+impl Goal {
+    fn random(kind: usize, rng: &mut impl Rng) -> Self {
+        match kind {
+            0 => Goal::AwayFromEdges {
+                food: rng.gen_range(3..=6),
+            },
+            1 => {
+                let length = [10, 15, 20][rng.gen_range(0..3)];
+                Goal::ReachLength {
+                    length,
+                    seconds: length as u32 * 3,
+                }
+            }
+            _ => Goal::ScoreInOneLife {
+                points: rng.gen_range(2..=5) * 10,
+            },
+        }
+    }
+
+    fn bonus(self) -> u32 {
+        match self {
+            Goal::AwayFromEdges { food } => food * 5,
+            Goal::ReachLength { length, .. } => length as u32 * 2,
+            Goal::ScoreInOneLife { points } => points / 2,
+        }
+    }
+}
+
+pub struct Objective {
+    goal: Goal,
+    /// Food eaten in a row, or points since the last death, toward the goal.
+    progress: u32,
+    done: bool,
+    /// Out of time, for goals that have a limit.
+    failed: bool,
+}
+
+impl Objective {
+    /// A line for the HUD, marked once met or missed.
+    pub fn describe(&self) -> String {
+        let mark = if self.done {
+            "[x]"
+        } else if self.failed {
+            "[-]"
+        } else {
+            "[ ]"
+        };
+        let text = match self.goal {
+            Goal::AwayFromEdges { food } => format!(
+                "Eat {} food in a row away from the edges ({}/{})",
+                food, self.progress, food
+            ),
+            Goal::ReachLength { length, seconds } => {
+                format!("Reach length {} in {}s", length, seconds)
+            }
+            Goal::ScoreInOneLife { points } => format!(
+                "Score {} without dying ({}/{})",
+                points,
+                self.progress.min(points),
+                points
+            ),
+        };
+        format!("{} {}  +{}", mark, text, self.goal.bonus())
+    }
+}
+
+/// This run's objectives.
+#[derive(Default)]
+pub struct Objectives {
+    pub list: Vec<Objective>,
+    /// Game time since the run started.
+    elapsed: Duration,
+    /// The score at the last death, for `Goal::ScoreInOneLife`.
+    score_at_death: u32,
+}
+
+pub struct ObjectivesPlugin;
+
+impl Plugin for ObjectivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Objectives>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(roll_objectives.after(seed_run)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(track_objectives.after(snake_growth)),
+            );
+    }
+}
+
+/// Two player runs and casual runs, which are about something else, don't
+/// get any.
+fn roll_objectives(
+    mut objectives: ResMut<Objectives>,
+    mut rng: ResMut<GameRng>,
+    rules: Res<GameRules>,
+) {
+    *objectives = Objectives::default();
+    if rules.mode.two_player() || rules.mode == GameMode::Casual {
+        return;
+    }
+    let rng = rng.objectives();
+    let count = rng.gen_range(1..=MAX_OBJECTIVES);
+    // Each kind of goal at most once.
+    let mut kinds: Vec<usize> = (0..MAX_OBJECTIVES).collect();
+    for _ in 0..count {
+        let kind = kinds.remove(rng.gen_range(0..kinds.len()));
+        objectives.list.push(Objective {
+            goal: Goal::random(kind, rng),
+            progress: 0,
+            done: false,
+            failed: false,
+        });
+    }
+}
+
+fn track_objectives(
+    mut objectives: ResMut<Objectives>,
+    mut score: ResMut<Score>,
+    move_timer: Res<MoveTimer>,
+    grid: Res<Grid>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+    mut game_over_reader: EventReader<GameOverEvent>,
+    heads: Query<&Position, With<Player>>,
+    players: Query<(&Player, &SnakeSegments)>,
+) {
+    if objectives.list.is_empty() {
+        return;
+    }
+    objectives.elapsed += move_timer.step();
+    if game_over_reader.iter().next().is_some() {
+        objectives.score_at_death = score.0;
+    }
+    let ate = eaten_reader.iter().count() as u32;
+    let on_edge = heads.iter().any(|pos| {
+        pos.x <= 0
+            || pos.y <= 0
+            || pos.x >= grid.width as i32 - 1
+            || pos.y >= grid.height as i32 - 1
+    });
+    let length = player_length(&players);
+    let seconds = objectives.elapsed.as_secs_f32();
+    let scored = score.0.saturating_sub(objectives.score_at_death);
+
+    let mut bonus = 0;
+    for objective in objectives.list.iter_mut() {
+        if objective.done || objective.failed {
+            continue;
+        }
+        match objective.goal {
+            Goal::AwayFromEdges { food } => {
+                objective.progress = if on_edge { 0 } else { objective.progress + ate };
+                objective.done = objective.progress >= food;
+            }
+            Goal::ReachLength {
+                length: target,
+                seconds: limit,
+            } => {
+                objective.done = length >= target;
+                objective.failed = !objective.done && seconds > limit as f32;
+            }
+            Goal::ScoreInOneLife { points } => {
+                objective.progress = scored;
+                objective.done = scored >= points;
+            }
+        }
+        if objective.done {
+            bonus += objective.goal.bonus();
+        }
+    }
+    if bonus > 0 {
+        score.0 += bonus;
+    }
+}
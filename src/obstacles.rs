@@ -0,0 +1,78 @@
+//! Random obstacle layouts that never cut the board into separate regions.
+
+use std::collections::{HashSet, VecDeque};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::level::Level;
+use crate::{Direction, Position};
+
+/// Cells ahead of the spawn point kept clear so a run never starts facing a
+/// wall.
+const SPAWN_RUNWAY: i32 = 4;
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Right,
+    Direction::Down,
+];
+
+/// Picks extra wall positions for `level` covering roughly `density` of its
+/// free cells, drawn from `rng`. Every free cell stays reachable from every
+/// other one.
+pub fn generate(level: &Level, density: f32, rng: &mut impl Rng) -> Vec<Position> {
+    let mut blocked: HashSet<Position> = level.walls().collect();
+    let mut reserved = HashSet::new();
+    // Both snakes in two player modes get a runway.
+    for (start, direction) in [(level.start, level.start_direction), level.second_start()] {
+        let mut pos = start.step(direction.opposite());
+        for _ in 0..SPAWN_RUNWAY + 2 {
+            reserved.insert(pos);
+            pos = pos.step(direction);
+        }
+    }
+    // Snakes can leave a portal in any direction.
+    for (_, ends) in level.portals() {
+        for direction in DIRECTIONS {
+            reserved.extend(ends.map(|end| end.step(direction)));
+        }
+    }
+
+    let mut candidates: Vec<Position> = (0..level.width as i32)
+        .flat_map(|x| (0..level.height as i32).map(move |y| Position { x, y }))
+        .filter(|pos| level.is_floor(*pos) && !reserved.contains(pos))
+        .collect();
+    let target = (candidates.len() as f32 * density.clamp(0.0, 1.0)) as usize;
+    candidates.shuffle(rng);
+
+    let mut obstacles = Vec::with_capacity(target);
+    for candidate in candidates {
+        if obstacles.len() >= target {
+            break;
+        }
+        blocked.insert(candidate);
+        if is_connected(level, &blocked) {
+            obstacles.push(candidate);
+        } else {
+            blocked.remove(&candidate);
+        }
+    }
+    obstacles
+}
+
+/// Whether all cells not in `blocked` form a single region.
+fn is_connected(level: &Level, blocked: &HashSet<Position>) -> bool {
+    let free = (level.width * level.height) as usize - blocked.len();
+    let mut seen = HashSet::from([level.start]);
+    let mut queue = VecDeque::from([level.start]);
+    while let Some(pos) = queue.pop_front() {
+        for direction in DIRECTIONS {
+            let next = pos.step(direction);
+            if level.contains(next) && !blocked.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.len() == free
+}
@@ -0,0 +1,75 @@
+//! Speed pads. A snake whose head moves onto one is pushed a further cell in
+//! the pad's direction on the same tick.
+
+use bevy::prelude::*;
+
+use crate::level::Level;
+use crate::{Direction, Size};
+
+const PAD_COLOR: Color = Color::rgb(0.25, 0.55, 0.3);
+const ARROW_COLOR: Color = Color::rgb(0.6, 1.0, 0.5);
+/// Pads are floor, under the snake and anything else on them.
+const PAD_Z: f32 = -0.05;
+
+#[derive(Component)]
+pub struct SpeedPad(pub Direction);
+
+/// The pad a level layout character stands for, if any.
+pub fn pad_direction(tile: char) -> Option<Direction> {
+    match tile {
+        '↑' => Some(Direction::Up),
+        '←' => Some(Direction::Left),
+        '→' => Some(Direction::Right),
+        '↓' => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+pub fn pad_tile(direction: Direction) -> char {
+    match direction {
+        Direction::Up => '↑',
+        Direction::Left => '←',
+        Direction::Right => '→',
+        Direction::Down => '↓',
+    }
+}
+
+/// Spawns every speed pad in `level`, each with a marker on the side it
+/// pushes towards.
+pub fn spawn_pads(commands: &mut Commands, level: &Level) {
+    for (position, direction) in level.pads() {
+        let offset = match direction {
+            Direction::Up => Vec2::new(0.0, 0.3),
+            Direction::Left => Vec2::new(-0.3, 0.0),
+            Direction::Right => Vec2::new(0.3, 0.0),
+            Direction::Down => Vec2::new(0.0, -0.3),
+        };
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: PAD_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, PAD_Z),
+                ..default()
+            })
+            .insert(SpeedPad(direction))
+            .insert(position)
+            .insert(Size::square(0.9))
+            .with_children(|parent| {
+                // Placed relative to the pad, so it scales along with it.
+                parent.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: ARROW_COLOR,
+                        ..default()
+                    },
+                    transform: Transform {
+                        translation: offset.extend(0.01),
+                        scale: Vec3::new(0.3, 0.3, 1.0),
+                        ..default()
+                    },
+                    ..default()
+                });
+            });
+    }
+}
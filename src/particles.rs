@@ -0,0 +1,105 @@
+//! Small particle bursts: a ring of colored quads that fly apart, growing
+//! and fading as they go. Eating food sets one off where it was eaten.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{cell_center, snake_growth, Grid, GrowthEvent, Position, SnakeHead};
+
+const BURST_PARTICLES: usize = 12;
+/// Seconds a particle lasts.
+const PARTICLE_LIFETIME: f32 = 0.4;
+/// Cells a second, with every other particle half as fast again to give the
+/// burst some depth.
+const PARTICLE_SPEED: f32 = 3.0;
+/// Side of a particle in cells, when it's set off and when it's gone.
+const START_SIZE: f32 = 0.2;
+const END_SIZE: f32 = 0.4;
+/// Over the board, under the fog.
+const PARTICLE_Z: f32 = 0.8;
+
+#[derive(Component)]
+struct Particle {
+    /// Pixels a second.
+    velocity: Vec2,
+    /// Side of a cell in pixels when it was set off.
+    cell_size: f32,
+    lifetime: Timer,
+}
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(eat_burst.after(snake_growth))
+            .add_system(update_particles);
+    }
+}
+
+/// Sets off a burst of `color` particles from `center`, sized for cells
+/// `cell_size` pixels across.
+pub fn spawn_burst(commands: &mut Commands, center: Vec2, cell_size: f32, color: Color) {
+    for i in 0..BURST_PARTICLES {
+        let angle = i as f32 / BURST_PARTICLES as f32 * TAU;
+        let speed = if i % 2 == 0 {
+            PARTICLE_SPEED
+        } else {
+            PARTICLE_SPEED * 1.5
+        };
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                transform: Transform {
+                    translation: center.extend(PARTICLE_Z),
+                    scale: Vec3::new(START_SIZE * cell_size, START_SIZE * cell_size, 1.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Particle {
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed * cell_size,
+                cell_size,
+                lifetime: Timer::from_seconds(PARTICLE_LIFETIME, false),
+            });
+    }
+}
+
+fn eat_burst(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut growth_reader: EventReader<GrowthEvent>,
+    heads: Query<(&SnakeHead, &Position)>,
+) {
+    let window = windows.get_primary().unwrap();
+    // Light cycle trails grow the snakes every tick without anything eaten.
+    for growth in growth_reader.iter().filter(|growth| growth.value > 0) {
+        if let Ok((head, pos)) = heads.get(growth.snake) {
+            spawn_burst(
+                &mut commands,
+                cell_center(window, *grid, *pos),
+                grid.cell_size(window),
+                head.color,
+            );
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entt, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entt).despawn();
+            continue;
+        }
+        let age = particle.lifetime.percent();
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        let side = (START_SIZE + (END_SIZE - START_SIZE) * age) * particle.cell_size;
+        transform.scale = Vec3::new(side, side, 1.0);
+        sprite.color.set_a(1.0 - age);
+    }
+}
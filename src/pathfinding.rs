@@ -0,0 +1,403 @@
+//! Grid searches used by the autopilot. All of them share one best-first
+//! loop and only differ in how they order the cells waiting to be explored.
+//!
+//! A* and greedy best-first order cells by an estimate of the distance left,
+//! with a choice of heuristic and a weight on it, see `SearchOptions`.
+//!
+//! Stepping onto mud or water costs more than plain floor. Dijkstra and A*
+//! look for the cheapest path, BFS only counts steps.
+//!
+//! Snake bodies move out of the way as the search goes: a body cell is only
+//! in the way until the tail has passed it. Whether a cell is open then
+//! depends on when the snake gets there, so the search runs over cells at a
+//! given step rather than just cells. Once every body cell is free the step
+//! stops mattering and later steps are all treated as one.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::autopilot::DIRECTIONS;
+use crate::{Direction, Grid, Position};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    /// Explores in rings of equal distance from the start.
+    Bfs,
+    /// Explores the cheapest cell first. On plain floor every step costs the
+    /// same, so it finds the same paths as BFS and differs only in
+    /// tie-breaking.
+    Dijkstra,
+    /// Cheapest cell first, counting the estimated distance still to go.
+    AStar,
+    /// Whichever cell looks closest to the food, ignoring the way there.
+    /// Quick, but the paths aren't always the shortest.
+    GreedyBestFirst,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::Bfs,
+        Algorithm::Dijkstra,
+        Algorithm::AStar,
+        Algorithm::GreedyBestFirst,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Algorithm::Bfs => "BFS",
+            Algorithm::Dijkstra => "Dijkstra",
+            Algorithm::AStar => "A*",
+            Algorithm::GreedyBestFirst => "Greedy",
+        }
+    }
+
+    /// Whether the algorithm looks at the distance left at all.
+    pub fn uses_heuristic(self) -> bool {
+        matches!(self, Algorithm::AStar | Algorithm::GreedyBestFirst)
+    }
+
+    pub fn next(self) -> Self {
+        let i = Algorithm::ALL.iter().position(|a| *a == self).unwrap();
+        Algorithm::ALL[(i + 1) % Algorithm::ALL.len()]
+    }
+
+    /// Order in which a cell `cost` from the start and an estimated
+    /// `remaining` from the food gets explored, lowest first.
+    fn priority(self, cost: i32, remaining: f32) -> f32 {
+        match self {
+            Algorithm::Bfs | Algorithm::Dijkstra => cost as f32,
+            Algorithm::AStar => cost as f32 + remaining,
+            Algorithm::GreedyBestFirst => remaining,
+        }
+    }
+}
+
+/// Estimates of the distance from a cell to the food.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Heuristic {
+    /// Steps along the grid. Exact on an empty board since the snake can't
+    /// move diagonally.
+    Manhattan,
+    /// Straight line distance.
+    Euclidean,
+    /// Steps if diagonal moves were allowed.
+    Chebyshev,
+}
+
+impl Heuristic {
+    pub fn label(self) -> &'static str {
+        match self {
+            Heuristic::Manhattan => "Manhattan",
+            Heuristic::Euclidean => "Euclidean",
+            Heuristic::Chebyshev => "Chebyshev",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Heuristic::Manhattan => Heuristic::Euclidean,
+            Heuristic::Euclidean => Heuristic::Chebyshev,
+            Heuristic::Chebyshev => Heuristic::Manhattan,
+        }
+    }
+
+    fn estimate(self, dx: i32, dy: i32) -> f32 {
+        match self {
+            Heuristic::Manhattan => (dx + dy) as f32,
+            Heuristic::Euclidean => ((dx * dx + dy * dy) as f32).sqrt(),
+            Heuristic::Chebyshev => dx.max(dy) as f32,
+        }
+    }
+}
+
+/// How A* and greedy best-first estimate the distance left.
+#[derive(Clone, Copy)]
+pub struct SearchOptions {
+    pub heuristic: Heuristic,
+    /// Multiplies the estimate. Above 1 A* heads for the food more eagerly,
+    /// exploring less but no longer guaranteed to find the shortest path.
+    pub weight: f32,
+}
+
+impl SearchOptions {
+    pub const MIN_WEIGHT: f32 = 0.5;
+    pub const MAX_WEIGHT: f32 = 5.0;
+    pub const WEIGHT_STEP: f32 = 0.5;
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            heuristic: Heuristic::Manhattan,
+            weight: 1.0,
+        }
+    }
+}
+
+/// What a search needs to know about the board.
+pub struct Board<'a> {
+    pub grid: Grid,
+    pub blocked: &'a [Position],
+    /// Each portal's position and where it leads.
+    pub portals: Vec<(Position, Position)>,
+    pub wrap_edges: bool,
+    /// Cost of stepping onto each cell that costs more than plain floor.
+    pub costs: HashMap<Position, i32>,
+    /// Cells a snake's body is moving out of, with the number of steps after
+    /// which each one is free to enter.
+    pub vacates: HashMap<Position, i32>,
+}
+
+impl Board<'_> {
+    /// Where a snake at `pos` ends up moving in `direction`, taking portals
+    /// and wrapping edges into account.
+    pub fn step(&self, pos: Position, direction: Direction) -> Position {
+        let from = self
+            .portals
+            .iter()
+            .find(|(portal, _)| *portal == pos)
+            .map_or(pos, |(_, exit)| *exit);
+        let next = from.step(direction);
+        if self.wrap_edges {
+            self.grid.wrap(next)
+        } else {
+            next
+        }
+    }
+
+    /// What stepping onto `pos` costs, 1 on plain floor.
+    pub fn cost(&self, pos: Position) -> i32 {
+        self.costs.get(&pos).copied().unwrap_or(1)
+    }
+
+    pub fn is_open(&self, pos: Position) -> bool {
+        self.grid.contains(pos) && !self.blocked.contains(&pos)
+    }
+
+    /// Whether a snake can move onto `pos` on its `steps`th step.
+    pub fn is_open_at(&self, pos: Position, steps: i32) -> bool {
+        self.is_open(pos) && self.vacates.get(&pos).is_none_or(|free| steps > *free)
+    }
+
+    /// The step from which every body cell is free.
+    fn horizon(&self) -> i32 {
+        self.vacates.values().max().copied().unwrap_or(0)
+    }
+
+    /// Steps to the closest of `targets`, ignoring obstacles.
+    pub fn distance_to(&self, pos: Position, targets: &[Position]) -> i32 {
+        self.estimate(Heuristic::Manhattan, pos, targets) as i32
+    }
+
+    /// `heuristic`'s distance to the closest of `targets`, counting the
+    /// short way round when edges wrap.
+    fn estimate(&self, heuristic: Heuristic, pos: Position, targets: &[Position]) -> f32 {
+        targets
+            .iter()
+            .map(|target| {
+                let mut dx = (target.x - pos.x).abs();
+                let mut dy = (target.y - pos.y).abs();
+                if self.wrap_edges {
+                    dx = dx.min(self.grid.width as i32 - dx);
+                    dy = dy.min(self.grid.height as i32 - dy);
+                }
+                heuristic.estimate(dx, dy)
+            })
+            .fold(None, |closest: Option<f32>, d| {
+                Some(closest.map_or(d, |closest| closest.min(d)))
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Searches from `start` to whichever of `targets` `algorithm` reaches
+    /// first.
+    pub fn search(
+        &self,
+        algorithm: Algorithm,
+        options: &SearchOptions,
+        start: Position,
+        targets: &[Position],
+    ) -> SearchResult {
+        let mut search = Search::new(self, algorithm, options, start, targets);
+        while search.expand() {}
+        search.result()
+    }
+}
+
+pub struct SearchResult {
+    /// Every step of the path along with the direction taken to get there,
+    /// `None` if no target can be reached.
+    pub path: Option<Vec<(Direction, Position)>>,
+    /// Cells explored on the way, in the order they were first explored.
+    pub explored: Vec<Position>,
+}
+
+/// A cell reached after a number of steps, capped at the board's horizon.
+type Node = (Position, i32);
+
+/// A search in progress, advanced one explored cell at a time.
+pub struct Search<'a> {
+    board: &'a Board<'a>,
+    algorithm: Algorithm,
+    options: &'a SearchOptions,
+    targets: &'a [Position],
+    horizon: i32,
+    /// Cells waiting to be explored by priority, then by insertion order so
+    /// BFS explores first come first served. Priorities are kept in
+    /// thousandths so they can be ordered.
+    open: BinaryHeap<Reverse<(i64, usize, i32, i32, i32)>>,
+    pushed: usize,
+    closed: HashSet<Node>,
+    /// Each cell once, however many steps it was explored at.
+    explored: Vec<Position>,
+    explored_cells: HashSet<Position>,
+    /// Cost of the cheapest way to each cell found so far. For BFS it's the
+    /// number of steps.
+    costs: HashMap<Node, i32>,
+    came_from: HashMap<Node, (Direction, Node)>,
+    found: Option<Node>,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(
+        board: &'a Board<'a>,
+        algorithm: Algorithm,
+        options: &'a SearchOptions,
+        start: Position,
+        targets: &'a [Position],
+    ) -> Self {
+        let mut search = Self {
+            board,
+            algorithm,
+            options,
+            targets,
+            horizon: board.horizon(),
+            open: BinaryHeap::new(),
+            pushed: 0,
+            closed: HashSet::new(),
+            explored: Vec::new(),
+            explored_cells: HashSet::new(),
+            costs: HashMap::new(),
+            came_from: HashMap::new(),
+            found: None,
+        };
+        search.push((start, 0), 0);
+        search
+    }
+
+    fn push(&mut self, node: Node, cost: i32) {
+        let (pos, steps) = node;
+        let remaining = self.remaining(pos);
+        let priority = (self.algorithm.priority(cost, remaining) * 1000.0).round() as i64;
+        // Dijkstra breaks ties the other way round to BFS.
+        let order = match self.algorithm {
+            Algorithm::Dijkstra => usize::MAX - self.pushed,
+            _ => self.pushed,
+        };
+        self.pushed += 1;
+        self.costs.insert(node, cost);
+        self.open
+            .push(Reverse((priority, order, pos.x, pos.y, steps)));
+    }
+
+    /// Explores the next cell in line. Returns `false` once the search is
+    /// over, either at a target or with nothing left to explore.
+    pub fn expand(&mut self) -> bool {
+        if self.found.is_some() {
+            return false;
+        }
+        while let Some(Reverse((_, _, x, y, steps))) = self.open.pop() {
+            let node = (Position { x, y }, steps);
+            // Cells can be queued more than once when a shorter way to them
+            // turns up.
+            if !self.closed.insert(node) {
+                continue;
+            }
+            let pos = node.0;
+            if self.explored_cells.insert(pos) {
+                self.explored.push(pos);
+            }
+            if self.targets.contains(&pos) {
+                self.found = Some(node);
+                return false;
+            }
+            let cost = self.costs[&node];
+            for direction in DIRECTIONS {
+                let next = self.board.step(pos, direction);
+                let next_node = (next, (steps + 1).min(self.horizon));
+                let next_cost = cost
+                    + match self.algorithm {
+                        Algorithm::Bfs => 1,
+                        _ => self.board.cost(next),
+                    };
+                if !self.board.is_open_at(next, steps + 1)
+                    || self.closed.contains(&next_node)
+                    || self.costs.get(&next_node).is_some_and(|c| *c <= next_cost)
+                {
+                    continue;
+                }
+                self.came_from.insert(next_node, (direction, node));
+                self.push(next_node, next_cost);
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Cells explored so far, in the order they were first explored.
+    pub fn closed(&self) -> &[Position] {
+        &self.explored
+    }
+
+    /// Cells queued to be explored, each once.
+    pub fn open(&self) -> Vec<Position> {
+        let mut open: Vec<Position> = self
+            .open
+            .iter()
+            .filter(|Reverse((_, _, x, y, steps))| {
+                !self.closed.contains(&(Position { x: *x, y: *y }, *steps))
+            })
+            .map(|Reverse((_, _, x, y, _))| Position { x: *x, y: *y })
+            .collect();
+        open.sort_by_key(|pos| (pos.x, pos.y));
+        open.dedup();
+        open
+    }
+
+    /// Cost of getting from the start to `pos` along the best way found so
+    /// far.
+    pub fn cost(&self, pos: Position) -> Option<i32> {
+        (0..=self.horizon)
+            .filter_map(|steps| self.costs.get(&(pos, steps)).copied())
+            .min()
+    }
+
+    /// The weighted estimate of the distance left from `pos`.
+    pub fn remaining(&self, pos: Position) -> f32 {
+        self.options.weight
+            * self
+                .board
+                .estimate(self.options.heuristic, pos, self.targets)
+    }
+
+    /// The way to the target, once one has been reached.
+    pub fn path(&self) -> Option<Vec<(Direction, Position)>> {
+        self.found.map(|found| {
+            let mut path = Vec::new();
+            let mut current = found;
+            while let Some((direction, previous)) = self.came_from.get(&current) {
+                path.push((*direction, current.0));
+                current = *previous;
+            }
+            path.reverse();
+            path
+        })
+    }
+
+    pub fn result(self) -> SearchResult {
+        SearchResult {
+            path: self.path(),
+            explored: self.explored,
+        }
+    }
+}
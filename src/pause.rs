@@ -0,0 +1,139 @@
+//! Pausing a run, by hand or by switching to another window. A run paused
+//! that way picks up again with a countdown once the window has focus back.
+
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+
+use crate::input_map::{key_names, InputMap};
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::ui;
+
+/// Seconds counted down before a run paused by losing focus carries on.
+const RESUME_DELAY: f32 = 3.0;
+
+#[derive(Component)]
+struct PauseOverlay;
+
+#[derive(Component)]
+struct CountdownText;
+
+/// Set when the window losing focus paused the run, rather than the player.
+#[derive(Default)]
+struct FocusPause {
+    paused: bool,
+    /// Counting down to resume, once the window has focus again.
+    countdown: Option<Timer>,
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FocusPause>()
+            .add_system(focus_pause)
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(pause_input))
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(resume_input)
+                    .with_system(resume_countdown),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(spawn_overlay))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(despawn_overlay));
+    }
+}
+
+pub fn pause_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, input_map.pause.iter().copied()) {
+        writer.send(GameStateEvent::Pause);
+    }
+}
+
+fn resume_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, input_map.pause.iter().copied()) {
+        writer.send(GameStateEvent::Resume);
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Q]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn focus_pause(
+    mut events: EventReader<WindowFocused>,
+    state: Res<State<GameState>>,
+    mut focus_pause: ResMut<FocusPause>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    for event in events.iter() {
+        match (event.focused, state.current()) {
+            (false, GameState::Playing) => {
+                focus_pause.paused = true;
+                writer.send(GameStateEvent::Pause);
+            }
+            (false, GameState::Paused) => focus_pause.countdown = None,
+            (true, GameState::Paused) if focus_pause.paused => {
+                focus_pause.countdown = Some(Timer::from_seconds(RESUME_DELAY, false));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn resume_countdown(
+    time: Res<Time>,
+    mut focus_pause: ResMut<FocusPause>,
+    mut writer: EventWriter<GameStateEvent>,
+    mut texts: Query<&mut Text, With<CountdownText>>,
+) {
+    let seconds_left = match focus_pause.countdown.as_mut() {
+        Some(timer) => {
+            if timer.tick(time.delta()).just_finished() {
+                writer.send(GameStateEvent::Resume);
+            }
+            (timer.duration() - timer.elapsed())
+                .as_secs_f32()
+                .ceil()
+                .to_string()
+        }
+        None => String::new(),
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = seconds_left.clone();
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, asset_server: Res<AssetServer>, input_map: Res<InputMap>) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(PauseOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line("Paused", ui::text_style(&asset_server, 64.0)));
+            parent.spawn_bundle(ui::text_line(
+                format!(
+                    "Press {} to resume or Q to quit to the menu",
+                    key_names(&input_map.pause)
+                ),
+                ui::text_style(&asset_server, 24.0),
+            ));
+            parent
+                .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 96.0)))
+                .insert(CountdownText);
+        });
+}
+
+fn despawn_overlay(
+    mut commands: Commands,
+    mut focus_pause: ResMut<FocusPause>,
+    overlays: Query<Entity, With<PauseOverlay>>,
+) {
+    for entt in overlays.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+    *focus_pause = FocusPause::default();
+}
@@ -0,0 +1,72 @@
+//! Reading and writing game data files in the platform config directory.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use bevy::log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const APP_DIR: &str = "snake-game";
+
+/// Full path of `file_name` inside the game's config directory, if the
+/// platform has one.
+pub fn data_path(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(file_name))
+}
+
+/// Loads a RON encoded value from the config directory. Returns `None` if the
+/// file doesn't exist yet or can't be parsed.
+pub fn load<T: DeserializeOwned>(file_name: &str) -> Option<T> {
+    let path = data_path(file_name)?;
+    let contents = fs::read_to_string(&path).ok()?;
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("Ignoring malformed {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Loads a RON encoded list from the config directory, or from `bundled`, the
+/// copy shipped with the game, if there's none there or it's empty. `what`
+/// names the entries, for the warning.
+pub fn load_list<T: DeserializeOwned>(file_name: &str, bundled: &str, what: &str) -> Vec<T> {
+    let bundled = || {
+        ron::from_str(bundled).unwrap_or_else(|err| panic!("bundled {} are invalid: {}", what, err))
+    };
+    match load::<Vec<T>>(file_name) {
+        Some(list) if list.is_empty() => {
+            warn!("Ignoring {} without any {}", file_name, what);
+            bundled()
+        }
+        Some(list) => list,
+        None => bundled(),
+    }
+}
+
+/// Saves `value` as RON in the config directory, creating it if needed.
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> io::Result<()> {
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    write(file_name, contents)
+}
+
+/// Like `save`, but on a single line, for files that are too long to be
+/// edited by hand anyway.
+pub fn save_compact<T: Serialize>(file_name: &str, value: &T) -> io::Result<()> {
+    let contents =
+        ron::to_string(value).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    write(file_name, contents)
+}
+
+fn write(file_name: &str, contents: String) -> io::Result<()> {
+    let path = data_path(file_name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
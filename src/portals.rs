@@ -0,0 +1,50 @@
+//! Portal tiles. A snake entering one leaves through its partner on the next
+//! tick.
+
+use bevy::prelude::*;
+
+use crate::level::Level;
+use crate::{Position, Size};
+
+/// One color per pair, indexed by the pair's digit in the level layout.
+const PORTAL_COLORS: [Color; 9] = [
+    Color::rgb(0.2, 0.6, 1.0),
+    Color::rgb(1.0, 0.5, 0.1),
+    Color::rgb(0.8, 0.3, 1.0),
+    Color::rgb(0.1, 0.9, 0.9),
+    Color::rgb(1.0, 0.3, 0.6),
+    Color::rgb(0.6, 0.9, 0.3),
+    Color::rgb(0.9, 0.8, 0.5),
+    Color::rgb(0.5, 0.5, 1.0),
+    Color::rgb(0.9, 0.9, 0.9),
+];
+/// Portals are floor tiles and sit behind anything standing on them. The 2D
+/// camera sees down to z = -0.1.
+const PORTAL_Z: f32 = -0.05;
+
+#[derive(Component)]
+pub struct Portal {
+    /// Where the partner portal is.
+    pub exit: Position,
+}
+
+/// Spawns both ends of every portal pair in `level`.
+pub fn spawn_portals(commands: &mut Commands, level: &Level) {
+    for (pair, [a, b]) in level.portals() {
+        let color = PORTAL_COLORS[pair as usize - 1];
+        spawn_portal(commands, a, b, color);
+        spawn_portal(commands, b, a, color);
+    }
+}
+
+fn spawn_portal(commands: &mut Commands, position: Position, exit: Position, color: Color) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform::from_xyz(0.0, 0.0, PORTAL_Z),
+            ..default()
+        })
+        .insert(Portal { exit })
+        .insert(position)
+        .insert(Size::square(0.9));
+}
@@ -0,0 +1,352 @@
+//! Collectibles that grant a temporary effect instead of growth.
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::food::{free_cells, Expiring, Food, Occupying};
+use crate::rng::GameRng;
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::{snake_eating, snake_movement, Grid, Player, Position, Size, SnakeSegments};
+
+const SPAWN_INTERVAL: f32 = 12.0;
+const SPAWN_CHANCE: f32 = 0.4;
+/// Seconds an uncollected power-up stays on the board.
+const LIFETIME: f32 = 8.0;
+const GHOST_ALPHA: f32 = 0.4;
+/// The ghost effect flashes for this many seconds before it wears off.
+const GHOST_WARNING_TIME: f32 = 1.5;
+const GHOST_FLASH_RATE: f32 = 6.0;
+/// Food within this many cells (Manhattan distance) of the head is pulled in
+/// by the magnet.
+const MAGNET_RADIUS: i32 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PowerUpKind {
+    /// Halves the movement interval.
+    SpeedBoost,
+    /// Doubles the movement interval.
+    SlowMotion,
+    /// Lets the snake pass through its own body.
+    Ghost,
+    /// Absorbs the next collision.
+    Shield,
+    /// Doubles the points scored while it runs.
+    DoubleScore,
+    /// Triples the points scored while it runs.
+    TripleScore,
+    /// Pulls nearby food towards the head.
+    Magnet,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 7] = [
+        PowerUpKind::SpeedBoost,
+        PowerUpKind::SlowMotion,
+        PowerUpKind::Ghost,
+        PowerUpKind::Shield,
+        PowerUpKind::DoubleScore,
+        PowerUpKind::TripleScore,
+        PowerUpKind::Magnet,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUpKind::SpeedBoost => "Speed",
+            PowerUpKind::SlowMotion => "Slow-mo",
+            PowerUpKind::Ghost => "Ghost",
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::DoubleScore => "x2",
+            PowerUpKind::TripleScore => "x3",
+            PowerUpKind::Magnet => "Magnet",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PowerUpKind::SpeedBoost => Color::rgb(1.0, 0.3, 0.1),
+            PowerUpKind::SlowMotion => Color::rgb(0.4, 0.5, 1.0),
+            PowerUpKind::Ghost => Color::rgb(0.9, 0.9, 0.95),
+            PowerUpKind::Shield => Color::rgb(0.2, 0.9, 0.9),
+            PowerUpKind::DoubleScore => Color::rgb(0.6, 1.0, 0.6),
+            PowerUpKind::TripleScore => Color::rgb(0.2, 0.8, 0.3),
+            PowerUpKind::Magnet => Color::rgb(0.8, 0.1, 0.1),
+        }
+    }
+
+    /// Seconds the effect lasts once collected, `None` for effects that
+    /// last until they're used up.
+    fn duration(self) -> Option<f32> {
+        match self {
+            PowerUpKind::SpeedBoost => Some(5.0),
+            PowerUpKind::SlowMotion => Some(6.0),
+            PowerUpKind::Ghost => Some(6.0),
+            PowerUpKind::Shield => None,
+            PowerUpKind::DoubleScore => Some(8.0),
+            PowerUpKind::TripleScore => Some(6.0),
+            PowerUpKind::Magnet => Some(8.0),
+        }
+    }
+
+    /// Effects that are cancelled when this one starts, so the latest pickup
+    /// wins.
+    fn cancels(self) -> &'static [PowerUpKind] {
+        match self {
+            PowerUpKind::SpeedBoost => &[PowerUpKind::SlowMotion],
+            PowerUpKind::SlowMotion => &[PowerUpKind::SpeedBoost],
+            PowerUpKind::DoubleScore => &[PowerUpKind::TripleScore],
+            PowerUpKind::TripleScore => &[PowerUpKind::DoubleScore],
+            PowerUpKind::Ghost | PowerUpKind::Shield | PowerUpKind::Magnet => &[],
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+}
+
+/// Effects currently running: timed ones with the time they have left, plus
+/// a shield waiting for a collision.
+#[derive(Default)]
+pub struct ActiveEffects {
+    timed: HashMap<PowerUpKind, Timer>,
+    shielded: bool,
+}
+
+impl ActiveEffects {
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        match kind {
+            PowerUpKind::Shield => self.shielded,
+            _ => self.timed.contains_key(&kind),
+        }
+    }
+
+    /// Seconds left on a timed effect, if it's running.
+    pub fn time_left(&self, kind: PowerUpKind) -> Option<f32> {
+        self.timed
+            .get(&kind)
+            .map(|timer| timer.duration().as_secs_f32() - timer.elapsed_secs())
+    }
+
+    /// Running effects with their remaining seconds, shortest first. Untimed
+    /// effects come last without a time.
+    pub fn remaining(&self) -> Vec<(PowerUpKind, Option<f32>)> {
+        let mut remaining: Vec<_> = self
+            .timed
+            .keys()
+            .map(|kind| (*kind, self.time_left(*kind)))
+            .collect();
+        remaining.sort_by(|a, b| a.1.unwrap_or(0.0).total_cmp(&b.1.unwrap_or(0.0)));
+        if self.shielded {
+            remaining.push((PowerUpKind::Shield, None));
+        }
+        remaining
+    }
+
+    /// Factor applied to points scored right now.
+    pub fn score_multiplier(&self) -> u32 {
+        if self.is_active(PowerUpKind::TripleScore) {
+            3
+        } else if self.is_active(PowerUpKind::DoubleScore) {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Uses up the shield, returning whether there was one.
+    pub fn consume_shield(&mut self) -> bool {
+        std::mem::take(&mut self.shielded)
+    }
+
+    /// Starts `kind`, or restarts it if it's already running.
+    fn activate(&mut self, kind: PowerUpKind) {
+        for cancelled in kind.cancels() {
+            self.timed.remove(cancelled);
+        }
+        match kind.duration() {
+            Some(seconds) => {
+                self.timed.insert(kind, Timer::from_seconds(seconds, false));
+            }
+            None => self.shielded = true,
+        }
+    }
+}
+
+struct PowerUpSpawnTimer(Timer);
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveEffects>()
+            .insert_resource(PowerUpSpawnTimer(Timer::from_seconds(SPAWN_INTERVAL, true)))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_power_ups))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(ghost_appearance.after(tick_effects)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(tick_effects.before(snake_movement))
+                    .with_system(spawn_power_ups.after(snake_movement))
+                    .with_system(collect_power_ups.after(snake_movement))
+                    .with_system(magnet_pull.after(snake_eating)),
+            );
+    }
+}
+
+fn reset_power_ups(
+    mut commands: Commands,
+    mut effects: ResMut<ActiveEffects>,
+    mut timer: ResMut<PowerUpSpawnTimer>,
+    power_ups: Query<Entity, With<PowerUp>>,
+) {
+    for entt in power_ups.iter() {
+        commands.entity(entt).despawn();
+    }
+    *effects = ActiveEffects::default();
+    timer.0.reset();
+}
+
+fn spawn_power_ups(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    mut timer: ResMut<PowerUpSpawnTimer>,
+    mut rng: ResMut<GameRng>,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    power_ups: Query<(), With<PowerUp>>,
+    occupied: Query<&Position, Occupying>,
+) {
+    // Effects apply to whoever is playing, which can't be made fair between
+    // two players.
+    if rules.mode == GameMode::Versus {
+        return;
+    }
+    if !timer.0.tick(move_timer.step()).just_finished()
+        || !power_ups.is_empty()
+        || rng.power_ups().gen::<f32>() >= SPAWN_CHANCE
+    {
+        return;
+    }
+    let position = match free_cells(*grid, occupied.iter()).choose(rng.power_ups()) {
+        Some(position) => *position,
+        None => return,
+    };
+    let kind = *PowerUpKind::ALL.choose(rng.power_ups()).unwrap();
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                ..default()
+            },
+            // Power-ups are drawn as diamonds to set them apart from food.
+            transform: Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+            ..default()
+        })
+        .insert(PowerUp { kind })
+        .insert(Expiring(Timer::from_seconds(LIFETIME, false)))
+        .insert(position)
+        .insert(Size::square(0.6));
+}
+
+fn collect_power_ups(
+    mut commands: Commands,
+    mut effects: ResMut<ActiveEffects>,
+    power_ups: Query<(Entity, &Position, &PowerUp)>,
+    heads: Query<&Position, With<Player>>,
+) {
+    for head_pos in heads.iter() {
+        for (entt, pos, power_up) in power_ups.iter() {
+            if pos == head_pos {
+                commands.entity(entt).despawn();
+                effects.activate(power_up.kind);
+            }
+        }
+    }
+}
+
+/// Moves each piece of food near the head one cell closer to it, trying the
+/// axis with the larger gap first and never onto an occupied cell.
+fn magnet_pull(
+    effects: Res<ActiveEffects>,
+    heads: Query<&Position, (With<Player>, Without<Food>)>,
+    mut food: Query<&mut Position, With<Food>>,
+    others: Query<&Position, (Occupying, Without<Food>)>,
+) {
+    if !effects.is_active(PowerUpKind::Magnet) {
+        return;
+    }
+    let head = match heads.iter().next() {
+        Some(head) => *head,
+        None => return,
+    };
+    let mut taken: HashSet<Position> = others.iter().chain(food.iter()).copied().collect();
+    for mut pos in food.iter_mut() {
+        let (dx, dy) = (head.x - pos.x, head.y - pos.y);
+        let distance = dx.abs() + dy.abs();
+        if distance <= 1 || distance > MAGNET_RADIUS {
+            continue;
+        }
+        let step_x = Position {
+            x: pos.x + dx.signum(),
+            ..*pos
+        };
+        let step_y = Position {
+            y: pos.y + dy.signum(),
+            ..*pos
+        };
+        let steps = if dx.abs() >= dy.abs() {
+            [step_x, step_y]
+        } else {
+            [step_y, step_x]
+        };
+        if let Some(next) = steps
+            .into_iter()
+            .find(|next| *next != *pos && !taken.contains(next))
+        {
+            taken.remove(&pos);
+            taken.insert(next);
+            *pos = next;
+        }
+    }
+}
+
+fn tick_effects(move_timer: Res<MoveTimer>, mut effects: ResMut<ActiveEffects>) {
+    effects
+        .timed
+        .retain(|_, timer| !timer.tick(move_timer.step()).finished());
+}
+
+/// Fades the player's snake while the ghost effect runs, flashing it shortly before
+/// the effect ends.
+fn ghost_appearance(
+    effects: Res<ActiveEffects>,
+    time: Res<Time>,
+    players: Query<&SnakeSegments, With<Player>>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    let alpha = match effects.time_left(PowerUpKind::Ghost) {
+        Some(left)
+            if left < GHOST_WARNING_TIME
+                && (time.seconds_since_startup() as f32 * GHOST_FLASH_RATE).fract() < 0.5 =>
+        {
+            1.0
+        }
+        Some(_) => GHOST_ALPHA,
+        None => 1.0,
+    };
+    for segment in players.iter().flat_map(SnakeSegments::iter) {
+        if let Ok(mut sprite) = sprites.get_mut(*segment) {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Asking before a run is thrown away. Esc mid-run, or closing the window
+//! during one, opens a dialog to confirm. Closing the window anywhere else
+//! exits straight away.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::{WindowCloseRequested, WindowPlugin};
+
+use crate::demo::Demo;
+use crate::pause::pause_input;
+use crate::replay::Playback;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::ui;
+
+const BUTTON_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.1);
+const SELECTED_COLOR: Color = Color::rgb(0.3, 0.6, 0.3);
+
+/// Bevy's `WindowPlugin` without the system that exits as soon as the window
+/// is asked to close, so `close_requested` can ask first.
+pub struct ConfirmCloseWindowPlugin;
+
+impl Plugin for ConfirmCloseWindowPlugin {
+    fn build(&self, app: &mut App) {
+        WindowPlugin {
+            exit_on_close: false,
+            ..default()
+        }
+        .build(app);
+    }
+}
+
+#[derive(Default)]
+struct QuitDialog {
+    /// Closing the window asked, so yes exits the game rather than going
+    /// back to the menu.
+    exit: bool,
+    /// Whether Yes is picked. No is, to begin with.
+    yes: bool,
+}
+
+#[derive(Component)]
+struct QuitOverlay;
+
+#[derive(Component)]
+struct QuitButton {
+    yes: bool,
+}
+
+pub struct QuitPlugin;
+
+impl Plugin for QuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuitDialog>()
+            .add_system(close_requested)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(quit_input.before(pause_input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::ConfirmQuit)
+                    .with_system(dialog_input)
+                    .with_system(button_update.after(dialog_input)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::ConfirmQuit).with_system(spawn_dialog))
+            .add_system_set(SystemSet::on_exit(GameState::ConfirmQuit).with_system(despawn_dialog));
+    }
+}
+
+/// Whether quitting now would lose anything. Leaving a replay or the demo
+/// doesn't.
+fn in_run(state: GameState, playback: &Playback, demo: &Demo) -> bool {
+    matches!(
+        state,
+        GameState::Playing | GameState::Paused | GameState::Respawning | GameState::Dying
+    ) && !playback.is_active()
+        && !demo.is_active()
+}
+
+fn close_requested(
+    mut events: EventReader<WindowCloseRequested>,
+    state: Res<State<GameState>>,
+    playback: Res<Playback>,
+    demo: Res<Demo>,
+    mut dialog: ResMut<QuitDialog>,
+    mut state_writer: EventWriter<GameStateEvent>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    if events.iter().next().is_none() {
+        return;
+    }
+    if *state.current() == GameState::ConfirmQuit {
+        // Already asking, now about closing the game.
+        dialog.exit = true;
+    } else if in_run(*state.current(), &playback, &demo) {
+        *dialog = QuitDialog {
+            exit: true,
+            ..default()
+        };
+        state_writer.send(GameStateEvent::AskQuit);
+    } else {
+        exit_writer.send(AppExit);
+    }
+}
+
+fn quit_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    playback: Res<Playback>,
+    demo: Res<Demo>,
+    mut dialog: ResMut<QuitDialog>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    // The replay and the demo take Esc themselves.
+    if !in_run(*state.current(), &playback, &demo) {
+        return;
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        *dialog = QuitDialog::default();
+        writer.send(GameStateEvent::AskQuit);
+    }
+}
+
+fn dialog_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut dialog: ResMut<QuitDialog>,
+    mut state_writer: EventWriter<GameStateEvent>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    let arrows = [
+        KeyCode::Left,
+        KeyCode::Right,
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::Tab,
+    ];
+    if take_just_pressed(&mut kbd_input, arrows) {
+        dialog.yes = !dialog.yes;
+    }
+    let yes = if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+        dialog.yes
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Y]) {
+        true
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Escape, KeyCode::N]) {
+        false
+    } else {
+        return;
+    };
+    if !yes {
+        state_writer.send(GameStateEvent::Resume);
+    } else if dialog.exit {
+        exit_writer.send(AppExit);
+    } else {
+        state_writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn spawn_dialog(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(QuitOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line("Quit?", ui::text_style(&asset_server, 64.0)));
+            parent.spawn_bundle(ui::text_line(
+                "Progress will be lost",
+                ui::text_style(&asset_server, 24.0),
+            ));
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    color: UiColor(Color::NONE),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (yes, label) in [(true, "Yes"), (false, "No")] {
+                        row.spawn_bundle(NodeBundle {
+                            style: Style {
+                                padding: Rect::all(Val::Px(8.0)),
+                                margin: Rect::all(Val::Px(12.0)),
+                                ..default()
+                            },
+                            color: UiColor(BUTTON_COLOR),
+                            ..default()
+                        })
+                        .insert(QuitButton { yes })
+                        .with_children(|button| {
+                            button.spawn_bundle(ui::text_line(
+                                label,
+                                ui::text_style(&asset_server, 40.0),
+                            ));
+                        });
+                    }
+                });
+            parent.spawn_bundle(ui::text_line(
+                "Left/Right to pick, Enter to answer",
+                ui::text_style(&asset_server, 20.0),
+            ));
+        });
+}
+
+fn button_update(dialog: Res<QuitDialog>, mut buttons: Query<(&QuitButton, &mut UiColor)>) {
+    for (button, mut color) in buttons.iter_mut() {
+        color.0 = if button.yes == dialog.yes {
+            SELECTED_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+    }
+}
+
+fn despawn_dialog(mut commands: Commands, overlays: Query<Entity, With<QuitOverlay>>) {
+    for entt in overlays.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
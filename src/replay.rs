@@ -0,0 +1,386 @@
+//! Replays. A run is fully decided by its seed, rules, level and the turns
+//! the player made, so that's all a replay stores: every change of direction
+//! with the movement tick it happened on. The last run is always saved, and
+//! each mode keeps the replay of its best run.
+//!
+//! Watching a replay plays the run again from the same seed, steering the
+//! snake with the recorded turns instead of the keyboard.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::dash::Dash;
+use crate::level::{CurrentLevel, Level};
+use crate::menu::menu_input;
+use crate::pause::pause_input;
+use crate::persistence;
+use crate::rng::GameRng;
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::sprint::Sprint;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::tick::{MoveTimer, MovementTick};
+use crate::ui;
+use crate::{
+    snake_growth, snake_movement, snake_movement_input, Direction, Player, Position, SnakeHead,
+    SnakeSegments,
+};
+
+const LAST_REPLAY_FILE: &str = "replays/last.ron";
+/// How many times faster than normal a replay plays when sped up.
+const FAST_SPEED: f32 = 2.0;
+
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: GameRules,
+    pub level: Level,
+    pub score: u32,
+    /// Movement ticks the run lasted.
+    pub ticks: u32,
+    /// Player one's direction each time it changed, with the tick it moved
+    /// that way on. The first entry is the direction the run started in.
+    pub turns: Vec<(u32, Direction)>,
+    /// Where player one's head was after each tick, and its length each
+    /// time that changed, so the run can be drawn without playing it again.
+    #[serde(default)]
+    pub trail: Vec<Position>,
+    #[serde(default)]
+    pub lengths: Vec<(u32, usize)>,
+    /// Ticks player one dashed on.
+    #[serde(default)]
+    pub dashes: Vec<u32>,
+    /// Ticks that were sprinted.
+    #[serde(default)]
+    pub sprints: Vec<u32>,
+}
+
+impl Replay {
+    pub fn load_last() -> Option<Self> {
+        persistence::load(LAST_REPLAY_FILE)
+    }
+
+    /// The replay of the best run in `mode`, if one has been recorded.
+    pub fn load_best(mode: GameMode) -> Option<Self> {
+        persistence::load(&best_replay_file(mode))
+    }
+}
+
+fn best_replay_file(mode: GameMode) -> String {
+    format!("replays/best-{:?}.ron", mode).to_lowercase()
+}
+
+/// The turns of the run in progress.
+#[derive(Default)]
+struct Recording {
+    ticks: u32,
+    turns: Vec<(u32, Direction)>,
+    trail: Vec<Position>,
+    lengths: Vec<(u32, usize)>,
+    dashes: Vec<u32>,
+    sprints: Vec<u32>,
+}
+
+/// Set while a replay is being watched.
+#[derive(Default)]
+pub struct Playback(Option<Watching>);
+
+struct Watching {
+    replay: Replay,
+    /// The rules and level picked on the menu, put back afterwards.
+    picked: (GameRules, CurrentLevel),
+    /// Movement ticks played so far.
+    tick: u32,
+    paused: bool,
+    fast: bool,
+}
+
+impl Playback {
+    pub fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// The seed of the replay being watched.
+    pub fn seed(&self) -> Option<u64> {
+        self.0.as_ref().map(|watching| watching.replay.seed)
+    }
+}
+
+#[derive(Component)]
+struct PlaybackBanner;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .init_resource::<Playback>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(start_recording)
+                    .with_system(rewind_playback),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(record_tick.after(snake_growth))
+                    .with_system(
+                        play_turns
+                            .after(snake_movement_input)
+                            .before(snake_movement),
+                    ),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(save_replay))
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_playback))
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(watch_input.before(menu_input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(playback_input.before(pause_input))
+                    .with_system(banner_update.after(playback_input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Respawning).with_system(playback_input),
+            );
+    }
+}
+
+fn start_recording(mut recording: ResMut<Recording>) {
+    *recording = Recording::default();
+}
+
+/// R on the menu watches the last run, T the best one in the selected mode.
+fn watch_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut playback: ResMut<Playback>,
+    mut rules: ResMut<GameRules>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    let replay = if take_just_pressed(&mut kbd_input, [KeyCode::R]) {
+        Replay::load_last()
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::T]) {
+        Replay::load_best(rules.mode)
+    } else {
+        return;
+    };
+    let replay = match replay {
+        Some(replay) => replay,
+        None => {
+            info!("No replay to watch yet");
+            return;
+        }
+    };
+    let picked_rules = std::mem::replace(&mut *rules, replay.rules.clone());
+    let picked_level = std::mem::replace(
+        &mut *current_level,
+        CurrentLevel::custom(replay.level.clone()),
+    );
+    playback.0 = Some(Watching {
+        replay,
+        picked: (picked_rules, picked_level),
+        tick: 0,
+        paused: false,
+        fast: false,
+    });
+    writer.send(GameStateEvent::StartRun);
+}
+
+/// Starts the replay over with each new run, and shows that it's one.
+fn rewind_playback(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut playback: ResMut<Playback>,
+    banners: Query<(), With<PlaybackBanner>>,
+) {
+    let watching = match playback.0.as_mut() {
+        Some(watching) => watching,
+        None => return,
+    };
+    watching.tick = 0;
+    if banners.is_empty() {
+        commands
+            .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 24.0)))
+            .insert(PlaybackBanner);
+    }
+}
+
+/// Steers player one the way the recording says, overriding the keyboard.
+fn play_turns(
+    mut playback: ResMut<Playback>,
+    mut sprint: ResMut<Sprint>,
+    mut players: Query<(&Player, &mut SnakeHead, Option<&mut Dash>)>,
+) {
+    let watching = match playback.0.as_mut() {
+        Some(watching) => watching,
+        None => return,
+    };
+    let tick = watching.tick;
+    watching.tick += 1;
+    let direction = watching
+        .replay
+        .turns
+        .iter()
+        .take_while(|(turn_tick, _)| *turn_tick <= tick)
+        .last()
+        .map(|(_, direction)| *direction);
+    let dashes = watching.replay.dashes.contains(&tick);
+    sprint.active = watching.replay.sprints.contains(&tick);
+    for (player, mut head, dash) in players.iter_mut() {
+        if player.0 != 1 {
+            continue;
+        }
+        head.input_queue.clear();
+        if let Some(direction) = direction {
+            head.direction = direction;
+        }
+        if let Some(mut dash) = dash.filter(|_| dashes) {
+            dash.request();
+        }
+    }
+}
+
+fn playback_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut playback: ResMut<Playback>,
+    mut move_timer: ResMut<MoveTimer>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    let watching = match playback.0.as_mut() {
+        Some(watching) => watching,
+        None => return,
+    };
+    if take_just_pressed(&mut kbd_input, [KeyCode::Space]) {
+        watching.paused = !watching.paused;
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::F]) {
+        watching.fast = !watching.fast;
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::R]) {
+        watching.paused = false;
+        writer.send(GameStateEvent::StartRun);
+    }
+    if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+    let speed = match (watching.paused, watching.fast) {
+        (true, _) => 0.0,
+        (false, true) => FAST_SPEED,
+        (false, false) => 1.0,
+    };
+    if move_timer.speed != speed {
+        move_timer.speed = speed;
+    }
+}
+
+fn banner_update(playback: Res<Playback>, mut banners: Query<&mut Text, With<PlaybackBanner>>) {
+    let watching = match &playback.0 {
+        Some(watching) if playback.is_changed() => watching,
+        _ => return,
+    };
+    let status = if watching.paused {
+        "Paused"
+    } else if watching.fast {
+        "2x speed"
+    } else {
+        "Playing"
+    };
+    for mut text in banners.iter_mut() {
+        text.sections[0].value = format!(
+            "Replay ({}, {} points) - {}\nSpace pause   F speed   R restart   Esc menu",
+            watching.replay.rules.mode.label(),
+            watching.replay.score,
+            status
+        );
+    }
+}
+
+fn end_playback(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut rules: ResMut<GameRules>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut move_timer: ResMut<MoveTimer>,
+    banners: Query<Entity, With<PlaybackBanner>>,
+) {
+    if let Some(watching) = playback.0.take() {
+        (*rules, *current_level) = watching.picked;
+        move_timer.speed = 1.0;
+    }
+    for entt in banners.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
+
+fn record_tick(
+    mut recording: ResMut<Recording>,
+    sprint: Res<Sprint>,
+    players: Query<(
+        &Player,
+        &SnakeHead,
+        &Position,
+        &SnakeSegments,
+        Option<&Dash>,
+    )>,
+) {
+    let tick = recording.ticks;
+    recording.ticks += 1;
+    if sprint.active {
+        recording.sprints.push(tick);
+    }
+    let (head, position, segments, dash) = match players.iter().find(|(player, ..)| player.0 == 1) {
+        Some((_, head, position, segments, dash)) => (head, position, segments, dash),
+        None => return,
+    };
+    if recording.turns.last().map(|(_, last)| *last) != Some(head.direction) {
+        recording.turns.push((tick, head.direction));
+    }
+    if dash.is_some_and(|dash| dash.dashed) {
+        recording.dashes.push(tick);
+    }
+    recording.trail.push(*position);
+    if recording.lengths.last().map(|(_, last)| *last) != Some(segments.len()) {
+        recording.lengths.push((tick, segments.len()));
+    }
+}
+
+fn save_replay(
+    mut recording: ResMut<Recording>,
+    playback: Res<Playback>,
+    rules: Res<GameRules>,
+    current: Res<CurrentLevel>,
+    rng: Res<GameRng>,
+    score: Res<Score>,
+) {
+    // Replays follow a single player, and watching one doesn't make a new
+    // one. A rewound run can't be played back from its turns.
+    if rules.mode.two_player() || rules.mode == GameMode::Casual || playback.is_active() {
+        return;
+    }
+    let recording = std::mem::take(&mut *recording);
+    let replay = Replay {
+        seed: rng.seed(),
+        rules: rules.clone(),
+        level: current.level.clone(),
+        score: score.0,
+        ticks: recording.ticks,
+        turns: recording.turns,
+        trail: recording.trail,
+        lengths: recording.lengths,
+        dashes: recording.dashes,
+        sprints: recording.sprints,
+    };
+    if let Err(err) = persistence::save_compact(LAST_REPLAY_FILE, &replay) {
+        warn!("Failed to save replay: {}", err);
+    }
+    // A daily best only stands against runs on the same day's board.
+    let beaten = Replay::load_best(rules.mode).is_none_or(|best| {
+        replay.score > best.score || (rules.mode == GameMode::Daily && replay.seed != best.seed)
+    });
+    if beaten && replay.score > 0 {
+        if let Err(err) = persistence::save_compact(&best_replay_file(rules.mode), &replay) {
+            warn!("Failed to save best replay: {}", err);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Rewinding in casual mode. Every tick the players' snakes, the food, the
+//! score and the run's randomness are kept, going back `REWIND_SECONDS` of
+//! game time. Holding Backspace steps back through them, and play goes on
+//! from wherever it's let go. A crash doesn't cost a life in casual mode: the
+//! snake stops where it hit until it's rewound.
+//!
+//! Enemies and power-ups aren't kept and carry on from where they are.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::food::{spawn_food, Food, FoodKind};
+use crate::rng::GameRng;
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::state::GameState;
+use crate::tick::{FoodEaten, MoveTimer, MovementTick};
+use crate::ui;
+use crate::{
+    game_over, snake_movement, spawn_snake_segment, Direction, GameOverEvent, Player, Position,
+    SnakeHead, SnakeSegments,
+};
+
+/// Game time kept to rewind through.
+const REWIND_SECONDS: f32 = 10.0;
+/// Real seconds per tick stepped back while Backspace is held.
+const REWIND_STEP: f32 = 0.05;
+const REWIND_KEY: KeyCode = KeyCode::Back;
+
+/// The state of the run before each of the last ticks, oldest first.
+#[derive(Default)]
+struct History {
+    snapshots: VecDeque<Snapshot>,
+    /// Set from a crash until the next rewind.
+    crashed: bool,
+    /// Paces stepping back while the rewind key is held.
+    rewinding: Timer,
+}
+
+struct Snapshot {
+    /// Game time the tick after the snapshot took.
+    step: Duration,
+    /// Each player's head, direction and the cells of its body, head first.
+    snakes: Vec<(Entity, Direction, Vec<Position>)>,
+    food: Vec<(Position, FoodKind)>,
+    score: u32,
+    food_eaten: FoodEaten,
+    rng: GameRng,
+}
+
+#[derive(Component)]
+struct RewindBanner;
+
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_history))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(take_snapshot.before(snake_movement))
+                    .with_system(stop_at_crash.after(game_over)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(rewind_input)
+                    .with_system(banner_update.after(rewind_input)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_history));
+    }
+}
+
+fn start_history(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut history: ResMut<History>,
+    mut move_timer: ResMut<MoveTimer>,
+    rules: Res<GameRules>,
+    banners: Query<(), With<RewindBanner>>,
+) {
+    // A run restarted while stopped at a crash starts moving again.
+    if history.crashed {
+        move_timer.speed = 1.0;
+    }
+    *history = History::default();
+    if rules.mode == GameMode::Casual && banners.is_empty() {
+        commands
+            .spawn_bundle(ui::text_line("", ui::text_style(&asset_server, 24.0)))
+            .insert(RewindBanner);
+    }
+}
+
+fn end_history(
+    mut commands: Commands,
+    mut history: ResMut<History>,
+    mut move_timer: ResMut<MoveTimer>,
+    banners: Query<Entity, With<RewindBanner>>,
+) {
+    if history.crashed {
+        move_timer.speed = 1.0;
+    }
+    *history = History::default();
+    for entt in banners.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
+
+fn take_snapshot(
+    mut history: ResMut<History>,
+    rules: Res<GameRules>,
+    move_timer: Res<MoveTimer>,
+    score: Res<Score>,
+    food_eaten: Res<FoodEaten>,
+    rng: Res<GameRng>,
+    players: Query<(Entity, &SnakeHead, &SnakeSegments), With<Player>>,
+    positions: Query<&Position>,
+    food: Query<(&Position, &Food)>,
+) {
+    if rules.mode != GameMode::Casual {
+        return;
+    }
+    let snakes = players
+        .iter()
+        .map(|(snake, head, segments)| {
+            let cells = segments
+                .iter()
+                .map(|e| *positions.get(*e).unwrap())
+                .collect();
+            (snake, head.direction, cells)
+        })
+        .collect();
+    history.snapshots.push_back(Snapshot {
+        step: move_timer.step(),
+        snakes,
+        food: food.iter().map(|(pos, food)| (*pos, food.kind)).collect(),
+        score: score.0,
+        food_eaten: food_eaten.clone(),
+        rng: rng.clone(),
+    });
+    let limit = Duration::from_secs_f32(REWIND_SECONDS);
+    while history.snapshots.iter().map(|s| s.step).sum::<Duration>() > limit {
+        history.snapshots.pop_front();
+    }
+}
+
+/// Stops the ticks at a crash, leaving the snake where it hit.
+fn stop_at_crash(
+    mut reader: EventReader<GameOverEvent>,
+    mut history: ResMut<History>,
+    mut move_timer: ResMut<MoveTimer>,
+    rules: Res<GameRules>,
+) {
+    if rules.mode == GameMode::Casual && reader.iter().last().is_some() {
+        history.crashed = true;
+        move_timer.speed = 0.0;
+    }
+}
+
+fn rewind_input(
+    mut commands: Commands,
+    time: Res<Time>,
+    kbd_input: Res<Input<KeyCode>>,
+    rules: Res<GameRules>,
+    mut history: ResMut<History>,
+    mut move_timer: ResMut<MoveTimer>,
+    mut score: ResMut<Score>,
+    mut food_eaten: ResMut<FoodEaten>,
+    mut rng: ResMut<GameRng>,
+    mut players: Query<(&mut SnakeHead, &mut SnakeSegments)>,
+    mut positions: Query<&mut Position>,
+    food: Query<Entity, With<Food>>,
+) {
+    if rules.mode != GameMode::Casual {
+        return;
+    }
+    if kbd_input.just_released(REWIND_KEY) && !history.crashed {
+        move_timer.speed = 1.0;
+    }
+    if !kbd_input.pressed(REWIND_KEY) {
+        return;
+    }
+    move_timer.speed = 0.0;
+    if kbd_input.just_pressed(REWIND_KEY) {
+        // The first step back comes straight away.
+        history.rewinding = Timer::from_seconds(REWIND_STEP, true);
+    } else if !history.rewinding.tick(time.delta()).just_finished() {
+        return;
+    }
+    let snapshot = match history.snapshots.pop_back() {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+    history.crashed = false;
+
+    for (snake, direction, cells) in snapshot.snakes {
+        let (mut head, mut segments) = match players.get_mut(snake) {
+            Ok(snake) => snake,
+            Err(_) => continue,
+        };
+        head.direction = direction;
+        head.input_queue.clear();
+        // Body segments are moved back into place, with any the snake has
+        // lost since put back and any it has grown since dropped.
+        let length = segments.len();
+        let body = segments.remove_tail(length - 1);
+        *positions.get_mut(snake).unwrap() = cells[0];
+        for (i, cell) in cells.iter().enumerate().skip(1) {
+            let segment = match body.get(i - 1) {
+                Some(segment) => {
+                    *positions.get_mut(*segment).unwrap() = *cell;
+                    *segment
+                }
+                None => spawn_snake_segment(&mut commands, *cell, head.color),
+            };
+            segments.push(segment);
+        }
+        for segment in body.iter().skip(cells.len() - 1) {
+            commands.entity(*segment).despawn();
+        }
+    }
+
+    for entt in food.iter() {
+        commands.entity(entt).despawn();
+    }
+    for (pos, kind) in snapshot.food {
+        spawn_food(&mut commands, pos, kind);
+    }
+    score.0 = snapshot.score;
+    *food_eaten = snapshot.food_eaten;
+    *rng = snapshot.rng;
+}
+
+fn banner_update(history: Res<History>, mut banners: Query<&mut Text, With<RewindBanner>>) {
+    if !history.is_changed() {
+        return;
+    }
+    let status = if history.crashed {
+        "Crashed! Hold Backspace to rewind"
+    } else {
+        "Hold Backspace to rewind"
+    };
+    for mut text in banners.iter_mut() {
+        text.sections[0].value = format!(
+            "{} ({:.0}s kept)",
+            status,
+            history
+                .snapshots
+                .iter()
+                .map(|s| s.step)
+                .sum::<Duration>()
+                .as_secs_f32()
+        );
+    }
+}
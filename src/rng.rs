@@ -0,0 +1,138 @@
+//! Seeded randomness. Everything random in a run draws from `GameRng`, which
+//! is reseeded at the start of every run so the same seed plays out the same
+//! way. The seed comes from the replay being watched, if any, then from the
+//! date in the daily challenge, otherwise from `--seed` or the `seed` setting,
+//! then from the best run being raced, see `ghost`, and is random when none
+//! of those apply.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::cli::CliArgs;
+use crate::daily;
+use crate::ghost::Ghost;
+use crate::replay::Playback;
+use crate::rules::{GameMode, GameRules};
+use crate::settings::Settings;
+use crate::state::GameState;
+
+#[derive(Clone)]
+pub struct GameRng {
+    seed: u64,
+    board: ChaCha8Rng,
+    food: ChaCha8Rng,
+    special_food: ChaCha8Rng,
+    power_ups: ChaCha8Rng,
+    enemies: ChaCha8Rng,
+    objectives: ChaCha8Rng,
+    boss: ChaCha8Rng,
+    world_events: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        // Separate streams, so e.g. an enemy spawning doesn't shift which
+        // food comes up next, and systems running in either order within a
+        // tick draw the same numbers.
+        let stream = |n| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(n);
+            rng
+        };
+        Self {
+            seed,
+            board: stream(0),
+            food: stream(1),
+            special_food: stream(2),
+            power_ups: stream(3),
+            enemies: stream(4),
+            objectives: stream(5),
+            boss: stream(6),
+            world_events: stream(7),
+        }
+    }
+
+    /// The seed the current run started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Randomness for laying out the board at the start of a run.
+    pub fn board(&mut self) -> &mut ChaCha8Rng {
+        &mut self.board
+    }
+
+    /// Randomness for the next piece of food. Each piece takes exactly one
+    /// draw from the run's food stream however many cells it has to try, so
+    /// the nth piece comes out the same in every run with the same seed.
+    pub fn next_food(&mut self) -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(self.food.gen())
+    }
+
+    /// Randomness for when and where poison and golden food turn up.
+    pub fn special_food(&mut self) -> &mut ChaCha8Rng {
+        &mut self.special_food
+    }
+
+    pub fn power_ups(&mut self) -> &mut ChaCha8Rng {
+        &mut self.power_ups
+    }
+
+    /// Randomness for where enemies spawn and when they wander.
+    pub fn enemies(&mut self) -> &mut ChaCha8Rng {
+        &mut self.enemies
+    }
+
+    /// Randomness for rolling the run's objectives.
+    pub fn objectives(&mut self) -> &mut ChaCha8Rng {
+        &mut self.objectives
+    }
+
+    /// Randomness for when boss food turns up and where it goes.
+    pub fn boss(&mut self) -> &mut ChaCha8Rng {
+        &mut self.boss
+    }
+
+    /// Randomness for which world event comes next, when, and where it
+    /// puts things.
+    pub fn world_events(&mut self) -> &mut ChaCha8Rng {
+        &mut self.world_events
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::seeded(rand::random())
+    }
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(seed_run));
+    }
+}
+
+pub fn seed_run(
+    mut rng: ResMut<GameRng>,
+    rules: Res<GameRules>,
+    cli: Res<CliArgs>,
+    settings: Res<Settings>,
+    playback: Res<Playback>,
+    ghost: Res<Ghost>,
+) {
+    let seed = if let Some(seed) = playback.seed() {
+        seed
+    } else if rules.mode == GameMode::Daily {
+        daily::todays_seed()
+    } else {
+        cli.seed
+            .or(settings.seed)
+            .or_else(|| ghost.seed())
+            .unwrap_or_else(rand::random)
+    };
+    *rng = GameRng::seeded(seed);
+}
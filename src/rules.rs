@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tick::BASE_TICK_INTERVAL;
+use crate::Direction;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Play until the last life is lost.
+    Classic,
+    /// Score as much as possible before the clock runs out.
+    Timed,
+    /// The board closes in from the edges every so often.
+    Survival,
+    /// The board grows as the snake gets longer, see `expanding`.
+    Expanding,
+    /// Numbered food has to be eaten in order, see `sequence`.
+    Sequence,
+    /// Classic rules where the look of the board changes as the score goes
+    /// up, see `biomes`.
+    Endless,
+    /// Nothing ends the run: edges wrap and the snake stops at walls and at
+    /// its own body.
+    Zen,
+    /// Classic rules where crashing stops the snake instead of costing a
+    /// life, and the last seconds can be rewound, see `rewind`.
+    Casual,
+    /// Two players on one keyboard. The first to die loses.
+    Versus,
+    /// Two players on one keyboard sharing a score. The run ends when either
+    /// dies.
+    Coop,
+    /// Classic rules on a board that is the same for everyone on the same
+    /// day, see `daily`.
+    Daily,
+    /// The bundled levels in order, each cleared by reaching a length, see
+    /// `campaign`.
+    Campaign,
+}
+
+impl GameMode {
+    /// Every mode, in menu order.
+    pub const ALL: [GameMode; 12] = [
+        GameMode::Classic,
+        GameMode::Timed,
+        GameMode::Survival,
+        GameMode::Expanding,
+        GameMode::Sequence,
+        GameMode::Endless,
+        GameMode::Zen,
+        GameMode::Casual,
+        GameMode::Versus,
+        GameMode::Coop,
+        GameMode::Daily,
+        GameMode::Campaign,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Timed => "Timed challenge",
+            GameMode::Survival => "Shrinking arena",
+            GameMode::Expanding => "Expanding board",
+            GameMode::Sequence => "Number sequence",
+            GameMode::Endless => "Endless biomes",
+            GameMode::Zen => "Zen",
+            GameMode::Casual => "Casual (rewind)",
+            GameMode::Versus => "Two player versus",
+            GameMode::Coop => "Two player co-op",
+            GameMode::Daily => "Daily challenge",
+            GameMode::Campaign => "Campaign",
+        }
+    }
+
+    /// The mode after this one in the menu.
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Classic => GameMode::Timed,
+            GameMode::Timed => GameMode::Survival,
+            GameMode::Survival => GameMode::Expanding,
+            GameMode::Expanding => GameMode::Sequence,
+            GameMode::Sequence => GameMode::Endless,
+            GameMode::Endless => GameMode::Zen,
+            GameMode::Zen => GameMode::Casual,
+            GameMode::Casual => GameMode::Versus,
+            GameMode::Versus => GameMode::Coop,
+            GameMode::Coop => GameMode::Daily,
+            GameMode::Daily => GameMode::Campaign,
+            GameMode::Campaign => GameMode::Classic,
+        }
+    }
+
+    /// Whether a second player joins with their own snake.
+    pub fn two_player(self) -> bool {
+        matches!(self, GameMode::Versus | GameMode::Coop)
+    }
+}
+
+/// Share of the free cells the daily challenge covers with obstacles.
+const DAILY_OBSTACLE_DENSITY: f32 = 0.1;
+
+/// Steering turned around as a challenge, for a score multiplier.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Mirror {
+    #[default]
+    Off,
+    /// Left turns go right and right turns go left.
+    LeftRight,
+    /// Up and down are swapped as well.
+    Both,
+}
+
+impl Mirror {
+    pub fn label(self) -> &'static str {
+        match self {
+            Mirror::Off => "Off",
+            Mirror::LeftRight => "Left and right (x2 score)",
+            Mirror::Both => "All directions (x3 score)",
+        }
+    }
+
+    /// The setting after this one in the menu.
+    pub fn next(self) -> Self {
+        match self {
+            Mirror::Off => Mirror::LeftRight,
+            Mirror::LeftRight => Mirror::Both,
+            Mirror::Both => Mirror::Off,
+        }
+    }
+
+    /// The way a snake turns when its player asks for `direction`.
+    pub fn apply(self, direction: Direction) -> Direction {
+        match (self, direction) {
+            (Mirror::Off, _) => direction,
+            (_, Direction::Left | Direction::Right) => direction.opposite(),
+            (Mirror::LeftRight, _) => direction,
+            (Mirror::Both, _) => direction.opposite(),
+        }
+    }
+
+    /// Factor applied to every point scored.
+    pub fn score_multiplier(self) -> u32 {
+        match self {
+            Mirror::Off => 1,
+            Mirror::LeftRight => 2,
+            Mirror::Both => 3,
+        }
+    }
+}
+
+/// Presets for the rules that set how hard a run is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    /// Slow and steady like the old phones, drawn in their green, see
+    /// `handheld`.
+    Handheld,
+}
+
+impl Difficulty {
+    const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Handheld,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Handheld => "Classic handheld",
+        }
+    }
+
+    /// The difficulty after this one in the menu.
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Handheld,
+            Difficulty::Handheld => Difficulty::Easy,
+        }
+    }
+
+    fn tick_interval(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.25,
+            Difficulty::Normal => BASE_TICK_INTERVAL,
+            Difficulty::Hard => 0.14,
+            Difficulty::Handheld => 0.3,
+        }
+    }
+
+    fn food_respawn_delay(self) -> f32 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal | Difficulty::Handheld => 0.0,
+            Difficulty::Hard => 1.0,
+        }
+    }
+
+    fn obstacle_density(self) -> f32 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal | Difficulty::Handheld => 0.0,
+            Difficulty::Hard => 0.1,
+        }
+    }
+
+    fn wrap_edges(self) -> bool {
+        self == Difficulty::Easy
+    }
+
+    /// The handheld keeps the same speed all run.
+    fn speed_up_every(self) -> u32 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal | Difficulty::Hard => 5,
+            Difficulty::Handheld => 0,
+        }
+    }
+}
+
+/// Gameplay rules chosen on the menu before a run starts.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRules {
+    pub mode: GameMode,
+    /// Seconds between movement steps before any speed-ups.
+    pub tick_interval: f32,
+    /// Seconds before eaten food is replaced.
+    pub food_respawn_delay: f32,
+    /// Leaving the board re-enters it on the opposite side instead of
+    /// ending the run.
+    pub wrap_edges: bool,
+    /// Share of the free cells covered by randomly generated obstacles at the
+    /// start of a run, `0.0` to play the level as designed.
+    pub obstacle_density: f32,
+    /// The snake starves, losing its tail every `hunger_interval` ticks
+    /// without food.
+    pub hunger: bool,
+    pub hunger_interval: u32,
+    /// Lives per run. The snake respawns until they run out.
+    pub lives: u32,
+    /// The snake speeds up by `speed_step` seconds per tick every
+    /// `speed_up_every` pieces of food, down to `min_tick_interval`. `0`
+    /// keeps the speed constant.
+    pub speed_up_every: u32,
+    pub speed_step: f32,
+    pub min_tick_interval: f32,
+    /// Computer-controlled snakes on the board at once.
+    pub enemies: u32,
+    /// Co-op snakes crash into each other. When off they pass through.
+    pub friendly_collisions: bool,
+    /// Players move slower while their head is on mud or water, by the
+    /// terrain's cost.
+    pub terrain_slows: bool,
+    /// Only the cells around the players' heads can be seen, see `fog`.
+    #[serde(default)]
+    pub fog: bool,
+    /// Player turns are mirrored, see `Mirror`.
+    #[serde(default)]
+    pub mirror: Mirror,
+    /// Only the front of the players' snakes is drawn, see `invisible`.
+    #[serde(default)]
+    pub invisible_tail: bool,
+    /// Players leave a trail behind them that never goes away, see
+    /// `lightcycle`.
+    #[serde(default)]
+    pub light_cycle: bool,
+    /// Random events shake up the board every so often, see
+    /// `world_events`.
+    #[serde(default)]
+    pub world_events: bool,
+}
+
+impl GameRules {
+    pub fn with_difficulty(difficulty: Difficulty) -> Self {
+        let mut rules = Self {
+            mode: GameMode::Classic,
+            tick_interval: BASE_TICK_INTERVAL,
+            food_respawn_delay: 0.0,
+            wrap_edges: false,
+            obstacle_density: 0.0,
+            hunger: false,
+            hunger_interval: 40,
+            lives: 3,
+            speed_up_every: 0,
+            speed_step: 0.01,
+            min_tick_interval: 0.08,
+            enemies: 0,
+            friendly_collisions: false,
+            terrain_slows: true,
+            fog: false,
+            mirror: Mirror::Off,
+            invisible_tail: false,
+            light_cycle: false,
+            world_events: false,
+        };
+        rules.apply_difficulty(difficulty);
+        rules
+    }
+
+    /// The daily challenge's rules, ignoring whatever was picked on the menu
+    /// so every player's run is comparable.
+    pub fn daily() -> Self {
+        Self {
+            mode: GameMode::Daily,
+            obstacle_density: DAILY_OBSTACLE_DENSITY,
+            ..Self::with_difficulty(Difficulty::Normal)
+        }
+    }
+
+    /// Overwrites the rules covered by `difficulty`'s preset.
+    pub fn apply_difficulty(&mut self, difficulty: Difficulty) {
+        self.tick_interval = difficulty.tick_interval();
+        self.food_respawn_delay = difficulty.food_respawn_delay();
+        self.obstacle_density = difficulty.obstacle_density();
+        self.wrap_edges = difficulty.wrap_edges();
+        self.speed_up_every = difficulty.speed_up_every();
+    }
+
+    /// The preset the current rules match, if any. Tweaking a single option
+    /// afterwards makes them custom.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        Difficulty::ALL.into_iter().find(|difficulty| {
+            self.tick_interval == difficulty.tick_interval()
+                && self.food_respawn_delay == difficulty.food_respawn_delay()
+                && self.obstacle_density == difficulty.obstacle_density()
+                && self.wrap_edges == difficulty.wrap_edges()
+                && self.speed_up_every == difficulty.speed_up_every()
+        })
+    }
+}
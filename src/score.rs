@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+
+use crate::powerups::ActiveEffects;
+use crate::rules::GameRules;
+use crate::sprint::Sprint;
+use crate::{snake_growth, GrowthEvent, Player};
+
+/// Points earned during the current run.
+#[derive(Default)]
+pub struct Score(pub u32);
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            // Growth is scored on the tick it happened, while it's still
+            // known whether that tick was sprinted.
+            .add_system(score_update.after(snake_growth));
+    }
+}
+
+fn score_update(
+    mut score: ResMut<Score>,
+    rules: Res<GameRules>,
+    effects: Res<ActiveEffects>,
+    mut sprint: ResMut<Sprint>,
+    mut growth_reader: EventReader<GrowthEvent>,
+    players: Query<(), With<Player>>,
+) {
+    // Other snakes eating doesn't earn the player anything.
+    for growth in growth_reader
+        .iter()
+        .filter(|g| players.get(g.snake).is_ok())
+    {
+        score.0 += sprint
+            .halve(growth.value * effects.score_multiplier() * rules.mirror.score_multiplier());
+    }
+}
@@ -0,0 +1,145 @@
+//! The number sequence mode. Food comes in numbered sets that have to be
+//! eaten in order, each set one longer than the last. Eating a number out of
+//! turn costs a life, see `snake_eating`.
+
+use bevy::prelude::*;
+
+use crate::food::{free_cells, random_free_cell, spawn_food, FoodKind, Occupying};
+use crate::rng::GameRng;
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+use crate::tick::MovementTick;
+use crate::ui;
+use crate::{cell_center, snake_eating, Grid, Position};
+
+const FIRST_SET: u32 = 3;
+const LONGEST_SET: u32 = 9;
+const LABEL_COLOR: Color = Color::BLACK;
+/// Above the food, below the fog.
+const LABEL_Z: f32 = 0.5;
+/// Labels are laid out at this size and then scaled to the cells.
+const LABEL_FONT_SIZE: f32 = 32.0;
+/// Height of a label as a share of a cell.
+const LABEL_HEIGHT: f32 = 0.7;
+
+/// Where a piece of food comes in its set, from 1.
+#[derive(Component)]
+pub struct FoodNumber(pub u32);
+
+/// The number drawn over a piece of numbered food. It doesn't get a
+/// `Position` of its own, so it isn't mistaken for something on the cell.
+#[derive(Component)]
+struct FoodLabel(Entity);
+
+/// The number to eat next and how many the current set has. Once the last
+/// one is eaten a longer set is dealt.
+pub struct Sequence {
+    pub next: u32,
+    length: u32,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self { next: 1, length: 0 }
+    }
+}
+
+impl Sequence {
+    fn finished(&self) -> bool {
+        self.next > self.length
+    }
+}
+
+pub struct SequencePlugin;
+
+impl Plugin for SequencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sequence>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_sequence))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(deal_set.after(snake_eating)),
+            )
+            .add_system(place_labels);
+    }
+}
+
+fn reset_sequence(mut sequence: ResMut<Sequence>) {
+    *sequence = Sequence::default();
+}
+
+fn deal_set(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    mut sequence: ResMut<Sequence>,
+    mut rng: ResMut<GameRng>,
+    occupied: Query<&Position, Occupying>,
+) {
+    if rules.mode != GameMode::Sequence || !sequence.finished() {
+        return;
+    }
+    let length = if sequence.length == 0 {
+        FIRST_SET
+    } else {
+        (sequence.length + 1).min(LONGEST_SET)
+    };
+    *sequence = Sequence { next: 1, length };
+    let mut free = free_cells(*grid, occupied.iter());
+    for number in 1..=length {
+        let position = match random_free_cell(&mut rng.next_food(), *grid, &free) {
+            Some(position) => position,
+            None => break,
+        };
+        free.retain(|pos| *pos != position);
+        let food = spawn_food(&mut commands, position, FoodKind::Normal);
+        commands.entity(food).insert(FoodNumber(number));
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    number.to_string(),
+                    TextStyle {
+                        color: LABEL_COLOR,
+                        ..ui::text_style(&asset_server, LABEL_FONT_SIZE)
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, LABEL_Z),
+                ..default()
+            })
+            .insert(FoodLabel(food));
+    }
+}
+
+/// Keeps each label over its food, and drops it once the food is gone.
+fn place_labels(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    food: Query<&Position, With<FoodNumber>>,
+    mut labels: Query<(Entity, &FoodLabel, &mut Transform)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let scale = grid.cell_size(window) * LABEL_HEIGHT / LABEL_FONT_SIZE;
+    for (entt, FoodLabel(food_entt), mut transform) in labels.iter_mut() {
+        let pos = match food.get(*food_entt) {
+            Ok(pos) => pos,
+            Err(_) => {
+                commands.entity(entt).despawn();
+                continue;
+            }
+        };
+        let center = cell_center(window, *grid, *pos);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
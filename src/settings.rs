@@ -0,0 +1,107 @@
+//! Player preferences that persist between sessions.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::handheld::Palette;
+use crate::input_map::InputMap;
+use crate::persistence;
+use crate::rules::{Difficulty, GameRules};
+use crate::skins::Skin;
+use crate::switch_access::SwitchAccess;
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub difficulty: Difficulty,
+    /// Edges wrapping or not, as last picked over the difficulty's preset.
+    /// Picking a difficulty clears it.
+    pub wrap_edges: Option<bool>,
+    /// Board size last picked on the menu, `None` for the level's own.
+    /// `--board` and `--level` take precedence.
+    pub board: Option<(u32, u32)>,
+    /// How fast the game runs, scaling the time between movement ticks.
+    pub game_speed: f32,
+    /// Sound effect volume, from `0.0` for silence to `1.0`.
+    pub volume: f32,
+    pub palette: Palette,
+    /// Name of the color theme, see `theme`.
+    pub theme: String,
+    /// Player one's skin, see `skins`. Only worn once it's unlocked.
+    pub skin: Skin,
+    /// Shake the screen on crashes and mega food.
+    pub screen_shake: bool,
+    /// Draw in high contrast, see `high_contrast`.
+    pub high_contrast: bool,
+    /// Name shown next to submitted scores.
+    pub player_name: String,
+    /// Online leaderboard scores are sent to and fetched from, `None` to keep
+    /// scores offline. Only used with the `online-leaderboard` feature.
+    pub leaderboard_url: Option<String>,
+    /// Seed for every run instead of a random one. `--seed` takes precedence.
+    pub seed: Option<u64>,
+    /// Race a ghost of the best run in the mode when the level and rules
+    /// match it.
+    pub ghost: bool,
+    /// Steer player one's snake toward the mouse cursor.
+    pub mouse_steering: bool,
+    pub switch_access: SwitchAccess,
+    /// Seconds the highlight is left alone before the snake turns, with
+    /// `SwitchAccess::Dwell`.
+    pub switch_dwell: f32,
+    /// Set once the tutorial has been finished or skipped, so it doesn't run
+    /// again.
+    pub tutorial_done: bool,
+    /// Keys for everything done in a run, see `input_map`.
+    pub controls: InputMap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::Normal,
+            wrap_edges: None,
+            board: None,
+            game_speed: 1.0,
+            volume: 0.7,
+            palette: Palette::FullColor,
+            theme: "Classic".to_string(),
+            skin: Skin::Plain,
+            screen_shake: true,
+            high_contrast: false,
+            player_name: "Player".to_string(),
+            leaderboard_url: None,
+            seed: None,
+            ghost: true,
+            mouse_steering: false,
+            switch_access: SwitchAccess::Off,
+            switch_dwell: 1.5,
+            tutorial_done: false,
+            controls: InputMap::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        persistence::load(SETTINGS_FILE).unwrap_or_default()
+    }
+
+    /// The rules a session starts with: the saved difficulty's, with the
+    /// edges as last picked.
+    pub fn rules(&self) -> GameRules {
+        let mut rules = GameRules::with_difficulty(self.difficulty);
+        if let Some(wrap_edges) = self.wrap_edges {
+            rules.wrap_edges = wrap_edges;
+        }
+        rules
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = persistence::save(SETTINGS_FILE, self) {
+            warn!("Failed to save settings: {}", err);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Screen shake: the board's camera jolts when a snake dies and, more
+//! gently, when mega food is eaten. It can be turned off in the settings for
+//! players who'd rather the screen kept still.
+
+use bevy::prelude::*;
+use bevy::render::camera::Camera2d;
+
+use crate::food::FoodKind;
+use crate::settings::Settings;
+use crate::{FoodEatenEvent, GameOverEvent};
+
+/// Pixels, at the most, and seconds of a crash's shake.
+const CRASH_INTENSITY: f32 = 12.0;
+const CRASH_DURATION: f32 = 0.4;
+const MEGA_FOOD_INTENSITY: f32 = 4.0;
+const MEGA_FOOD_DURATION: f32 = 0.2;
+
+/// The shake going on, easing off to nothing as its timer runs out.
+#[derive(Default)]
+pub struct Shake {
+    intensity: f32,
+    timer: Option<Timer>,
+}
+
+impl Shake {
+    /// Shakes the screen up to `intensity` pixels off center for `seconds`,
+    /// unless it's shaking harder already.
+    pub fn start(&mut self, intensity: f32, seconds: f32) {
+        if self.timer.is_some() && self.intensity > intensity {
+            return;
+        }
+        self.intensity = intensity;
+        self.timer = Some(Timer::from_seconds(seconds, false));
+    }
+}
+
+pub struct ShakePlugin;
+
+impl Plugin for ShakePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Shake>()
+            .add_system(start_shake)
+            .add_system(shake_camera.after(start_shake));
+    }
+}
+
+fn start_shake(
+    settings: Res<Settings>,
+    mut shake: ResMut<Shake>,
+    mut game_over_reader: EventReader<GameOverEvent>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+) {
+    let crashed = game_over_reader.iter().count() > 0;
+    let mega_food = eaten_reader
+        .iter()
+        .any(|eaten| eaten.kind == FoodKind::Mega);
+    if !settings.screen_shake {
+        return;
+    }
+    if crashed {
+        shake.start(CRASH_INTENSITY, CRASH_DURATION);
+    } else if mega_food {
+        shake.start(MEGA_FOOD_INTENSITY, MEGA_FOOD_DURATION);
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    mut shake: ResMut<Shake>,
+    // The UI's camera stays put.
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    let intensity = shake.intensity;
+    let timer = match shake.timer.as_mut() {
+        Some(timer) => timer,
+        None => return,
+    };
+    let offset = if timer.tick(time.delta()).finished() {
+        shake.timer = None;
+        Vec2::ZERO
+    } else {
+        let strength = intensity * (1.0 - timer.percent());
+        // Only for show, so it doesn't take from the run's randomness.
+        let jitter = || rand::random::<f32>() * 2.0 - 1.0;
+        Vec2::new(jitter(), jitter()) * strength
+    };
+    for mut transform in cameras.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
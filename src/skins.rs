@@ -0,0 +1,324 @@
+//! Cosmetic skins for player one's snake, unlocked by reaching milestones and
+//! picked on the skins screen (U on the menu). Which skins are unlocked is
+//! kept between sessions, and the demo and watched replays don't unlock any.
+//!
+//! A skin colors each segment from the snake's own color, so the stripes and
+//! the gradient go along with themes and biomes. Only the rainbow has colors
+//! of its own.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::demo::Demo;
+use crate::persistence;
+use crate::replay::Playback;
+use crate::score::Score;
+use crate::settings::Settings;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::stats::{LifetimeStats, RunStats};
+use crate::tick::MovementTick;
+use crate::ui;
+use crate::{snake_growth, Player, SnakeHead, SnakeSegments};
+
+const SKINS_FILE: &str = "skins.ron";
+/// Food eaten over every run that unlocks the stripes.
+const STRIPED_FOOD: u32 = 100;
+/// Length a snake has to reach to unlock the gradient.
+const GRADIENT_LENGTH: usize = 30;
+/// Points in a single run that unlock the rainbow.
+const RAINBOW_SCORE: u32 = 100;
+/// Segments in each stripe.
+const STRIPE_LENGTH: usize = 2;
+/// How bright the dark stripes and the tail end of the gradient are.
+const DARK: f32 = 0.5;
+/// Degrees of hue the rainbow moves on each second, and between segments.
+const RAINBOW_SPEED: f32 = 120.0;
+const RAINBOW_SPREAD: f32 = 20.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Skin {
+    /// The snake's color all over.
+    #[default]
+    Plain,
+    Striped,
+    /// Darkening from the head to the tail.
+    Gradient,
+    /// Cycling through every hue, a bit further on with each segment.
+    Rainbow,
+}
+
+impl Skin {
+    const ALL: [Skin; 4] = [Skin::Plain, Skin::Striped, Skin::Gradient, Skin::Rainbow];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Skin::Plain => "Plain",
+            Skin::Striped => "Striped",
+            Skin::Gradient => "Gradient",
+            Skin::Rainbow => "Rainbow",
+        }
+    }
+
+    /// What unlocks the skin, for the skins screen.
+    fn milestone(self) -> String {
+        match self {
+            Skin::Plain => "always unlocked".to_string(),
+            Skin::Striped => format!("eat {} food in total", STRIPED_FOOD),
+            Skin::Gradient => format!("grow to a length of {}", GRADIENT_LENGTH),
+            Skin::Rainbow => format!("score {} points in one run", RAINBOW_SCORE),
+        }
+    }
+
+    fn reached(self, stats: &LifetimeStats, score: u32) -> bool {
+        match self {
+            Skin::Plain => true,
+            Skin::Striped => stats.food_eaten >= STRIPED_FOOD,
+            Skin::Gradient => stats.longest_snake >= GRADIENT_LENGTH,
+            Skin::Rainbow => score >= RAINBOW_SCORE,
+        }
+    }
+
+    /// The color of segment `index` of a snake `length` segments long, the
+    /// head being segment 0.
+    fn segment_color(self, color: Color, index: usize, length: usize, seconds: f32) -> Color {
+        match self {
+            Skin::Plain => color,
+            Skin::Striped if (index / STRIPE_LENGTH) % 2 == 1 => darken(color, DARK),
+            Skin::Striped => color,
+            Skin::Gradient => {
+                let along = index as f32 / (length.max(2) - 1) as f32;
+                darken(color, 1.0 - (1.0 - DARK) * along)
+            }
+            Skin::Rainbow => {
+                let hue = (seconds * RAINBOW_SPEED + index as f32 * RAINBOW_SPREAD) % 360.0;
+                Color::hsl(hue, 0.9, 0.6).as_rgba()
+            }
+        }
+    }
+}
+
+fn darken(color: Color, brightness: f32) -> Color {
+    Color::rgba(
+        color.r() * brightness,
+        color.g() * brightness,
+        color.b() * brightness,
+        color.a(),
+    )
+}
+
+/// The skins unlocked so far, besides the plain one.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnlockedSkins {
+    unlocked: Vec<Skin>,
+}
+
+impl UnlockedSkins {
+    pub fn load() -> Self {
+        persistence::load(SKINS_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = persistence::save(SKINS_FILE, self) {
+            warn!("Failed to save unlocked skins: {}", err);
+        }
+    }
+
+    fn has(&self, skin: Skin) -> bool {
+        skin == Skin::Plain || self.unlocked.contains(&skin)
+    }
+}
+
+/// The skin highlighted on the skins screen.
+#[derive(Default)]
+struct Selected(usize);
+
+#[derive(Component)]
+struct SkinsScreen;
+
+#[derive(Component)]
+struct SkinList;
+
+pub struct SkinsPlugin;
+
+impl Plugin for SkinsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(UnlockedSkins::load())
+            .init_resource::<Selected>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(unlock_skins.after(snake_growth)),
+            )
+            .add_system(paint_skin)
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_input))
+            .add_system_set(SystemSet::on_enter(GameState::Skins).with_system(spawn_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::Skins)
+                    .with_system(select_input)
+                    .with_system(skin_list_update.after(select_input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Skins).with_system(despawn_screen));
+    }
+}
+
+fn unlock_skins(
+    stats: Res<LifetimeStats>,
+    score: Res<Score>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+    mut skins: ResMut<UnlockedSkins>,
+    mut run: ResMut<RunStats>,
+) {
+    if demo.is_active() || playback.is_active() {
+        return;
+    }
+    for skin in Skin::ALL {
+        if !skins.has(skin) && skin.reached(&stats, score.0) {
+            info!("Unlocked the {} skin", skin.label());
+            skins.unlocked.push(skin);
+            skins.save();
+            run.unlocked_skins.push(skin);
+        }
+    }
+}
+
+/// Colors player one's snake segment by segment for the picked skin.
+fn paint_skin(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    skins: Res<UnlockedSkins>,
+    state: Res<State<GameState>>,
+    mut painted: Local<Skin>,
+    heads: Query<(&Player, &SnakeHead, &SnakeSegments)>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    // The dying snake flashes, see `death`.
+    if *state.current() == GameState::Dying {
+        return;
+    }
+    let skin = if skins.has(settings.skin) {
+        settings.skin
+    } else {
+        Skin::Plain
+    };
+    // Plain snakes are left to whatever else colors them, once they've been
+    // put back to plain.
+    if skin == Skin::Plain && *painted == Skin::Plain {
+        return;
+    }
+    *painted = skin;
+    let seconds = time.seconds_since_startup() as f32;
+    for (_, head, segments) in heads.iter().filter(|(player, ..)| player.0 == 1) {
+        for (index, entt) in segments.iter().enumerate() {
+            let color = skin.segment_color(head.color, index, segments.len(), seconds);
+            if let Ok(mut sprite) = sprites.get_mut(*entt) {
+                if sprite.color != color {
+                    sprite.color = color;
+                }
+            }
+        }
+    }
+}
+
+fn open_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut selected: ResMut<Selected>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::U]) {
+        selected.0 = Skin::ALL
+            .iter()
+            .position(|skin| *skin == settings.skin)
+            .unwrap_or(0);
+        writer.send(GameStateEvent::OpenSkins);
+    }
+}
+
+fn select_input(
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    skins: Res<UnlockedSkins>,
+    mut selected: ResMut<Selected>,
+    mut settings: ResMut<Settings>,
+    mut writer: EventWriter<GameStateEvent>,
+) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::Up, KeyCode::W]) {
+        selected.0 = selected.0.saturating_sub(1);
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Down, KeyCode::S]) {
+        selected.0 = (selected.0 + 1).min(Skin::ALL.len() - 1);
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Return]) {
+        let skin = Skin::ALL[selected.0];
+        if skins.has(skin) {
+            settings.skin = skin;
+            settings.save();
+        }
+    } else if take_just_pressed(&mut kbd_input, [KeyCode::Escape]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn spawn_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    skins: Res<UnlockedSkins>,
+    selected: Res<Selected>,
+) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(SkinsScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line("Skins", ui::text_style(&asset_server, 64.0)));
+            parent
+                .spawn_bundle(ui::text_line(
+                    skin_list(&settings, &skins, selected.0),
+                    ui::text_style(&asset_server, 24.0),
+                ))
+                .insert(SkinList);
+            parent.spawn_bundle(ui::text_line(
+                "Up and Down to pick a skin, Enter to wear it, Esc to go back",
+                ui::text_style(&asset_server, 24.0),
+            ));
+        });
+}
+
+/// One line per skin, with the selected one marked.
+fn skin_list(settings: &Settings, skins: &UnlockedSkins, selected: usize) -> String {
+    Skin::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, skin)| {
+            let marker = if index == selected { ">" } else { " " };
+            let status = if *skin == settings.skin {
+                "wearing".to_string()
+            } else if skins.has(*skin) {
+                "unlocked".to_string()
+            } else {
+                format!("locked, {}", skin.milestone())
+            };
+            format!("{} {}: {}", marker, skin.label(), status)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn skin_list_update(
+    settings: Res<Settings>,
+    skins: Res<UnlockedSkins>,
+    selected: Res<Selected>,
+    mut texts: Query<&mut Text, With<SkinList>>,
+) {
+    if !selected.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = skin_list(&settings, &skins, selected.0);
+    }
+}
+
+fn despawn_screen(mut commands: Commands, screens: Query<Entity, With<SkinsScreen>>) {
+    for entt in screens.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
@@ -0,0 +1,257 @@
+//! Snakes drawn from pieces instead of flat squares: a rounded head facing
+//! the way it's going, straight and corner pieces for the body and a tapered
+//! tail. Each segment's piece and how it's turned come from the cells of the
+//! segments either side of it, in `SnakeSegments` order.
+//!
+//! The pieces are drawn in white when the game starts, so the sprite's color
+//! tints them like it did the squares. The head's eyes are drawn in black,
+//! which stays black whatever the tint, so it's plain which way a snake is
+//! facing.
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::{Direction, Grid, Position, Size, SnakeHead, SnakeSegments};
+
+/// Pixels along each side of a piece.
+const PIECE_PIXELS: u32 = 32;
+/// Samples along each side of a pixel, to smooth the edges.
+const SUBSAMPLES: u32 = 4;
+/// Width of the body across a cell, as the flat segments were.
+const BODY_WIDTH: f32 = 0.65;
+const HEAD_WIDTH: f32 = 0.8;
+/// Where the tail comes to a point, up from the bottom of its cell.
+const TAIL_TIP: f32 = 0.1;
+/// Where the eyes are on a head facing up, either side of the middle, and
+/// how big.
+const EYE_SPREAD: f32 = 0.17;
+const EYE_HEIGHT: f32 = 0.62;
+const EYE_RADIUS: f32 = 0.07;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Piece {
+    /// Facing up.
+    Head,
+    /// Joining the cells above and below.
+    Straight,
+    /// Joining the cells above and to the right.
+    Corner,
+    /// Joined to the cell above.
+    Tail,
+}
+
+impl Piece {
+    /// Whether the point `(x, y)` of a cell, from `(0, 0)` at the bottom left
+    /// to `(1, 1)` at the top right, is part of the piece.
+    fn covers(self, x: f32, y: f32) -> bool {
+        let half = BODY_WIDTH / 2.0;
+        let (dx, dy) = (x - 0.5, y - 0.5);
+        match self {
+            Piece::Head => (dx.abs() <= half && dy <= 0.0) || dx.hypot(dy) <= HEAD_WIDTH / 2.0,
+            Piece::Straight => dx.abs() <= half,
+            Piece::Corner => {
+                (dx.abs() <= half && dy >= 0.0)
+                    || (dy.abs() <= half && dx >= 0.0)
+                    // The outside of the bend is rounded.
+                    || dx.hypot(dy) <= half
+            }
+            Piece::Tail => {
+                let width = if dy >= 0.0 {
+                    half
+                } else {
+                    half * (y - TAIL_TIP) / (0.5 - TAIL_TIP)
+                };
+                dx.abs() <= width
+            }
+        }
+    }
+
+    /// Whether the point `(x, y)` is one of the eyes, drawn dark.
+    fn eye(self, x: f32, y: f32) -> bool {
+        self == Piece::Head
+            && [0.5 - EYE_SPREAD, 0.5 + EYE_SPREAD]
+                .iter()
+                .any(|eye_x| (x - eye_x).hypot(y - EYE_HEIGHT) <= EYE_RADIUS)
+    }
+
+    fn image(self) -> Image {
+        let mut data = Vec::with_capacity((PIECE_PIXELS * PIECE_PIXELS * 4) as usize);
+        let samples = (PIECE_PIXELS * SUBSAMPLES) as f32;
+        for row in 0..PIECE_PIXELS {
+            for column in 0..PIECE_PIXELS {
+                let (mut covered, mut dark) = (0, 0);
+                for sample in 0..SUBSAMPLES * SUBSAMPLES {
+                    let x = (column * SUBSAMPLES + sample % SUBSAMPLES) as f32 + 0.5;
+                    // Rows run top to bottom.
+                    let y = (row * SUBSAMPLES + sample / SUBSAMPLES) as f32 + 0.5;
+                    let (x, y) = (x / samples, 1.0 - y / samples);
+                    if self.covers(x, y) {
+                        covered += 1;
+                        dark += self.eye(x, y) as u32;
+                    }
+                }
+                let alpha = covered * 255 / (SUBSAMPLES * SUBSAMPLES);
+                let shade = 255 - dark * 255 / covered.max(1);
+                data.extend_from_slice(&[shade as u8, shade as u8, shade as u8, alpha as u8]);
+            }
+        }
+        Image::new(
+            Extent3d {
+                width: PIECE_PIXELS,
+                height: PIECE_PIXELS,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
+
+struct PieceImages {
+    head: Handle<Image>,
+    straight: Handle<Image>,
+    corner: Handle<Image>,
+    tail: Handle<Image>,
+}
+
+impl PieceImages {
+    fn get(&self, piece: Piece) -> &Handle<Image> {
+        match piece {
+            Piece::Head => &self.head,
+            Piece::Straight => &self.straight,
+            Piece::Corner => &self.corner,
+            Piece::Tail => &self.tail,
+        }
+    }
+}
+
+impl FromWorld for PieceImages {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self {
+            head: images.add(Piece::Head.image()),
+            straight: images.add(Piece::Straight.image()),
+            corner: images.add(Piece::Corner.image()),
+            tail: images.add(Piece::Tail.image()),
+        }
+    }
+}
+
+pub struct SnakeSpritesPlugin;
+
+impl Plugin for SnakeSpritesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PieceImages>()
+            .add_system_to_stage(CoreStage::PostUpdate, pick_pieces);
+    }
+}
+
+/// The way from `from` to the next cell `to`, also across a wrapped edge.
+/// `None` when they're the same cell, e.g. while the snake grows, or not next
+/// to each other, e.g. either side of a portal.
+fn toward(grid: Grid, from: Position, to: Position) -> Option<Direction> {
+    let unwrap = |d: i32, length: u32| {
+        if d == length as i32 - 1 {
+            -1
+        } else if d == 1 - length as i32 {
+            1
+        } else {
+            d
+        }
+    };
+    match (
+        unwrap(to.x - from.x, grid.width),
+        unwrap(to.y - from.y, grid.height),
+    ) {
+        (0, 1) => Some(Direction::Up),
+        (0, -1) => Some(Direction::Down),
+        (1, 0) => Some(Direction::Right),
+        (-1, 0) => Some(Direction::Left),
+        _ => None,
+    }
+}
+
+/// The turn that points a piece drawn facing up toward `direction`.
+fn rotation(direction: Direction) -> Quat {
+    let quarters = match direction {
+        Direction::Up => 0.0,
+        Direction::Left => 1.0,
+        Direction::Down => 2.0,
+        Direction::Right => 3.0,
+    };
+    Quat::from_rotation_z(quarters * FRAC_PI_2)
+}
+
+/// The piece for a body segment joined to the cells `ahead` and `behind` of
+/// it, and the way to turn it.
+fn body_piece(ahead: Option<Direction>, behind: Option<Direction>) -> (Piece, Direction) {
+    match (ahead, behind) {
+        (Some(ahead), Some(behind)) if ahead.clockwise() == behind => (Piece::Corner, ahead),
+        (Some(ahead), Some(behind)) if behind.clockwise() == ahead => (Piece::Corner, behind),
+        (Some(direction), _) | (None, Some(direction)) => (Piece::Straight, direction),
+        (None, None) => (Piece::Straight, Direction::Up),
+    }
+}
+
+fn pick_pieces(
+    grid: Res<Grid>,
+    images: Res<PieceImages>,
+    snakes: Query<(&SnakeHead, &SnakeSegments)>,
+    mut sprites: Query<(
+        &Position,
+        &mut Handle<Image>,
+        &mut Sprite,
+        &mut Transform,
+        &mut Size,
+    )>,
+) {
+    for (head, segments) in snakes.iter() {
+        let cells: Vec<Position> = segments
+            .iter()
+            .filter_map(|entt| sprites.get(*entt).ok().map(|(pos, ..)| *pos))
+            .collect();
+        if cells.len() != segments.len() {
+            continue;
+        }
+        // The tail follows whichever way the body last went, as a segment
+        // that's just grown sits on the tail's cell.
+        let mut last = head.direction.opposite();
+        for (i, entt) in segments.iter().enumerate() {
+            let ahead = i
+                .checked_sub(1)
+                .and_then(|j| toward(*grid, cells[i], cells[j]));
+            let behind = cells
+                .get(i + 1)
+                .and_then(|next| toward(*grid, cells[i], *next));
+            let (piece, direction) = if i == 0 {
+                (Piece::Head, head.direction)
+            } else if i == cells.len() - 1 {
+                (Piece::Tail, ahead.unwrap_or(last.opposite()))
+            } else {
+                body_piece(ahead, behind)
+            };
+            if let Some(behind) = behind {
+                last = behind;
+            }
+            let (_, mut image, mut sprite, mut transform, mut size) =
+                sprites.get_mut(*entt).unwrap();
+            if *image != *images.get(piece) {
+                *image = images.get(piece).clone();
+            }
+            if sprite.custom_size != Some(Vec2::ONE) {
+                // Sized by `Size` like the squares were.
+                sprite.custom_size = Some(Vec2::ONE);
+            }
+            if size.width != 1.0 || size.height != 1.0 {
+                *size = Size::square(1.0);
+            }
+            let rotation = rotation(direction);
+            if transform.rotation != rotation {
+                transform.rotation = rotation;
+            }
+        }
+    }
+}
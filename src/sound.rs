@@ -0,0 +1,43 @@
+//! Sound effects, played at the volume picked in the settings.
+
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+use crate::{GameOverEvent, Player};
+
+struct Sounds {
+    crash: Handle<AudioSource>,
+}
+
+impl FromWorld for Sounds {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            crash: asset_server.load("sound/thunderbolt_2718.ogg"),
+        }
+    }
+}
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sounds>().add_system(crash_sound);
+    }
+}
+
+fn crash_sound(
+    mut reader: EventReader<GameOverEvent>,
+    settings: Res<Settings>,
+    sounds: Res<Sounds>,
+    audio: Res<Audio>,
+    players: Query<(), With<Player>>,
+) {
+    let crashed = reader.iter().any(|event| players.contains(event.snake));
+    if crashed && settings.volume > 0.0 {
+        audio.play_with_settings(
+            sounds.crash.clone(),
+            PlaybackSettings::ONCE.with_volume(settings.volume),
+        );
+    }
+}
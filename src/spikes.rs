@@ -0,0 +1,137 @@
+//! Spike tiles. They cycle on the movement tick between safe, a warning that
+//! they're about to come up and deadly, with the timing set by the level. A
+//! snake whose head is on a spike while it's up dies as if it hit a wall.
+
+use bevy::prelude::*;
+
+use crate::level::{CurrentLevel, SpikeTiming};
+use crate::powerups::ActiveEffects;
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+use crate::tick::MovementTick;
+use crate::{
+    despawn_snake, game_over, snake_movement, DeathCause, GameOverEvent, Player, Position, Size,
+    SnakeHead, SnakeSegments,
+};
+
+/// Spikes are floor, under the snake and anything else on them.
+const SPIKE_Z: f32 = -0.08;
+
+#[derive(Component)]
+pub struct Spike;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SpikePhase {
+    Safe,
+    /// About to come up, safe for now.
+    Warning,
+    Deadly,
+}
+
+impl SpikePhase {
+    fn at(timing: &SpikeTiming, ticks: u32) -> Self {
+        let period = timing.safe_ticks + timing.warning_ticks + timing.deadly_ticks;
+        let tick = ticks % period.max(1);
+        if tick < timing.safe_ticks {
+            SpikePhase::Safe
+        } else if tick < timing.safe_ticks + timing.warning_ticks {
+            SpikePhase::Warning
+        } else {
+            SpikePhase::Deadly
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            SpikePhase::Safe => Color::rgb(0.35, 0.35, 0.4),
+            SpikePhase::Warning => Color::rgb(0.95, 0.6, 0.1),
+            SpikePhase::Deadly => Color::rgb(0.9, 0.9, 0.95),
+        }
+    }
+}
+
+/// Movement ticks since the run started, which the phase follows.
+#[derive(Default)]
+struct SpikeCycle {
+    ticks: u32,
+}
+
+pub fn spawn_spike(commands: &mut Commands, position: Position) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: SpikePhase::Safe.color(),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, SPIKE_Z),
+            ..default()
+        })
+        .insert(Spike)
+        .insert(position)
+        .insert(Size::square(0.8));
+}
+
+pub struct SpikePlugin;
+
+impl Plugin for SpikePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpikeCycle>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_cycle))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(advance_spikes.before(snake_movement))
+                    .with_system(spike_hits.after(snake_movement).before(game_over)),
+            );
+    }
+}
+
+fn reset_cycle(mut cycle: ResMut<SpikeCycle>, mut spikes: Query<&mut Sprite, With<Spike>>) {
+    cycle.ticks = 0;
+    for mut sprite in spikes.iter_mut() {
+        sprite.color = SpikePhase::Safe.color();
+    }
+}
+
+fn advance_spikes(
+    mut cycle: ResMut<SpikeCycle>,
+    current: Res<CurrentLevel>,
+    mut spikes: Query<&mut Sprite, With<Spike>>,
+) {
+    cycle.ticks += 1;
+    let color = SpikePhase::at(&current.level.spikes, cycle.ticks).color();
+    for mut sprite in spikes.iter_mut() {
+        sprite.color = color;
+    }
+}
+
+fn spike_hits(
+    mut commands: Commands,
+    cycle: Res<SpikeCycle>,
+    current: Res<CurrentLevel>,
+    rules: Res<GameRules>,
+    mut effects: ResMut<ActiveEffects>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+    heads: Query<(Entity, &Position, &SnakeSegments, Option<&Player>), With<SnakeHead>>,
+    spikes: Query<&Position, With<Spike>>,
+) {
+    if SpikePhase::at(&current.level.spikes, cycle.ticks) != SpikePhase::Deadly {
+        return;
+    }
+    for (snake, head_pos, segments, player) in heads.iter() {
+        if !spikes.iter().any(|pos| pos == head_pos) {
+            continue;
+        }
+        if player.is_none() {
+            despawn_snake(&mut commands, segments);
+            continue;
+        }
+        if rules.mode == GameMode::Zen || effects.consume_shield() {
+            continue;
+        }
+        game_over_writer.send(GameOverEvent {
+            snake,
+            cause: DeathCause::Spikes,
+        });
+    }
+}
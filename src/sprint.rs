@@ -0,0 +1,69 @@
+//! Sprinting. Holding the sprint key halves the time between movement ticks,
+//! and everything eaten meanwhile scores half as much. Whether a tick is
+//! sprinted is settled once, at the start of the tick, so replays can sprint
+//! on the same ticks the run did.
+
+use bevy::prelude::*;
+
+use crate::input_map::{Controls, InputMap};
+use crate::settings::Settings;
+use crate::state::GameState;
+use crate::switch_access::SwitchAccess;
+use crate::tick::MovementTick;
+use crate::{snake_movement, Player};
+
+#[derive(Default)]
+pub struct Sprint {
+    /// Whether the current tick is sprinted, which also halves the wait for
+    /// the next one.
+    pub active: bool,
+    /// Half a point left over from halving an odd number of points.
+    half_point: bool,
+}
+
+impl Sprint {
+    /// `points` as scored right now: halved while sprinting, with an odd
+    /// half carried over to the next points scored.
+    pub fn halve(&mut self, points: u32) -> u32 {
+        if !self.active {
+            return points;
+        }
+        let halves = points + self.half_point as u32;
+        self.half_point = halves % 2 == 1;
+        halves / 2
+    }
+}
+
+pub struct SprintPlugin;
+
+impl Plugin for SprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sprint>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_sprint))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(sprint_input.before(snake_movement)),
+            );
+    }
+}
+
+fn reset_sprint(mut sprint: ResMut<Sprint>) {
+    *sprint = Sprint::default();
+}
+
+fn sprint_input(
+    kbd_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    settings: Res<Settings>,
+    mut sprint: ResMut<Sprint>,
+    // Replays sprint where the recording says, see `replay`, and the demo
+    // and online games don't sprint at all.
+    players: Query<(), (With<Player>, With<Controls>)>,
+) {
+    // Switch access steers with Space.
+    if players.is_empty() || settings.switch_access != SwitchAccess::Off {
+        return;
+    }
+    sprint.active = kbd_input.any_pressed(input_map.sprint.iter().copied());
+}
@@ -0,0 +1,140 @@
+//! Top level game states and the events that move between them.
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+    Editor,
+    /// Counting down to put the snake back after losing a life. Pushed on top
+    /// of `Playing`.
+    Respawning,
+    /// Showing a snake die before respawning or the game over screen, see
+    /// `death`. Pushed on top of `Playing`.
+    Dying,
+    /// Watching and steering in an online game simulated by the host, see
+    /// `net`.
+    Remote,
+    /// Stepping through the autopilot's search one cell at a time, see
+    /// `debugger`. Pushed on top of `Playing`, or set from the menu as a
+    /// sandbox.
+    Debugger,
+    /// Lifetime statistics, see `stats`.
+    Stats,
+    /// Changing the key bindings, see `input_map`.
+    Bindings,
+    /// Asking whether to give up the run, see `quit`. Pushed on top of the
+    /// run's state.
+    ConfirmQuit,
+    /// Picking a campaign level, see `campaign`.
+    LevelSelect,
+    /// Picking a skin, see `skins`.
+    Skins,
+    /// The bonus round between campaign levels, see `bonus`.
+    BonusRound,
+    /// The online leaderboard, see `leaderboard`.
+    #[cfg(feature = "online-leaderboard")]
+    Leaderboard,
+}
+
+/// Requests a state transition. Systems send these instead of mutating
+/// `State<GameState>` directly so invalid transitions are filtered in one
+/// place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStateEvent {
+    StartRun,
+    Pause,
+    Resume,
+    EndRun,
+    LoseLife,
+    /// A player died, see `death`. It's followed by `LoseLife` or `EndRun`.
+    Die,
+    ReturnToMenu,
+    OpenEditor,
+    JoinRemote,
+    OpenDebugger,
+    OpenStats,
+    OpenBindings,
+    AskQuit,
+    OpenLevelSelect,
+    OpenSkins,
+    StartBonusRound,
+    #[cfg(feature = "online-leaderboard")]
+    OpenLeaderboard,
+}
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(GameState::Menu)
+            .add_event::<GameStateEvent>()
+            .add_system(apply_state_events);
+    }
+}
+
+/// Returns whether any of `keys` was just pressed, consuming the press so
+/// systems of a state entered later this frame don't react to it as well.
+pub fn take_just_pressed(
+    kbd_input: &mut Input<KeyCode>,
+    keys: impl IntoIterator<Item = KeyCode>,
+) -> bool {
+    let mut pressed = false;
+    for key in keys {
+        if kbd_input.just_pressed(key) {
+            kbd_input.reset(key);
+            pressed = true;
+        }
+    }
+    pressed
+}
+
+fn apply_state_events(
+    mut events: EventReader<GameStateEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for event in events.iter() {
+        let result = match (event, *state.current()) {
+            (GameStateEvent::StartRun, GameState::Playing) => state.restart(),
+            (GameStateEvent::StartRun, _) => state.replace(GameState::Playing),
+            (GameStateEvent::Pause, GameState::Playing) => state.push(GameState::Paused),
+            (
+                GameStateEvent::Resume,
+                GameState::Paused
+                | GameState::Respawning
+                | GameState::Debugger
+                | GameState::ConfirmQuit,
+            ) => state.pop(),
+            (GameStateEvent::EndRun, GameState::Playing) => state.set(GameState::GameOver),
+            (GameStateEvent::EndRun, GameState::Dying) => state.replace(GameState::GameOver),
+            (GameStateEvent::StartBonusRound, GameState::GameOver) => {
+                state.set(GameState::BonusRound)
+            }
+            (GameStateEvent::LoseLife, GameState::Playing) => state.push(GameState::Respawning),
+            (GameStateEvent::LoseLife, GameState::Dying) => state.set(GameState::Respawning),
+            (GameStateEvent::Die, GameState::Playing) => state.push(GameState::Dying),
+            (GameStateEvent::OpenDebugger, GameState::Playing) => state.push(GameState::Debugger),
+            (GameStateEvent::OpenDebugger, GameState::Menu) => state.set(GameState::Debugger),
+            (GameStateEvent::ReturnToMenu, _) => state.replace(GameState::Menu),
+            (GameStateEvent::OpenEditor, GameState::Menu) => state.set(GameState::Editor),
+            (GameStateEvent::JoinRemote, GameState::Menu) => state.set(GameState::Remote),
+            (GameStateEvent::OpenStats, GameState::Menu) => state.set(GameState::Stats),
+            (GameStateEvent::OpenBindings, GameState::Menu) => state.set(GameState::Bindings),
+            (
+                GameStateEvent::AskQuit,
+                GameState::Playing | GameState::Paused | GameState::Respawning | GameState::Dying,
+            ) => state.push(GameState::ConfirmQuit),
+            (GameStateEvent::OpenLevelSelect, GameState::Menu) => state.set(GameState::LevelSelect),
+            (GameStateEvent::OpenSkins, GameState::Menu) => state.set(GameState::Skins),
+            #[cfg(feature = "online-leaderboard")]
+            (GameStateEvent::OpenLeaderboard, GameState::Menu) => state.set(GameState::Leaderboard),
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            warn!("Ignoring {:?}: {:?}", event, err);
+        }
+    }
+}
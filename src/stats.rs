@@ -0,0 +1,229 @@
+//! Lifetime statistics, added up over every run and kept between sessions,
+//! with a screen to look at them from the menu (S). The demo and watched
+//! replays don't count. The current run's own stats are kept too, for the
+//! results screen.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::demo::Demo;
+use crate::persistence;
+use crate::replay::Playback;
+use crate::skins::Skin;
+use crate::state::{take_just_pressed, GameState, GameStateEvent};
+use crate::tick::MovementTick;
+use crate::ui;
+use crate::{snake_growth, DeathCause, FoodEatenEvent, GameOverEvent, Player, SnakeSegments};
+
+const STATS_FILE: &str = "stats.ron";
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    games_played: u32,
+    pub food_eaten: u32,
+    deaths: Deaths,
+    /// Most segments a player's snake has had, head included.
+    pub longest_snake: usize,
+    /// Seconds spent in runs, pauses not included.
+    seconds_played: f64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Deaths {
+    wall: u32,
+    own_body: u32,
+    other_snake: u32,
+    shrank: u32,
+    spikes: u32,
+    wrong_number: u32,
+}
+
+impl LifetimeStats {
+    pub fn load() -> Self {
+        persistence::load(STATS_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = persistence::save(STATS_FILE, self) {
+            warn!("Failed to save stats: {}", err);
+        }
+    }
+
+    fn record_death(&mut self, cause: DeathCause) {
+        let count = match cause {
+            DeathCause::Wall => &mut self.deaths.wall,
+            DeathCause::OwnBody => &mut self.deaths.own_body,
+            DeathCause::OtherSnake => &mut self.deaths.other_snake,
+            DeathCause::Shrank => &mut self.deaths.shrank,
+            DeathCause::Spikes => &mut self.deaths.spikes,
+            DeathCause::WrongNumber => &mut self.deaths.wrong_number,
+        };
+        *count += 1;
+    }
+
+    fn summary(&self) -> String {
+        let minutes = (self.seconds_played / 60.0) as u64;
+        format!(
+            "Games played: {}\nTime played: {}h {:02}m\nFood eaten: {}\nLongest snake: {}\n\n\
+             Deaths\nWalls: {}\nOwn body: {}\nOther snakes: {}\nShrank away: {}\nSpikes: {}\n\
+             Wrong number: {}",
+            self.games_played,
+            minutes / 60,
+            minutes % 60,
+            self.food_eaten,
+            self.longest_snake,
+            self.deaths.wall,
+            self.deaths.own_body,
+            self.deaths.other_snake,
+            self.deaths.shrank,
+            self.deaths.spikes,
+            self.deaths.wrong_number,
+        )
+    }
+}
+
+/// The current run's stats, shown when it ends.
+#[derive(Default)]
+pub struct RunStats {
+    /// Seconds spent in the run, pauses not included.
+    pub seconds: f64,
+    pub food_eaten: u32,
+    /// What killed player one last, `None` while they haven't died.
+    pub death: Option<DeathCause>,
+    /// Skins unlocked during the run, see `skins`.
+    pub unlocked_skins: Vec<Skin>,
+}
+
+#[derive(Component)]
+struct StatsScreen;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LifetimeStats::load())
+            .init_resource::<RunStats>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(count_run))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(count_time))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(count_tick.after(snake_growth)),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(save_stats))
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(save_stats))
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_input))
+            .add_system_set(SystemSet::on_enter(GameState::Stats).with_system(spawn_screen))
+            .add_system_set(SystemSet::on_update(GameState::Stats).with_system(close_input))
+            .add_system_set(SystemSet::on_exit(GameState::Stats).with_system(despawn_screen));
+    }
+}
+
+fn counts(demo: &Demo, playback: &Playback) -> bool {
+    !demo.is_active() && !playback.is_active()
+}
+
+fn count_run(
+    mut stats: ResMut<LifetimeStats>,
+    mut run: ResMut<RunStats>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+) {
+    *run = RunStats::default();
+    if counts(&demo, &playback) {
+        stats.games_played += 1;
+    }
+}
+
+fn count_time(
+    time: Res<Time>,
+    mut stats: ResMut<LifetimeStats>,
+    mut run: ResMut<RunStats>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+) {
+    run.seconds += time.delta_seconds_f64();
+    if counts(&demo, &playback) {
+        stats.seconds_played += time.delta_seconds_f64();
+    }
+}
+
+fn count_tick(
+    mut stats: ResMut<LifetimeStats>,
+    mut run: ResMut<RunStats>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+    mut game_over_reader: EventReader<GameOverEvent>,
+    players: Query<(&Player, &SnakeSegments)>,
+) {
+    let eaten = eaten_reader.iter().count() as u32;
+    run.food_eaten += eaten;
+    let deaths: Vec<_> = game_over_reader.iter().collect();
+    if let Some(death) = deaths.iter().find(|death| {
+        players
+            .get(death.snake)
+            .is_ok_and(|(player, _)| player.0 == 1)
+    }) {
+        run.death = Some(death.cause);
+    }
+    if !counts(&demo, &playback) {
+        return;
+    }
+    stats.food_eaten += eaten;
+    for game_over in deaths {
+        stats.record_death(game_over.cause);
+    }
+    let longest = players
+        .iter()
+        .map(|(_, segments)| segments.len())
+        .max()
+        .unwrap_or(0);
+    if longest > stats.longest_snake {
+        stats.longest_snake = longest;
+    }
+}
+
+fn save_stats(stats: Res<LifetimeStats>) {
+    stats.save();
+}
+
+fn open_input(mut kbd_input: ResMut<Input<KeyCode>>, mut writer: EventWriter<GameStateEvent>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::S]) {
+        writer.send(GameStateEvent::OpenStats);
+    }
+}
+
+fn close_input(mut kbd_input: ResMut<Input<KeyCode>>, mut writer: EventWriter<GameStateEvent>) {
+    if take_just_pressed(&mut kbd_input, [KeyCode::Escape, KeyCode::Return]) {
+        writer.send(GameStateEvent::ReturnToMenu);
+    }
+}
+
+fn spawn_screen(mut commands: Commands, asset_server: Res<AssetServer>, stats: Res<LifetimeStats>) {
+    commands
+        .spawn_bundle(ui::overlay_node())
+        .insert(StatsScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(ui::text_line(
+                "Lifetime stats",
+                ui::text_style(&asset_server, 64.0),
+            ));
+            parent.spawn_bundle(ui::text_line(
+                stats.summary(),
+                ui::text_style(&asset_server, 24.0),
+            ));
+            parent.spawn_bundle(ui::text_line(
+                "Press Esc to go back",
+                ui::text_style(&asset_server, 24.0),
+            ));
+        });
+}
+
+fn despawn_screen(mut commands: Commands, screens: Query<Entity, With<StatsScreen>>) {
+    for entt in screens.iter() {
+        commands.entity(entt).despawn_recursive();
+    }
+}
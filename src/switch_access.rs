@@ -0,0 +1,195 @@
+//! Switch access, for playing with one or two switches instead of a
+//! keyboard. A highlight next to player one's head marks a way to turn: one
+//! switch moves it on to the next way round, clockwise, and the other takes
+//! the turn. With a single switch the turn is taken once the highlight has
+//! stayed put for a moment instead.
+//!
+//! The highlight shows the way the snake will actually go, so mirrored
+//! controls don't apply to it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input_map::{Controls, InputMap};
+use crate::settings::Settings;
+use crate::state::{take_just_pressed, GameState};
+use crate::terrain::Terrain;
+use crate::{
+    cell_center, on_ice, snake_movement_input, Direction, Grid, Player, Position, Size, SnakeHead,
+};
+
+const HIGHLIGHT_COLOR: Color = Color::rgba(1.0, 1.0, 0.3, 0.5);
+/// Over the floor and anything lying on it.
+const HIGHLIGHT_Z: f32 = 0.5;
+
+/// How switches steer, picked on the menu (A).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SwitchAccess {
+    #[default]
+    Off,
+    /// One switch moves the highlight, the other turns.
+    TwoSwitches,
+    /// The one switch moves the highlight, and the snake turns after it's
+    /// been left alone for `Settings::switch_dwell` seconds.
+    Dwell,
+}
+
+impl SwitchAccess {
+    pub fn label(self) -> &'static str {
+        match self {
+            SwitchAccess::Off => "Off",
+            SwitchAccess::TwoSwitches => "Two switches",
+            SwitchAccess::Dwell => "One switch, turns after a pause",
+        }
+    }
+
+    /// The setting after this one in the menu.
+    pub fn next(self) -> Self {
+        match self {
+            SwitchAccess::Off => SwitchAccess::TwoSwitches,
+            SwitchAccess::TwoSwitches => SwitchAccess::Dwell,
+            SwitchAccess::Dwell => SwitchAccess::Off,
+        }
+    }
+}
+
+/// The way the highlight points, and how long until it turns the snake in
+/// dwell mode.
+struct Highlight {
+    direction: Direction,
+    /// Started by moving the highlight, so leaving it alone only turns once.
+    dwell: Option<Timer>,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            direction: Direction::Up,
+            dwell: None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct HighlightCell;
+
+pub struct SwitchAccessPlugin;
+
+impl Plugin for SwitchAccessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Highlight>()
+            .add_startup_system(spawn_highlight)
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_highlight))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(switch_steering.before(snake_movement_input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::BonusRound)
+                    .with_system(switch_steering.before(snake_movement_input)),
+            )
+            .add_system(place_highlight.after(switch_steering));
+    }
+}
+
+/// The highlight moved on from `from` to a way the snake heading `heading`
+/// can turn. Going straight on or back isn't a turn.
+fn next_turn(from: Direction, heading: Direction) -> Direction {
+    let mut direction = from.clockwise();
+    while direction == heading || direction == heading.opposite() {
+        direction = direction.clockwise();
+    }
+    direction
+}
+
+fn spawn_highlight(mut commands: Commands) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: HIGHLIGHT_COLOR,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, HIGHLIGHT_Z),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(HighlightCell)
+        .insert(Size::square(0.9));
+}
+
+fn reset_highlight(mut highlight: ResMut<Highlight>) {
+    *highlight = Highlight::default();
+}
+
+fn switch_steering(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    input_map: Res<InputMap>,
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut highlight: ResMut<Highlight>,
+    mut heads: Query<(&Player, &mut SnakeHead, &Position), With<Controls>>,
+    terrain: Query<(&Position, &Terrain)>,
+) {
+    if settings.switch_access == SwitchAccess::Off {
+        return;
+    }
+    let (mut head, pos) = match heads.iter_mut().find(|(player, ..)| player.0 == 1) {
+        Some((_, head, pos)) => (head, pos),
+        None => return,
+    };
+    // After a turn the highlight points straight on, so it moves to the
+    // next turn by itself.
+    let heading = head.heading();
+    if highlight.direction == heading || highlight.direction == heading.opposite() {
+        highlight.direction = next_turn(highlight.direction, heading);
+    }
+    if take_just_pressed(&mut kbd_input, input_map.switch_next.iter().copied()) {
+        highlight.direction = next_turn(highlight.direction, heading);
+        if settings.switch_access == SwitchAccess::Dwell {
+            highlight.dwell = Some(Timer::from_seconds(settings.switch_dwell, false));
+        }
+    }
+    let dwelled = highlight
+        .dwell
+        .as_mut()
+        .is_some_and(|timer| timer.tick(time.delta()).just_finished());
+    let turn = match settings.switch_access {
+        SwitchAccess::TwoSwitches => {
+            take_just_pressed(&mut kbd_input, input_map.switch_turn.iter().copied())
+        }
+        _ => dwelled,
+    };
+    if turn && !on_ice(&terrain, *pos) {
+        highlight.dwell = None;
+        head.queue_turn(highlight.direction);
+    }
+}
+
+fn place_highlight(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    highlight: Res<Highlight>,
+    heads: Query<(&Player, &Position), (With<SnakeHead>, With<Controls>)>,
+    mut cells: Query<(&mut Transform, &mut Visibility), With<HighlightCell>>,
+) {
+    let in_run = matches!(
+        state.current(),
+        GameState::Playing | GameState::Paused | GameState::BonusRound
+    );
+    let head = heads
+        .iter()
+        .find(|(player, _)| player.0 == 1)
+        .map(|(_, pos)| *pos)
+        .filter(|_| in_run && settings.switch_access != SwitchAccess::Off);
+    let window = windows.get_primary().unwrap();
+    for (mut transform, mut visibility) in cells.iter_mut() {
+        visibility.is_visible = head.is_some();
+        if let Some(head) = head {
+            let center = cell_center(window, *grid, head.step(highlight.direction));
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+        }
+    }
+}
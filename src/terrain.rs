@@ -0,0 +1,83 @@
+//! Special ground. Mud and water tiles can be crossed but cost more than plain
+//! floor: searches count the cost, and with `GameRules::terrain_slows` the
+//! snake itself slows down while its head is on them. Ice doesn't slow the
+//! snake but won't let it turn until it's off again.
+
+use bevy::prelude::*;
+
+use crate::{Position, Size};
+
+/// Terrain is floor and sits under portals and anything else on it.
+const TERRAIN_Z: f32 = -0.08;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Terrain {
+    Mud,
+    Water,
+    Ice,
+}
+
+impl Terrain {
+    pub const ALL: [Terrain; 3] = [Terrain::Mud, Terrain::Water, Terrain::Ice];
+
+    /// The terrain a level layout character stands for, if any.
+    pub fn from_tile(tile: char) -> Option<Self> {
+        Terrain::ALL
+            .into_iter()
+            .find(|terrain| terrain.tile() == tile)
+    }
+
+    pub fn tile(self) -> char {
+        match self {
+            Terrain::Mud => ':',
+            Terrain::Water => '~',
+            Terrain::Ice => '=',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Terrain::Mud => "mud",
+            Terrain::Water => "water",
+            Terrain::Ice => "ice",
+        }
+    }
+
+    /// What it costs to step onto the tile, where plain floor costs 1. Also
+    /// how many times slower the snake moves on it.
+    pub fn cost(self) -> i32 {
+        match self {
+            Terrain::Mud => 2,
+            Terrain::Water => 4,
+            Terrain::Ice => 1,
+        }
+    }
+
+    /// Whether a snake with its head on the tile keeps going straight.
+    pub fn slippery(self) -> bool {
+        self == Terrain::Ice
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Terrain::Mud => Color::rgb(0.4, 0.28, 0.15),
+            Terrain::Water => Color::rgb(0.15, 0.3, 0.55),
+            Terrain::Ice => Color::rgb(0.7, 0.85, 0.95),
+        }
+    }
+}
+
+pub fn spawn_terrain(commands: &mut Commands, position: Position, terrain: Terrain) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: terrain.color(),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, TERRAIN_Z),
+            ..default()
+        })
+        .insert(terrain)
+        .insert(position)
+        .insert(Size::square(1.0));
+}
@@ -0,0 +1,134 @@
+//! Color themes, picked in the settings: the background, player one's
+//! snake, plain food, the walls and the text. Themes are read from
+//! `assets/themes.ron`, or from a `themes.ron` in the config directory if
+//! there is one. Switching theme recolors everything already on screen.
+//!
+//! The endless mode's biomes color the board themselves, so the theme only
+//! colors the text while one is showing.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::biomes::{Background, Biomes, CurrentBiome};
+use crate::color::{recolor_text, rgb};
+use crate::food::{Food, FoodKind};
+use crate::persistence;
+use crate::settings::Settings;
+use crate::ui;
+use crate::walls::Wall;
+use crate::{Player, SnakeHead, SnakeSegments};
+
+const THEMES_FILE: &str = "themes.ron";
+const BUNDLED_THEMES: &str = include_str!("../assets/themes.ron");
+
+#[derive(Deserialize)]
+pub struct Theme {
+    pub name: String,
+    background: (f32, f32, f32),
+    snake: (f32, f32, f32),
+    food: (f32, f32, f32),
+    walls: (f32, f32, f32),
+    text: (f32, f32, f32),
+}
+
+/// Every theme, in the order the settings cycle through them.
+pub struct Themes(Vec<Theme>);
+
+impl Themes {
+    fn load() -> Self {
+        Self(persistence::load_list(
+            THEMES_FILE,
+            BUNDLED_THEMES,
+            "themes",
+        ))
+    }
+
+    /// The theme called `name`, or the first one if there's none by that
+    /// name.
+    pub fn get(&self, name: &str) -> &Theme {
+        self.0
+            .iter()
+            .find(|theme| theme.name == name)
+            .unwrap_or(&self.0[0])
+    }
+
+    /// The theme after the one called `name` in the settings.
+    pub fn next(&self, name: &str) -> &Theme {
+        let index = self.0.iter().position(|theme| theme.name == name);
+        &self.0[index.map_or(0, |i| (i + 1) % self.0.len())]
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Themes::load())
+            .add_system(paint_theme)
+            .add_system(paint_text);
+    }
+}
+
+/// Colors the board for the theme. Walls and food are only painted when the
+/// theme changes or they're spawned, so the theme stays under other looks.
+fn paint_theme(
+    settings: Res<Settings>,
+    themes: Res<Themes>,
+    biomes: Res<Biomes>,
+    current_biome: Res<CurrentBiome>,
+    mut background: ResMut<Background>,
+    mut painted: Local<Option<String>>,
+    mut heads: Query<(&Player, &mut SnakeHead, &SnakeSegments)>,
+    mut segments: Query<&mut Sprite, (Without<Wall>, Without<Food>)>,
+    mut walls: Query<(ChangeTrackers<Wall>, &mut Sprite), (With<Wall>, Without<Food>)>,
+    mut food: Query<(ChangeTrackers<Food>, &Food, &mut Sprite), Without<Wall>>,
+) {
+    if current_biome.get(&biomes).is_some() {
+        // Painted again from scratch once the biomes are left.
+        *painted = None;
+        return;
+    }
+    let theme = themes.get(&settings.theme);
+    let changed = painted.as_deref() != Some(theme.name.as_str());
+    *painted = Some(theme.name.clone());
+    if background.0 != rgb(theme.background) {
+        background.0 = rgb(theme.background);
+    }
+    for (player, mut head, snake_segments) in heads.iter_mut() {
+        if player.0 == 1 {
+            head.paint(snake_segments, &mut segments, rgb(theme.snake));
+        }
+    }
+    for (tracker, mut sprite) in walls.iter_mut() {
+        if changed || tracker.is_added() {
+            sprite.color = rgb(theme.walls);
+        }
+    }
+    for (tracker, food, mut sprite) in food.iter_mut() {
+        if food.kind == FoodKind::Normal && (changed || tracker.is_added()) {
+            // Keeps the transparency of food blinking out.
+            let alpha = sprite.color.a();
+            sprite.color = rgb(theme.food);
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+/// Colors plain text for the theme, leaving text that has a color of its
+/// own alone.
+fn paint_text(
+    settings: Res<Settings>,
+    themes: Res<Themes>,
+    mut painted: Local<Option<Color>>,
+    mut texts: Query<&mut Text>,
+) {
+    let plain = rgb(themes.get(&settings.theme).text);
+    let old = painted.unwrap_or(ui::TEXT_COLOR);
+    *painted = Some(plain);
+    // Text is spawned in `ui::TEXT_COLOR` and written to all the time, so
+    // anything in that or the theme's last color is plain.
+    let stale = |color: Color| color != plain && (color == old || color == ui::TEXT_COLOR);
+    for text in texts.iter_mut() {
+        recolor_text(text, |color| stale(color).then_some(plain));
+    }
+}
@@ -0,0 +1,193 @@
+//! The movement tick that drives the snake, with a period that can change
+//! during a run: it shortens as food is eaten, power-ups scale it, sprinting
+//! halves it and mud or water under a player's head drag it out.
+//!
+//! Everything timed during a run, from power-ups wearing off to the arena
+//! closing in, counts game time in whole ticks using `MoveTimer::step`
+//! rather than the frame time. That keeps a run the same from tick to tick
+//! however fast the frames come, which replays rely on.
+
+use std::time::Duration;
+
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+
+use crate::food::FoodKind;
+use crate::framestep::FrameStep;
+use crate::powerups::{ActiveEffects, PowerUpKind};
+use crate::rules::GameRules;
+use crate::settings::Settings;
+use crate::sprint::Sprint;
+use crate::state::GameState;
+use crate::terrain::Terrain;
+use crate::{snake_eating, FoodEatenEvent, Player, Position, SnakeHead};
+
+/// Seconds between movement steps at normal difficulty.
+pub const BASE_TICK_INTERVAL: f32 = 0.2;
+const SPEED_BOOST_FACTOR: f32 = 0.5;
+const SLOW_MOTION_FACTOR: f32 = 2.0;
+const SPRINT_FACTOR: f32 = 0.5;
+
+/// Shared label so every system set that steps with the snake evaluates the
+/// same tick.
+#[derive(RunCriteriaLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MovementTick;
+
+pub struct MoveTimer {
+    timer: Timer,
+    /// Game time the last tick stood for.
+    step: Duration,
+    /// How fast ticks come compared to real time, `0.0` to stop them. Set by
+    /// replays, rewinding and the tutorial.
+    pub speed: f32,
+}
+
+impl Default for MoveTimer {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(BASE_TICK_INTERVAL, true),
+            step: Duration::ZERO,
+            speed: 1.0,
+        }
+    }
+}
+
+impl MoveTimer {
+    pub fn interval(&self) -> f32 {
+        self.timer.duration().as_secs_f32()
+    }
+
+    /// Game time that passed with the last tick, for systems on the tick to
+    /// advance their own timers by.
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// How far through the wait for the next tick it is, from `0.0` to
+    /// `1.0`.
+    pub fn progress(&self) -> f32 {
+        self.timer.percent()
+    }
+
+    fn set_interval(&mut self, seconds: f32) {
+        if (self.interval() - seconds).abs() > f32::EPSILON {
+            self.timer.set_duration(Duration::from_secs_f32(seconds));
+        }
+    }
+}
+
+/// Food eaten this run, which sets how far the snake has sped up.
+#[derive(Default, Clone)]
+pub struct FoodEaten(u32);
+
+impl FoodEaten {
+    /// Movement interval before power-up effects, shrinking as more food is
+    /// eaten.
+    pub fn base_interval(&self, rules: &GameRules) -> f32 {
+        if rules.speed_up_every == 0 {
+            return rules.tick_interval;
+        }
+        let speed_ups = (self.0 / rules.speed_up_every) as f32;
+        (rules.tick_interval - speed_ups * rules.speed_step).max(rules.min_tick_interval)
+    }
+}
+
+pub struct TickPlugin;
+
+impl Plugin for TickPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MoveTimer>()
+            .init_resource::<FoodEaten>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_move_timer))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(update_tick_rate))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(count_food_eaten.after(snake_eating)),
+            );
+    }
+}
+
+/// Run criteria for systems that step once per movement tick while a run is
+/// in progress.
+pub fn movement_tick(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    mut timer: ResMut<MoveTimer>,
+    mut frame_step: ResMut<FrameStep>,
+) -> ShouldRun {
+    if *state.current() != GameState::Playing {
+        return ShouldRun::No;
+    }
+    // Stepping by hand, the timer stands still and each tick takes as long
+    // as it would have.
+    if frame_step.enabled() {
+        if !frame_step.take_step() {
+            return ShouldRun::No;
+        }
+        timer.step = timer.timer.duration();
+        return ShouldRun::Yes;
+    }
+    // The game speed setting only changes how long a tick takes, not the
+    // game time it stands for.
+    let delta = time.delta().mul_f32(timer.speed * settings.game_speed);
+    if timer.timer.tick(delta).just_finished() {
+        // Read here, as the interval can change again before the tick's
+        // systems run.
+        timer.step = timer.timer.duration();
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn reset_move_timer(mut timer: ResMut<MoveTimer>, mut food_eaten: ResMut<FoodEaten>) {
+    *timer = MoveTimer {
+        speed: timer.speed,
+        ..default()
+    };
+    *food_eaten = FoodEaten::default();
+}
+
+fn count_food_eaten(
+    mut food_eaten: ResMut<FoodEaten>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+) {
+    food_eaten.0 += eaten_reader
+        .iter()
+        .filter(|eaten| eaten.kind != FoodKind::Poison)
+        .count() as u32;
+}
+
+fn update_tick_rate(
+    mut timer: ResMut<MoveTimer>,
+    effects: Res<ActiveEffects>,
+    rules: Res<GameRules>,
+    food_eaten: Res<FoodEaten>,
+    sprint: Res<Sprint>,
+    heads: Query<&Position, (With<SnakeHead>, With<Player>)>,
+    terrain: Query<(&Position, &Terrain)>,
+) {
+    let mut interval = food_eaten.base_interval(&rules);
+    if rules.terrain_slows {
+        // Every snake shares the tick, so the slowest ground sets it.
+        let cost = terrain
+            .iter()
+            .filter(|(pos, _)| heads.iter().any(|head| head == *pos))
+            .map(|(_, terrain)| terrain.cost())
+            .max()
+            .unwrap_or(1);
+        interval *= cost as f32;
+    }
+    if effects.is_active(PowerUpKind::SpeedBoost) {
+        interval *= SPEED_BOOST_FACTOR;
+    }
+    if effects.is_active(PowerUpKind::SlowMotion) {
+        interval *= SLOW_MOTION_FACTOR;
+    }
+    if sprint.active {
+        interval *= SPRINT_FACTOR;
+    }
+    timer.set_interval(interval);
+}
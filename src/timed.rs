@@ -0,0 +1,69 @@
+//! Timed challenge: score as much as possible before the clock runs out.
+
+use bevy::prelude::*;
+
+use crate::highscore::HighScores;
+use crate::replay::Playback;
+use crate::rules::{GameMode, GameRules};
+use crate::score::Score;
+use crate::state::{GameState, GameStateEvent};
+use crate::tick::{MoveTimer, MovementTick};
+use crate::{player_length, snake_movement, Player, SnakeSegments};
+
+/// Length of a timed challenge in seconds.
+pub const CHALLENGE_DURATION: f32 = 120.0;
+
+/// Time left in the current challenge. Only ticks while the snake is
+/// actually playing, so pausing or respawning stops the clock.
+pub struct ChallengeClock(pub Timer);
+
+impl Default for ChallengeClock {
+    fn default() -> Self {
+        Self(Timer::from_seconds(CHALLENGE_DURATION, false))
+    }
+}
+
+impl ChallengeClock {
+    pub fn seconds_left(&self) -> f32 {
+        (self.0.duration() - self.0.elapsed()).as_secs_f32()
+    }
+}
+
+pub struct TimedPlugin;
+
+impl Plugin for TimedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChallengeClock>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_clock))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(run_clock.after(snake_movement)),
+            );
+    }
+}
+
+fn reset_clock(mut clock: ResMut<ChallengeClock>) {
+    *clock = ChallengeClock::default();
+}
+
+fn run_clock(
+    move_timer: Res<MoveTimer>,
+    rules: Res<GameRules>,
+    mut clock: ResMut<ChallengeClock>,
+    score: Res<Score>,
+    players: Query<(&Player, &SnakeSegments)>,
+    mut high_scores: ResMut<HighScores>,
+    playback: Res<Playback>,
+    mut state_writer: EventWriter<GameStateEvent>,
+) {
+    if rules.mode != GameMode::Timed {
+        return;
+    }
+    if clock.0.tick(move_timer.step()).just_finished() {
+        if !playback.is_active() {
+            high_scores.record(GameMode::Timed, score.0, player_length(&players));
+        }
+        state_writer.send(GameStateEvent::EndRun);
+    }
+}
@@ -0,0 +1,130 @@
+//! Touchscreen input. Swiping steers player one's snake, and a swipe can
+//! carry on in a new direction without lifting the finger. A tap pauses or
+//! resumes a run and confirms on the other screens.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::gameover::ResultsButton;
+use crate::input_map::{confirm_key, Controls, InputMap};
+use crate::rules::GameRules;
+use crate::state::{GameState, GameStateEvent};
+use crate::terrain::Terrain;
+use crate::{on_ice, snake_movement_input, Direction, Player, Position, SnakeHead};
+
+/// How far a finger has to move, in logical pixels, to count as a swipe.
+const SWIPE_DISTANCE: f32 = 30.0;
+/// Bevy flips touch positions to count up from the bottom of the screen on
+/// mobile only, everywhere else they count down from the top.
+const TOUCH_Y_UP: bool = cfg!(any(target_os = "android", target_os = "ios"));
+
+struct SwipeEvent(Direction);
+
+/// Where each finger on the screen last swiped from, and whether it has
+/// swiped at all, which makes lifting it no longer a tap.
+#[derive(Default)]
+struct Fingers(HashMap<u64, (Vec2, bool)>);
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Fingers>()
+            .add_event::<SwipeEvent>()
+            .add_system_to_stage(CoreStage::PreUpdate, gestures.after(InputSystem))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(swipe_steering.before(snake_movement_input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::BonusRound)
+                    .with_system(swipe_steering.before(snake_movement_input)),
+            );
+    }
+}
+
+fn swipe_direction(distance: Vec2) -> Direction {
+    let y = if TOUCH_Y_UP { distance.y } else { -distance.y };
+    if distance.x.abs() > y.abs() {
+        if distance.x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else if y > 0.0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    }
+}
+
+/// Runs right after touches are read so a tap can stand in for a key press
+/// before anything looks at the keyboard.
+fn gestures(
+    touches: Res<Touches>,
+    state: Res<State<GameState>>,
+    input_map: Res<InputMap>,
+    results_buttons: Query<(), With<ResultsButton>>,
+    mut fingers: ResMut<Fingers>,
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut swipe_writer: EventWriter<SwipeEvent>,
+    mut state_writer: EventWriter<GameStateEvent>,
+) {
+    for touch in touches.iter_just_pressed() {
+        fingers
+            .0
+            .insert(touch.id(), (touch.start_position(), false));
+    }
+    for touch in touches.iter() {
+        if let Some((from, swiped)) = fingers.0.get_mut(&touch.id()) {
+            let distance = touch.position() - *from;
+            if distance.length() >= SWIPE_DISTANCE {
+                swipe_writer.send(SwipeEvent(swipe_direction(distance)));
+                *from = touch.position();
+                *swiped = true;
+            }
+        }
+    }
+    for touch in touches.iter_just_cancelled() {
+        fingers.0.remove(&touch.id());
+    }
+    for touch in touches.iter_just_released() {
+        let (from, swiped) = match fingers.0.remove(&touch.id()) {
+            Some(finger) => finger,
+            None => continue,
+        };
+        // A quick flick can move and lift in the same frame.
+        let distance = touch.position() - from;
+        if distance.length() >= SWIPE_DISTANCE {
+            swipe_writer.send(SwipeEvent(swipe_direction(distance)));
+        } else if !swiped {
+            match state.current() {
+                GameState::Playing => state_writer.send(GameStateEvent::Pause),
+                GameState::Paused => state_writer.send(GameStateEvent::Resume),
+                state => {
+                    if let Some(key) = confirm_key(*state, &input_map, !results_buttons.is_empty())
+                    {
+                        kbd_input.press(key);
+                        kbd_input.release(key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn swipe_steering(
+    rules: Res<GameRules>,
+    mut swipe_reader: EventReader<SwipeEvent>,
+    mut heads: Query<(&Player, &mut SnakeHead, &Position), With<Controls>>,
+    terrain: Query<(&Position, &Terrain)>,
+) {
+    for SwipeEvent(direction) in swipe_reader.iter() {
+        for (_, mut head, pos) in heads.iter_mut().filter(|(player, ..)| player.0 == 1) {
+            if !on_ice(&terrain, *pos) {
+                head.queue_turn(rules.mirror.apply(*direction));
+            }
+        }
+    }
+}
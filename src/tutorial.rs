@@ -0,0 +1,211 @@
+//! A guided first run. Prompts walk through steering, eating and staying
+//! alive, with the snake held still at each prompt until the player does what
+//! it asks. Finishing or skipping it is remembered in the settings so it only
+//! ever runs once.
+
+use bevy::prelude::*;
+
+use crate::demo::Demo;
+use crate::input_map::{key_names, InputMap};
+use crate::replay::Playback;
+use crate::rules::GameRules;
+use crate::settings::Settings;
+use crate::state::{take_just_pressed, GameState};
+use crate::tick::MoveTimer;
+use crate::ui;
+use crate::{Controls, FoodEatenEvent, Player};
+
+const PROMPT_FONT_SIZE: f32 = 28.0;
+const SKIP_KEY: KeyCode = KeyCode::Tab;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Steer,
+    Eat,
+    Avoid,
+    Pause,
+}
+
+impl Step {
+    /// The tutorial is only played alone, so it names the single player keys.
+    fn prompt(self, input_map: &InputMap) -> String {
+        let keys = &input_map.single_player;
+        match self {
+            Step::Steer => format!(
+                "Steer the snake with {}, {}, {} and {}. Press one to start moving.",
+                key_names(&keys.up),
+                key_names(&keys.left),
+                key_names(&keys.right),
+                key_names(&keys.down)
+            ),
+            Step::Eat => "Eat the orange food to grow and score points.".to_string(),
+            Step::Avoid => {
+                "Running into a wall or your own body costs a life.\nPress Enter to go on."
+                    .to_string()
+            }
+            Step::Pause => format!(
+                "Press {} any time to pause.\nPress Enter to play on.",
+                key_names(&input_map.pause)
+            ),
+        }
+    }
+
+    fn next(self) -> Option<Self> {
+        match self {
+            Step::Steer => Some(Step::Eat),
+            Step::Eat => Some(Step::Avoid),
+            Step::Avoid => Some(Step::Pause),
+            Step::Pause => None,
+        }
+    }
+
+    /// Whether the snake waits while the prompt is up.
+    fn holds(self) -> bool {
+        self != Step::Eat
+    }
+}
+
+/// The step being shown while the tutorial runs.
+#[derive(Default)]
+struct Tutorial(Option<Step>);
+
+#[derive(Component)]
+struct TutorialPrompt;
+
+#[derive(Component)]
+struct PromptText;
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tutorial>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_tutorial))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(tutorial_input))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(end_tutorial))
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_tutorial));
+    }
+}
+
+fn start_tutorial(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut tutorial: ResMut<Tutorial>,
+    mut move_timer: ResMut<MoveTimer>,
+    settings: Res<Settings>,
+    input_map: Res<InputMap>,
+    rules: Res<GameRules>,
+    demo: Res<Demo>,
+    playback: Res<Playback>,
+    prompts: Query<(), With<TutorialPrompt>>,
+) {
+    if settings.tutorial_done || demo.is_active() || playback.is_active() || rules.mode.two_player()
+    {
+        return;
+    }
+    tutorial.0 = Some(Step::Steer);
+    move_timer.speed = 0.0;
+    if !prompts.is_empty() {
+        return;
+    }
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(48.0),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(TutorialPrompt)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        prompt_text(Step::Steer, &input_map),
+                        ui::text_style(&asset_server, PROMPT_FONT_SIZE),
+                        TextAlignment {
+                            horizontal: HorizontalAlign::Center,
+                            ..default()
+                        },
+                    ),
+                    ..default()
+                })
+                .insert(PromptText);
+        });
+}
+
+fn prompt_text(step: Step, input_map: &InputMap) -> String {
+    format!("{}\n(Tab skips the tutorial)", step.prompt(input_map))
+}
+
+fn tutorial_input(
+    mut commands: Commands,
+    mut kbd_input: ResMut<Input<KeyCode>>,
+    mut tutorial: ResMut<Tutorial>,
+    mut move_timer: ResMut<MoveTimer>,
+    mut settings: ResMut<Settings>,
+    input_map: Res<InputMap>,
+    mut eaten_reader: EventReader<FoodEatenEvent>,
+    controls: Query<(&Player, &Controls)>,
+    prompts: Query<Entity, With<TutorialPrompt>>,
+    mut texts: Query<&mut Text, With<PromptText>>,
+) {
+    let step = match tutorial.0 {
+        Some(step) => step,
+        None => return,
+    };
+    let completed = match step {
+        Step::Steer => controls
+            .iter()
+            .filter(|(player, _)| player.0 == 1)
+            .any(|(_, controls)| kbd_input.any_just_pressed(controls.turn_keys())),
+        Step::Eat => eaten_reader.iter().next().is_some(),
+        Step::Avoid | Step::Pause => take_just_pressed(&mut kbd_input, [KeyCode::Return]),
+    };
+    let next = if take_just_pressed(&mut kbd_input, [SKIP_KEY]) {
+        None
+    } else if completed {
+        step.next()
+    } else {
+        return;
+    };
+    tutorial.0 = next;
+    match next {
+        Some(next) => {
+            move_timer.speed = if next.holds() { 0.0 } else { 1.0 };
+            for mut text in texts.iter_mut() {
+                text.sections[0].value = prompt_text(next, &input_map);
+            }
+        }
+        None => {
+            move_timer.speed = 1.0;
+            settings.tutorial_done = true;
+            settings.save();
+            for prompt in prompts.iter() {
+                commands.entity(prompt).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Leaving the run partway through puts the tutorial off until the next one.
+fn end_tutorial(
+    mut commands: Commands,
+    mut tutorial: ResMut<Tutorial>,
+    mut move_timer: ResMut<MoveTimer>,
+    prompts: Query<Entity, With<TutorialPrompt>>,
+) {
+    if tutorial.0.take().is_some() {
+        move_timer.speed = 1.0;
+    }
+    for prompt in prompts.iter() {
+        commands.entity(prompt).despawn_recursive();
+    }
+}
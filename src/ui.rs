@@ -0,0 +1,43 @@
+//! Shared styling helpers for the bevy UI screens.
+
+use bevy::prelude::*;
+
+pub const FONT_PATH: &str = "fonts/FiraSans-Bold.ttf";
+pub const TEXT_COLOR: Color = Color::WHITE;
+pub const OVERLAY_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+pub fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size,
+        color: TEXT_COLOR,
+    }
+}
+
+/// Full screen translucent node that stacks its children vertically in the
+/// center of the window.
+pub fn overlay_node() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::ColumnReverse,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: UiColor(OVERLAY_COLOR),
+        ..default()
+    }
+}
+
+pub fn text_line(text: impl Into<String>, style: TextStyle) -> TextBundle {
+    TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(6.0)),
+            ..default()
+        },
+        text: Text::with_section(text, style, TextAlignment::default()),
+        ..default()
+    }
+}
@@ -0,0 +1,74 @@
+//! Local two-player modes: a second snake steered with WASD shares the
+//! board. In versus the first snake to die loses, in co-op they share a score.
+
+use bevy::prelude::*;
+
+use crate::input_map::InputMap;
+use crate::level::CurrentLevel;
+use crate::rules::{GameMode, GameRules};
+use crate::state::GameState;
+use crate::tick::MovementTick;
+use crate::{snake_shrink, spawn_snake, GameOverEvent, Player};
+
+const PLAYER_TWO_COLOR: Color = Color::rgb(0.3, 0.6, 1.0);
+
+/// How the last versus run ended.
+#[derive(Default)]
+pub struct VersusOutcome {
+    /// Number of the surviving player, `None` when both died at once.
+    pub winner: Option<u8>,
+}
+
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VersusOutcome>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(spawn_player_two))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(record_outcome.after(snake_shrink)),
+            );
+    }
+}
+
+fn spawn_player_two(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    input_map: Res<InputMap>,
+    current: Res<CurrentLevel>,
+) {
+    if !rules.mode.two_player() {
+        return;
+    }
+    let (start, direction) = current.level.second_start();
+    let snake = spawn_snake(&mut commands, start, direction, PLAYER_TWO_COLOR);
+    commands
+        .entity(snake)
+        .insert(Player(2))
+        .insert(input_map.player(2, true).clone());
+}
+
+fn record_outcome(
+    rules: Res<GameRules>,
+    mut reader: EventReader<GameOverEvent>,
+    players: Query<&Player>,
+    mut outcome: ResMut<VersusOutcome>,
+) {
+    if rules.mode != GameMode::Versus {
+        return;
+    }
+    let losers: Vec<u8> = reader
+        .iter()
+        .filter_map(|event| players.get(event.snake).ok())
+        .map(|player| player.0)
+        .collect();
+    if losers.is_empty() {
+        return;
+    }
+    outcome.winner = players
+        .iter()
+        .map(|player| player.0)
+        .find(|number| !losers.contains(number));
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+use crate::{Position, Size};
+
+const WALL_COLOR: Color = Color::BLACK;
+
+#[derive(Component)]
+pub struct Wall;
+
+pub fn spawn_wall(commands: &mut Commands, position: Position) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: WALL_COLOR,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Wall)
+        .insert(position)
+        .insert(Size::square(1.0))
+        .id()
+}
@@ -0,0 +1,221 @@
+//! Random world events. With `GameRules::world_events` on, every so often
+//! something happens to the board for a few seconds and is announced on the
+//! HUD: a food frenzy covers it in short-lived food, an earthquake shuffles
+//! the food around, or the lights go out. When the next one comes is drawn
+//! from the run's seed and counted in game time, so replays see the same
+//! events.
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::food::{free_cells, random_free_cell, spawn_food, Expiring, Food, FoodKind, Occupying};
+use crate::rng::{seed_run, GameRng};
+use crate::rules::GameRules;
+use crate::state::GameState;
+use crate::tick::{MoveTimer, MovementTick};
+use crate::{snake_movement, Grid, Position};
+
+/// Game time between one event ending and the next starting, in seconds.
+const QUIET_TIME: std::ops::Range<f32> = 20.0..40.0;
+const FRENZY_FOOD: usize = 10;
+const LIGHTS_OUT_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.97);
+/// Above everything else on the board, fog included.
+const LIGHTS_OUT_Z: f32 = 0.95;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WorldEvent {
+    FoodFrenzy,
+    Earthquake,
+    LightsOut,
+}
+
+impl WorldEvent {
+    const ALL: [WorldEvent; 3] = [
+        WorldEvent::FoodFrenzy,
+        WorldEvent::Earthquake,
+        WorldEvent::LightsOut,
+    ];
+
+    pub fn announcement(self) -> &'static str {
+        match self {
+            WorldEvent::FoodFrenzy => "Food frenzy!",
+            WorldEvent::Earthquake => "Earthquake shuffles food",
+            WorldEvent::LightsOut => "Lights out for 5s",
+        }
+    }
+
+    /// Seconds the event lasts. An earthquake is over at once, this is just
+    /// how long it's announced for.
+    fn duration(self) -> f32 {
+        match self {
+            WorldEvent::FoodFrenzy => 8.0,
+            WorldEvent::Earthquake => 3.0,
+            WorldEvent::LightsOut => 5.0,
+        }
+    }
+}
+
+/// When the next event comes, and the one going on now.
+pub struct EventScheduler {
+    next: Timer,
+    active: Option<(WorldEvent, Timer)>,
+}
+
+impl Default for EventScheduler {
+    fn default() -> Self {
+        Self {
+            next: Timer::from_seconds(QUIET_TIME.end, false),
+            active: None,
+        }
+    }
+}
+
+impl EventScheduler {
+    pub fn active(&self) -> Option<WorldEvent> {
+        self.active.as_ref().map(|(event, _)| *event)
+    }
+}
+
+/// Covers the board while the lights are out. It doesn't get a `Position`
+/// of its own, so it isn't mistaken for something on a cell.
+#[derive(Component)]
+struct Darkness;
+
+pub struct WorldEventsPlugin;
+
+impl Plugin for WorldEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventScheduler>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(reset_scheduler.after(seed_run)),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(MovementTick)
+                    .with_system(run_events.after(snake_movement)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(place_darkness))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(end_events))
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_events));
+    }
+}
+
+fn reset_scheduler(
+    mut commands: Commands,
+    mut scheduler: ResMut<EventScheduler>,
+    mut rng: ResMut<GameRng>,
+    darkness: Query<Entity, With<Darkness>>,
+) {
+    for entt in darkness.iter() {
+        commands.entity(entt).despawn();
+    }
+    *scheduler = EventScheduler {
+        next: Timer::from_seconds(rng.world_events().gen_range(QUIET_TIME), false),
+        active: None,
+    };
+}
+
+fn run_events(
+    mut commands: Commands,
+    move_timer: Res<MoveTimer>,
+    rules: Res<GameRules>,
+    grid: Res<Grid>,
+    mut scheduler: ResMut<EventScheduler>,
+    mut rng: ResMut<GameRng>,
+    darkness: Query<Entity, With<Darkness>>,
+    mut food: Query<&mut Position, With<Food>>,
+    occupied: Query<&Position, (Occupying, Without<Food>)>,
+) {
+    if !rules.world_events {
+        return;
+    }
+    if let Some((event, timer)) = scheduler.active.as_mut() {
+        if !timer.tick(move_timer.step()).finished() {
+            return;
+        }
+        if *event == WorldEvent::LightsOut {
+            for entt in darkness.iter() {
+                commands.entity(entt).despawn();
+            }
+        }
+        scheduler.active = None;
+        scheduler.next = Timer::from_seconds(rng.world_events().gen_range(QUIET_TIME), false);
+        return;
+    }
+    if !scheduler.next.tick(move_timer.step()).finished() {
+        return;
+    }
+    let event = *WorldEvent::ALL.choose(rng.world_events()).unwrap();
+    let mut free = free_cells(*grid, occupied.iter().chain(food.iter()));
+    match event {
+        WorldEvent::FoodFrenzy => {
+            for _ in 0..FRENZY_FOOD {
+                let position = match random_free_cell(rng.world_events(), *grid, &free) {
+                    Some(position) => position,
+                    None => break,
+                };
+                free.retain(|pos| *pos != position);
+                let frenzy = spawn_food(&mut commands, position, FoodKind::Normal);
+                commands
+                    .entity(frenzy)
+                    .insert(Expiring(Timer::from_seconds(event.duration(), false)));
+            }
+        }
+        WorldEvent::Earthquake => {
+            for mut pos in food.iter_mut() {
+                if let Some(position) = random_free_cell(rng.world_events(), *grid, &free) {
+                    free.retain(|free_pos| *free_pos != position);
+                    free.push(*pos);
+                    *pos = position;
+                }
+            }
+        }
+        WorldEvent::LightsOut => {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: LIGHTS_OUT_COLOR,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, LIGHTS_OUT_Z),
+                    ..default()
+                })
+                .insert(Darkness);
+        }
+    }
+    scheduler.active = Some((event, Timer::from_seconds(event.duration(), false)));
+}
+
+/// Keeps the darkness over the whole board as the window or board changes
+/// size.
+fn place_darkness(
+    windows: Res<Windows>,
+    grid: Res<Grid>,
+    mut darkness: Query<&mut Transform, With<Darkness>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let cell_size = grid.cell_size(window);
+    for mut transform in darkness.iter_mut() {
+        transform.scale = Vec3::new(
+            grid.width as f32 * cell_size,
+            grid.height as f32 * cell_size,
+            1.0,
+        );
+    }
+}
+
+fn end_events(
+    mut commands: Commands,
+    mut scheduler: ResMut<EventScheduler>,
+    darkness: Query<Entity, With<Darkness>>,
+) {
+    for entt in darkness.iter() {
+        commands.entity(entt).despawn();
+    }
+    scheduler.active = None;
+}